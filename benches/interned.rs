@@ -0,0 +1,83 @@
+//! Reports the heap memory held by BYML documents parsed normally and with
+//! interned strings.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use roead::byml::{Byml, StringPool};
+
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Run `f` and return its result with the heap bytes it left allocated.
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = LIVE.load(Ordering::Relaxed);
+    let value = f();
+    (value, LIVE.load(Ordering::Relaxed) - before)
+}
+
+fn report(name: &str, files: &[Vec<u8>]) {
+    let (owned, owned_size) = measure(|| {
+        files
+            .iter()
+            .map(|data| Byml::from_binary(data).unwrap())
+            .collect::<Vec<_>>()
+    });
+    let (shared, shared_size) = measure(|| {
+        let mut pool = StringPool::new();
+        let docs = files
+            .iter()
+            .map(|data| Byml::from_binary_interned(data, &mut pool).unwrap())
+            .collect::<Vec<_>>();
+        (docs, pool)
+    });
+    assert!(owned.iter().zip(&shared.0).all(|(a, b)| *a == b.to_byml()));
+    println!(
+        "{name} ({} files): Byml {} KiB, SharedByml {} KiB ({:.1}% less)",
+        files.len(),
+        owned_size / 1024,
+        shared_size / 1024,
+        100.0 * (1.0 - shared_size as f64 / owned_size as f64)
+    );
+}
+
+fn main() {
+    report("ActorInfo.product.byml", &[std::fs::read(
+        "test/byml/ActorInfo.product.byml",
+    )
+    .unwrap()]);
+    let map_units: Vec<_> = std::fs::read_dir("test/byml")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| {
+                    name.ends_with("_Static.byml")
+                        || name.ends_with("_Dynamic.byml")
+                        || name.ends_with(".bcett.byml")
+                })
+                .unwrap_or(false)
+        })
+        .map(|path| std::fs::read(path).unwrap())
+        .filter(|data| Byml::from_binary(data).is_ok())
+        .collect();
+    report("Map units", &map_units);
+}
@@ -0,0 +1,57 @@
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use roead::{aamp::ParameterIO, byml::Byml};
+
+fn aamp_corpus() -> Vec<Vec<u8>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![std::path::PathBuf::from("test/aamp")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().map(|e| e != "yml").unwrap_or(true) {
+                files.push(std::fs::read(path).unwrap());
+            }
+        }
+    }
+    files
+}
+
+fn byml(c: &mut Criterion) {
+    let data = std::fs::read("test/byml/ActorInfo.product.byml").unwrap();
+    let mut group = c.benchmark_group("ActorInfo.product.byml");
+    group.bench_function("slice", |b| {
+        b.iter_with_large_drop(|| Byml::from_binary(&data).unwrap())
+    });
+    group.bench_function("reader", |b| {
+        b.iter_with_large_drop(|| Byml::read(Cursor::new(&data)).unwrap())
+    });
+    group.finish();
+}
+
+fn aamp(c: &mut Criterion) {
+    let files = aamp_corpus();
+    let mut group = c.benchmark_group("AAMP corpus");
+    group.bench_function("slice", |b| {
+        b.iter_with_large_drop(|| {
+            files
+                .iter()
+                .map(|data| ParameterIO::from_binary(data).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("reader", |b| {
+        b.iter_with_large_drop(|| {
+            files
+                .iter()
+                .map(|data| ParameterIO::read(Cursor::new(data)).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, byml, aamp);
+criterion_main!(benches);
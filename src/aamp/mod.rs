@@ -35,6 +35,7 @@ mod names;
 mod parser;
 #[cfg(feature = "yaml")]
 mod text;
+mod visit;
 mod writer;
 use binrw::binrw;
 use indexmap::IndexMap;
@@ -43,6 +44,7 @@ use num_traits::AsPrimitive;
 #[cfg(feature = "with-serde")]
 use serde::{Deserialize, Serialize};
 use smartstring::alias::String;
+pub use visit::{Visit, VisitMut};
 
 use crate::{types::*, util::u24, Error, Result};
 
@@ -52,22 +54,7 @@ type ParameterStructureMap<V> =
 /// CRC hash function matching that used in BOTW.
 #[inline]
 pub const fn hash_name(name: &str) -> u32 {
-    let mut crc = 0xFFFFFFFF;
-    let mut i = 0;
-    while i < name.len() {
-        crc ^= name.as_bytes()[i] as u32;
-        let mut j = 0;
-        while j < 8 {
-            if crc & 1 == 1 {
-                crc = (crc >> 1) ^ 0xEDB88320;
-            } else {
-                crc >>= 1;
-            }
-            j += 1;
-        }
-        i += 1;
-    }
-    !crc
+    crate::util::crc32(name.as_bytes())
 }
 
 /// A convenient macro for hashing AAMP names. This can help ensure they are
@@ -15,20 +15,39 @@ impl ParameterIO {
     ///
     /// **Note**: If and only if the `yaz0` feature is enabled, this function
     /// automatically decompresses the data when necessary.
+    ///
+    /// This uses a parser specialised for in-memory data, which is faster than
+    /// [`ParameterIO::read`] with a cursor.
     pub fn from_binary(data: impl AsRef<[u8]>) -> Result<ParameterIO> {
         #[cfg(feature = "yaz0")]
         {
             if data.as_ref().starts_with(b"Yaz0") {
-                return Parser::new(std::io::Cursor::new(crate::yaz0::decompress(
-                    data.as_ref(),
-                )?))?
-                .parse();
+                return SliceParser::new(&crate::yaz0::decompress(data.as_ref())?)?.parse();
             }
         }
-        Parser::new(std::io::Cursor::new(data.as_ref()))?.parse()
+        SliceParser::new(data.as_ref())?.parse()
     }
 }
 
+fn check_header(header: &ResHeader) -> Result<()> {
+    if header.version != 2 {
+        return Err(Error::InvalidData(
+            "Only version 2 parameter archives are supported",
+        ));
+    }
+    if header.flags & 1 << 0 != 1 << 0 {
+        return Err(Error::InvalidData(
+            "Only little endian parameter archives are supported",
+        ));
+    }
+    if header.flags & 1 << 1 != 1 << 1 {
+        return Err(Error::InvalidData(
+            "Only UTF-8 parameter archives are supported",
+        ));
+    }
+    Ok(())
+}
+
 struct Parser<R: Read + Seek> {
     reader: R,
     header: ResHeader,
@@ -41,21 +60,7 @@ impl<R: Read + Seek> Parser<R> {
             return Err(Error::InvalidData("Incomplete parameter archive"));
         }
         let header = ResHeader::read(&mut reader)?;
-        if header.version != 2 {
-            return Err(Error::InvalidData(
-                "Only version 2 parameter archives are supported",
-            ));
-        }
-        if header.flags & 1 << 0 != 1 << 0 {
-            return Err(Error::InvalidData(
-                "Only little endian parameter archives are supported",
-            ));
-        }
-        if header.flags & 1 << 1 != 1 << 1 {
-            return Err(Error::InvalidData(
-                "Only UTF-8 parameter archives are supported",
-            ));
-        }
+        check_header(&header)?;
         Ok(Self {
             reader,
             header,
@@ -162,7 +167,7 @@ impl<R: Read + Seek> Parser<R> {
             Type::StringRef => Parameter::StringRef(self.read_null_string()?),
             Type::BufferInt => Parameter::BufferInt(self.read_buffer::<i32>(data_offset)?),
             Type::BufferU32 => Parameter::BufferU32(self.read_buffer::<u32>(data_offset)?),
-            Type::BufferF32 => Parameter::BufferF32(self.read_float_buffer(offset)?),
+            Type::BufferF32 => Parameter::BufferF32(self.read_float_buffer(data_offset)?),
             Type::BufferBinary => Parameter::BufferBinary(self.read_buffer::<u8>(data_offset)?),
         };
         Ok((info.name, value))
@@ -195,6 +200,177 @@ impl<R: Read + Seek> Parser<R> {
     }
 }
 
+const OFFSET_OVERFLOW: Error = Error::InvalidData("Offset overflow in parameter archive");
+
+#[inline]
+fn add_offset(offset: u32, rel: u32) -> Result<u32> {
+    offset.checked_add(rel).ok_or(OFFSET_OVERFLOW)
+}
+
+#[inline]
+fn sub_offset(offset: u32, rel: u32) -> Result<u32> {
+    offset.checked_sub(rel).ok_or(OFFSET_OVERFLOW)
+}
+
+/// Parser for parameter archives already in memory. Reads directly from the
+/// slice instead of seeking a reader for every structure.
+struct SliceParser<'a> {
+    data:   &'a [u8],
+    header: ResHeader,
+}
+
+impl<'a> SliceParser<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        if data.len() < 0x30 {
+            return Err(Error::InvalidData("Incomplete parameter archive"));
+        }
+        let header = ResHeader::read(&mut std::io::Cursor::new(data))?;
+        check_header(&header)?;
+        Ok(Self { data, header })
+    }
+
+    fn parse(&self) -> Result<ParameterIO> {
+        let (root_name, param_root) = self.parse_list(add_offset(self.header.pio_offset, 0x30)?)?;
+        if root_name != ROOT_KEY {
+            Err(Error::InvalidData(
+                "No param root found in parameter archive",
+            ))
+        } else {
+            Ok(ParameterIO {
+                version: self.header.pio_version,
+                data_type: self.read_null_string(0x30)?.into(),
+                param_root,
+            })
+        }
+    }
+
+    #[inline]
+    fn slice(&self, offset: u32, len: usize) -> Result<&'a [u8]> {
+        let offset = offset as usize;
+        let end = offset.checked_add(len).ok_or(OFFSET_OVERFLOW)?;
+        self.data
+            .get(offset..end)
+            .ok_or(Error::InsufficientData(self.data.len(), end))
+    }
+
+    #[inline]
+    fn read_u32(&self, offset: u32) -> Result<u32> {
+        Ok(u32::from_le_bytes(
+            self.slice(offset, 4)?
+                .try_into()
+                .expect("Slice should be 4 bytes"),
+        ))
+    }
+
+    #[inline]
+    fn read<T: for<'b> BinRead<Args<'b> = ()>>(&self, offset: u32) -> Result<T> {
+        let data = self
+            .data
+            .get(offset as usize..)
+            .ok_or(Error::InsufficientData(self.data.len(), offset as usize))?;
+        Ok(T::read_le(&mut std::io::Cursor::new(data))?)
+    }
+
+    fn read_null_string(&self, offset: u32) -> Result<&'a str> {
+        let rest = self
+            .data
+            .get(offset as usize..)
+            .ok_or(Error::InsufficientData(self.data.len(), offset as usize))?;
+        let len = rest.iter().position(|c| *c == 0).ok_or(Error::InvalidData(
+            "Unterminated string in parameter archive",
+        ))?;
+        Ok(std::str::from_utf8(&rest[..len])?)
+    }
+
+    fn read_words<T>(&self, offset: u32, from_bytes: fn([u8; 4]) -> T) -> Result<Vec<T>> {
+        let size = self.read_u32(sub_offset(offset, 4)?)? as usize;
+        Ok(self
+            .slice(offset, size.checked_mul(4).ok_or(OFFSET_OVERFLOW)?)?
+            .chunks_exact(4)
+            .map(|c| from_bytes([c[0], c[1], c[2], c[3]]))
+            .collect())
+    }
+
+    fn parse_parameter(&self, offset: u32) -> Result<(Name, Parameter)> {
+        let info: ResParameter = self.read(offset)?;
+        let data_offset = add_offset(offset, info.data_rel_offset.as_u32() * 4)?;
+        let value = match info.type_ {
+            Type::Bool => Parameter::Bool(self.read_u32(data_offset)? != 0),
+            Type::F32 => Parameter::F32(f32::from_bits(self.read_u32(data_offset)?)),
+            Type::Int => Parameter::I32(self.read_u32(data_offset)? as i32),
+            Type::Vec2 => Parameter::Vec2(self.read(data_offset)?),
+            Type::Vec3 => Parameter::Vec3(self.read(data_offset)?),
+            Type::Vec4 => Parameter::Vec4(self.read(data_offset)?),
+            Type::Quat => Parameter::Quat(self.read(data_offset)?),
+            Type::Color => Parameter::Color(self.read(data_offset)?),
+            Type::U32 => Parameter::U32(self.read_u32(data_offset)?),
+            Type::Curve1 => Parameter::Curve1(self.read(data_offset)?),
+            Type::Curve2 => Parameter::Curve2(self.read(data_offset)?),
+            Type::Curve3 => Parameter::Curve3(self.read(data_offset)?),
+            Type::Curve4 => Parameter::Curve4(self.read(data_offset)?),
+            Type::String32 => Parameter::String32(self.read(data_offset)?),
+            Type::String64 => Parameter::String64(self.read(data_offset)?),
+            Type::String256 => Parameter::String256(self.read(data_offset)?),
+            Type::StringRef => Parameter::StringRef(self.read_null_string(data_offset)?.into()),
+            Type::BufferInt => {
+                Parameter::BufferInt(self.read_words(data_offset, i32::from_le_bytes)?)
+            }
+            Type::BufferU32 => {
+                Parameter::BufferU32(self.read_words(data_offset, u32::from_le_bytes)?)
+            }
+            Type::BufferF32 => {
+                Parameter::BufferF32(self.read_words(data_offset, f32::from_le_bytes)?)
+            }
+            Type::BufferBinary => {
+                let size = self.read_u32(sub_offset(data_offset, 4)?)? as usize;
+                Parameter::BufferBinary(self.slice(data_offset, size)?.to_vec())
+            }
+        };
+        Ok((info.name, value))
+    }
+
+    fn parse_object(&self, offset: u32) -> Result<(Name, ParameterObject)> {
+        let info: ResParameterObj = self.read(offset)?;
+        let offset = add_offset(offset, info.params_rel_offset as u32 * 4)?;
+        let mut params = ParameterStructureMap::with_capacity_and_hasher(
+            info.param_count as usize,
+            Default::default(),
+        );
+        for i in 0..info.param_count as u32 {
+            let (name, param) = self.parse_parameter(add_offset(offset, 0x8 * i)?)?;
+            params.insert(name, param);
+        }
+        Ok((info.name, ParameterObject(params)))
+    }
+
+    fn parse_list(&self, offset: u32) -> Result<(Name, ParameterList)> {
+        let info: ResParameterList = self.read(offset)?;
+        let lists_offset = add_offset(offset, info.lists_rel_offset as u32 * 4)?;
+        let objects_offset = add_offset(offset, info.objects_rel_offset as u32 * 4)?;
+        let mut lists = ParameterStructureMap::with_capacity_and_hasher(
+            info.list_count as usize,
+            Default::default(),
+        );
+        for i in 0..info.list_count as u32 {
+            let (name, list) = self.parse_list(add_offset(lists_offset, 0xC * i)?)?;
+            lists.insert(name, list);
+        }
+        let mut objects = ParameterStructureMap::with_capacity_and_hasher(
+            info.object_count as usize,
+            Default::default(),
+        );
+        for i in 0..info.object_count as u32 {
+            let (name, object) = self.parse_object(add_offset(objects_offset, 0x8 * i)?)?;
+            objects.insert(name, object);
+        }
+        let plist = ParameterList {
+            lists:   ParameterListMap(lists),
+            objects: ParameterObjectMap(objects),
+        };
+        Ok((info.name, plist))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,7 +387,18 @@ mod tests {
         {
             println!("{}", file.display());
             let data = std::fs::read(&file).unwrap();
-            ParameterIO::from_binary(data).unwrap();
+            let pio = ParameterIO::from_binary(&data).unwrap();
+            assert_eq!(pio, ParameterIO::read(std::io::Cursor::new(&data)).unwrap());
         }
     }
+
+    #[test]
+    fn offset_overflow() {
+        let mut data = std::fs::read("test/aamp/Lizalfos.bphysics").unwrap();
+        data[0x14..0x18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            ParameterIO::from_binary(&data),
+            Err(Error::InvalidData(_))
+        ));
+    }
 }
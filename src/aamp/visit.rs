@@ -0,0 +1,251 @@
+use super::*;
+
+/// A visitor for walking a parameter list and everything in it with
+/// [`ParameterList::visit`] or [`ParameterIO::visit`].
+///
+/// Each structure is passed with its path of names from the visited list,
+/// which itself has an empty path. In each list, the objects are visited
+/// before the child lists, both in their stored order. All methods do nothing
+/// by default.
+/// ```
+/// # use roead::aamp::*;
+/// # fn doctest() -> Result<(), Box<dyn std::error::Error>> {
+/// struct CountFloats(usize);
+///
+/// impl Visit for CountFloats {
+///     fn visit_parameter(&mut self, _path: &[Name], param: &Parameter) {
+///         if let Parameter::F32(_) = param {
+///             self.0 += 1;
+///         }
+///     }
+/// }
+///
+/// let pio = ParameterIO::from_binary(std::fs::read("test/aamp/Lizalfos.bphysics")?)?;
+/// let mut counter = CountFloats(0);
+/// pio.visit(&mut counter);
+/// assert!(counter.0 > 0);
+/// # Ok(())
+/// # }
+/// ```
+pub trait Visit {
+    /// Called for each list before its contents. Return `false` to skip them.
+    fn visit_list(&mut self, _path: &[Name], _list: &ParameterList) -> bool {
+        true
+    }
+
+    /// Called for each list after its contents, if they were not skipped.
+    fn leave_list(&mut self, _path: &[Name], _list: &ParameterList) {}
+
+    /// Called for each object before its parameters. Return `false` to skip
+    /// them.
+    fn visit_object(&mut self, _path: &[Name], _object: &ParameterObject) -> bool {
+        true
+    }
+
+    /// Called for each parameter.
+    fn visit_parameter(&mut self, _path: &[Name], _param: &Parameter) {}
+}
+
+/// A visitor for modifying a parameter list and everything in it with
+/// [`ParameterList::visit_mut`] or [`ParameterIO::visit_mut`].
+///
+/// This works like [`Visit`], but each method may change the structure it is
+/// given. If a list or object is changed before its contents are visited, the
+/// new contents are visited.
+/// ```
+/// # use roead::aamp::*;
+/// # fn doctest() -> Result<(), Box<dyn std::error::Error>> {
+/// struct Round;
+///
+/// impl VisitMut for Round {
+///     fn visit_parameter(&mut self, _path: &[Name], param: &mut Parameter) {
+///         if let Parameter::F32(f) = param {
+///             *f = f.round();
+///         }
+///     }
+/// }
+///
+/// let mut pio = ParameterIO::from_binary(std::fs::read("test/aamp/Lizalfos.bphysics")?)?;
+/// pio.visit_mut(&mut Round);
+/// # Ok(())
+/// # }
+/// ```
+pub trait VisitMut {
+    /// Called for each list before its contents. Return `false` to skip them.
+    fn visit_list(&mut self, _path: &[Name], _list: &mut ParameterList) -> bool {
+        true
+    }
+
+    /// Called for each list after its contents, if they were not skipped.
+    fn leave_list(&mut self, _path: &[Name], _list: &mut ParameterList) {}
+
+    /// Called for each object before its parameters. Return `false` to skip
+    /// them.
+    fn visit_object(&mut self, _path: &[Name], _object: &mut ParameterObject) -> bool {
+        true
+    }
+
+    /// Called for each parameter.
+    fn visit_parameter(&mut self, _path: &[Name], _param: &mut Parameter) {}
+}
+
+fn walk_list<V: Visit + ?Sized>(list: &ParameterList, path: &mut Vec<Name>, visitor: &mut V) {
+    if !visitor.visit_list(path, list) {
+        return;
+    }
+    for (name, object) in list.objects.iter() {
+        path.push(*name);
+        if visitor.visit_object(path, object) {
+            for (name, param) in object.iter() {
+                path.push(*name);
+                visitor.visit_parameter(path, param);
+                path.pop();
+            }
+        }
+        path.pop();
+    }
+    for (name, child) in list.lists.iter() {
+        path.push(*name);
+        walk_list(child, path, visitor);
+        path.pop();
+    }
+    visitor.leave_list(path, list);
+}
+
+fn walk_list_mut<V: VisitMut + ?Sized>(
+    list: &mut ParameterList,
+    path: &mut Vec<Name>,
+    visitor: &mut V,
+) {
+    if !visitor.visit_list(path, list) {
+        return;
+    }
+    for (name, object) in list.objects.iter_mut() {
+        path.push(*name);
+        if visitor.visit_object(path, object) {
+            for (name, param) in object.iter_mut() {
+                path.push(*name);
+                visitor.visit_parameter(path, param);
+                path.pop();
+            }
+        }
+        path.pop();
+    }
+    for (name, child) in list.lists.iter_mut() {
+        path.push(*name);
+        walk_list_mut(child, path, visitor);
+        path.pop();
+    }
+    visitor.leave_list(path, list);
+}
+
+impl ParameterList {
+    /// Walk this list and everything in it. See [`Visit`].
+    pub fn visit<V: Visit + ?Sized>(&self, visitor: &mut V) {
+        walk_list(self, &mut Vec::new(), visitor);
+    }
+
+    /// Walk this list and everything in it mutably. See [`VisitMut`].
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        walk_list_mut(self, &mut Vec::new(), visitor);
+    }
+}
+
+impl ParameterIO {
+    /// Walk the root list and everything in it. Paths start below
+    /// `param_root`. See [`Visit`].
+    pub fn visit<V: Visit + ?Sized>(&self, visitor: &mut V) {
+        self.param_root.visit(visitor);
+    }
+
+    /// Walk the root list and everything in it mutably. Paths start below
+    /// `param_root`. See [`VisitMut`].
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        self.param_root.visit_mut(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Paths {
+        lists:  Vec<Vec<Name>>,
+        left:   usize,
+        params: Vec<Vec<Name>>,
+    }
+
+    impl Visit for Paths {
+        fn visit_list(&mut self, path: &[Name], _list: &ParameterList) -> bool {
+            self.lists.push(path.to_vec());
+            true
+        }
+
+        fn leave_list(&mut self, _path: &[Name], _list: &ParameterList) {
+            self.left += 1;
+        }
+
+        fn visit_object(&mut self, path: &[Name], _object: &ParameterObject) -> bool {
+            path.last() != Some(&Name::from_str("Skipped"))
+        }
+
+        fn visit_parameter(&mut self, path: &[Name], _param: &Parameter) {
+            self.params.push(path.to_vec());
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let object = ParameterObject::new().with_parameter("Value", Parameter::I32(1));
+        let pio = ParameterIO::new()
+            .with_object("Root", object.clone())
+            .with_object("Skipped", object.clone())
+            .with_list("Child", ParameterList::new().with_object("Obj", object));
+        let mut paths = Paths::default();
+        pio.visit(&mut paths);
+        let name = Name::from_str;
+        assert_eq!(paths.lists, vec![vec![], vec![name("Child")]]);
+        assert_eq!(paths.left, 2);
+        assert_eq!(
+            paths.params,
+            vec![
+                vec![name("Root"), name("Value")],
+                vec![name("Child"), name("Obj"), name("Value")],
+            ]
+        );
+    }
+
+    #[test]
+    fn modify() {
+        struct Scale;
+        impl VisitMut for Scale {
+            fn visit_parameter(&mut self, _path: &[Name], param: &mut Parameter) {
+                if let Parameter::F32(f) = param {
+                    *f *= 2.0;
+                }
+            }
+        }
+
+        struct Floats(Vec<f32>);
+        impl Visit for Floats {
+            fn visit_parameter(&mut self, _path: &[Name], param: &Parameter) {
+                if let Parameter::F32(f) = param {
+                    self.0.push(*f);
+                }
+            }
+        }
+
+        let mut pio =
+            ParameterIO::from_binary(std::fs::read("test/aamp/Lizalfos.bphysics").unwrap())
+                .unwrap();
+        let mut before = Floats(vec![]);
+        pio.visit(&mut before);
+        assert!(!before.0.is_empty());
+        pio.visit_mut(&mut Scale);
+        let mut after = Floats(vec![]);
+        pio.visit(&mut after);
+        let doubled: Vec<f32> = before.0.iter().map(|f| f * 2.0).collect();
+        assert_eq!(after.0, doubled);
+    }
+}
@@ -0,0 +1,469 @@
+use super::{hash_value, take};
+use crate::{
+    byml::{Byml, Map},
+    util::crc32,
+    Endian, Error, Result,
+};
+
+/// The actor info table (`Actor/ActorInfo.product.sbyml`).
+///
+/// The table is stored as two parallel arrays: `Hashes` holds the CRC32 hash
+/// of each actor name, sorted as unsigned integers, and `Actors` holds the
+/// actor entries in the same order. The game looks up actors by binary search
+/// over the hashes, so both arrays must always stay sorted and in step. This
+/// type maintains that invariant; use [`ActorInfo::insert`] and
+/// [`ActorInfo::remove`] to add or remove actors.
+///
+/// Entries are exposed as [`Actor`] and [`ActorMut`] views with typed
+/// accessors for the common fields.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ActorInfo {
+    hashes: Vec<u32>,
+    actors: Vec<Map>,
+    /// Any other root keys, which are kept as is.
+    extra:  Map,
+}
+
+impl ActorInfo {
+    /// Create an empty actor info table.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Hash an actor name the way the game does.
+    #[inline]
+    pub const fn hash_name(name: &str) -> u32 {
+        crc32(name.as_bytes())
+    }
+
+    /// Wrap an actor info BYML document.
+    ///
+    /// Returns an error if the document is not a map with `Actors` and
+    /// `Hashes` arrays of the same length, if an actor has no name, or if a
+    /// hash does not match its actor's name. Tables which are merely out of
+    /// order are sorted.
+    pub fn from_byml(byml: Byml) -> Result<Self> {
+        let Byml::Map(mut root) = byml else {
+            return Err(Error::TypeError(byml.type_name(), "Map"));
+        };
+        let hashes = match take(&mut root, "Hashes") {
+            Some(Byml::Array(hashes)) => hashes,
+            Some(other) => return Err(Error::TypeError(other.type_name(), "Array")),
+            None => return Err(Error::InvalidData("Actor info has no Hashes array")),
+        };
+        let actors = match take(&mut root, "Actors") {
+            Some(Byml::Array(actors)) => actors,
+            Some(other) => return Err(Error::TypeError(other.type_name(), "Array")),
+            None => return Err(Error::InvalidData("Actor info has no Actors array")),
+        };
+        if hashes.len() != actors.len() {
+            return Err(Error::InvalidDataD(format!(
+                "Actor info has {} hashes but {} actors",
+                hashes.len(),
+                actors.len()
+            )));
+        }
+        let mut entries = hashes
+            .into_iter()
+            .zip(actors)
+            .map(|(hash, actor)| -> Result<(u32, Map)> {
+                let hash = hash_value(&hash)
+                    .ok_or_else(|| Error::TypeError(hash.type_name(), "I32 or U32"))?;
+                let Byml::Map(actor) = actor else {
+                    return Err(Error::TypeError(actor.type_name(), "Map"));
+                };
+                let name = actor_name(&actor)?;
+                if Self::hash_name(name) != hash {
+                    return Err(Error::InvalidDataD(format!(
+                        "Actor info hash {hash:#010x} does not match actor {name}"
+                    )));
+                }
+                Ok((hash, actor))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !entries.windows(2).all(|w| w[0].0 <= w[1].0) {
+            entries.sort_by_key(|(hash, _)| *hash);
+        }
+        let (hashes, actors) = entries.into_iter().unzip();
+        Ok(Self {
+            hashes,
+            actors,
+            extra: root,
+        })
+    }
+
+    /// Parse an actor info table from binary BYML data.
+    pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::from_byml(Byml::from_binary(data)?)
+    }
+
+    /// Convert the table back into a BYML document.
+    pub fn to_byml(&self) -> Byml {
+        self.clone().into_byml()
+    }
+
+    /// Convert the table into a BYML document.
+    pub fn into_byml(self) -> Byml {
+        let mut root = self.extra;
+        root.insert(
+            "Actors".into(),
+            Byml::Array(self.actors.into_iter().map(Byml::Map).collect()),
+        );
+        root.insert(
+            "Hashes".into(),
+            Byml::Array(self.hashes.into_iter().map(hash_node).collect()),
+        );
+        Byml::Map(root)
+    }
+
+    /// Serialize the table to binary BYML data.
+    pub fn to_binary(&self, endian: Endian) -> Vec<u8> {
+        self.to_byml().to_binary(endian)
+    }
+
+    /// Return the number of actors.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.actors.len()
+    }
+
+    /// Returns `true` if the table has no actors.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.actors.is_empty()
+    }
+
+    /// Find the index of an actor by name.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        let hash = Self::hash_name(name);
+        let start = self.hashes.partition_point(|h| *h < hash);
+        self.hashes[start..]
+            .iter()
+            .take_while(|h| **h == hash)
+            .zip(&self.actors[start..])
+            .position(|(_, actor)| actor_name(actor).is_ok_and(|n| n == name))
+            .map(|i| start + i)
+    }
+
+    /// Returns `true` if the table has an actor with the given name.
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Get an actor by name.
+    pub fn get(&self, name: &str) -> Option<Actor<'_>> {
+        self.position(name).map(|i| Actor(&self.actors[i]))
+    }
+
+    /// Get a mutable view of an actor by name.
+    pub fn get_mut(&mut self, name: &str) -> Option<ActorMut<'_>> {
+        self.position(name).map(|i| ActorMut(&mut self.actors[i]))
+    }
+
+    /// Iterate over the actors in table order.
+    pub fn iter(&self) -> impl Iterator<Item = Actor<'_>> {
+        self.actors.iter().map(Actor)
+    }
+
+    /// Iterate over the actor names in table order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.actors
+            .iter()
+            .filter_map(|actor| actor_name(actor).ok())
+    }
+
+    /// Add an actor to the table, keeping it sorted. If an actor with the
+    /// same name exists, it is replaced and the old entry is returned.
+    ///
+    /// Returns an error if the entry has no `name` string.
+    pub fn insert(&mut self, actor: Map) -> Result<Option<Map>> {
+        let name = actor_name(&actor)?;
+        if let Some(index) = self.position(name) {
+            return Ok(Some(std::mem::replace(&mut self.actors[index], actor)));
+        }
+        let hash = Self::hash_name(name);
+        let index = self.hashes.partition_point(|h| *h <= hash);
+        self.hashes.insert(index, hash);
+        self.actors.insert(index, actor);
+        Ok(None)
+    }
+
+    /// Remove an actor by name, returning its entry if it was present.
+    pub fn remove(&mut self, name: &str) -> Option<Map> {
+        let index = self.position(name)?;
+        self.hashes.remove(index);
+        Some(self.actors.remove(index))
+    }
+}
+
+impl TryFrom<Byml> for ActorInfo {
+    type Error = Error;
+
+    fn try_from(byml: Byml) -> Result<Self> {
+        Self::from_byml(byml)
+    }
+}
+
+impl From<ActorInfo> for Byml {
+    fn from(info: ActorInfo) -> Self {
+        info.into_byml()
+    }
+}
+
+fn actor_name(actor: &Map) -> Result<&str> {
+    match actor.get("name") {
+        Some(Byml::String(name)) => Ok(name.as_str()),
+        Some(other) => Err(Error::TypeError(other.type_name(), "String")),
+        None => Err(Error::InvalidData("Actor info entry has no name")),
+    }
+}
+
+/// The game stores hashes which fit as `I32` and the rest as `U32`.
+fn hash_node(hash: u32) -> Byml {
+    if hash < 0x80000000 {
+        Byml::I32(hash as i32)
+    } else {
+        Byml::U32(hash)
+    }
+}
+
+/// A borrowed actor info entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Actor<'a>(&'a Map);
+
+/// A mutable actor info entry.
+///
+/// The actor name cannot be changed through this view, as that would break
+/// the table's order. To rename an actor, remove it and insert it again.
+#[derive(Debug, PartialEq)]
+pub struct ActorMut<'a>(&'a mut Map);
+
+impl<'a> Actor<'a> {
+    /// The actor name.
+    pub fn name(&self) -> &'a str {
+        actor_name(self.0).unwrap_or_default()
+    }
+
+    /// Get a field by key.
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&'a Byml> {
+        self.0.get(key)
+    }
+
+    /// The underlying map of fields.
+    #[inline]
+    pub fn as_map(&self) -> &'a Map {
+        self.0
+    }
+
+    /// The CRC32 hashes of the actor's tags.
+    pub fn tag_hashes(&self) -> impl Iterator<Item = u32> + 'a {
+        self.0
+            .get("tags")
+            .and_then(|tags| tags.as_map().ok())
+            .into_iter()
+            .flat_map(|tags| tags.values())
+            .filter_map(hash_value)
+    }
+
+    /// Returns `true` if the actor has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let hash = crc32(tag.as_bytes());
+        self.tag_hashes().any(|h| h == hash)
+    }
+}
+
+impl<'a> ActorMut<'a> {
+    /// A read-only view of the entry.
+    #[inline]
+    pub fn as_actor(&self) -> Actor<'_> {
+        Actor(self.0)
+    }
+
+    /// The actor name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        actor_name(self.0).unwrap_or_default()
+    }
+
+    /// Get a field by key.
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&Byml> {
+        self.0.get(key)
+    }
+
+    /// Set a field, returning the old value. Returns an error for `name`,
+    /// which cannot be changed in place.
+    pub fn set(&mut self, key: &str, value: impl Into<Byml>) -> Result<Option<Byml>> {
+        if key == "name" {
+            return Err(Error::InvalidData("Actor names cannot be changed in place"));
+        }
+        Ok(self.0.insert(key.into(), value.into()))
+    }
+
+    /// Remove a field, returning its value. Returns an error for `name`,
+    /// which cannot be removed.
+    pub fn remove(&mut self, key: &str) -> Result<Option<Byml>> {
+        if key == "name" {
+            return Err(Error::InvalidData("Actor names cannot be removed"));
+        }
+        Ok(take(self.0, key))
+    }
+
+    /// Add a tag to the actor.
+    pub fn add_tag(&mut self, tag: &str) {
+        let hash = crc32(tag.as_bytes());
+        let tags = self
+            .0
+            .entry("tags".into())
+            .or_insert_with(|| Byml::Map(Default::default()));
+        if let Byml::Map(tags) = tags {
+            tags.insert(format!("tag{hash:08x}").into(), hash_node(hash));
+        }
+    }
+}
+
+macro_rules! actor_fields {
+    ($($key:literal => $get:ident, $set:ident: $kind:ident;)*) => {
+        impl<'a> Actor<'a> {
+            $(
+                #[doc = concat!("The `", $key, "` field.")]
+                #[inline]
+                pub fn $get(&self) -> Option<actor_fields!(@ret 'a $kind)> {
+                    actor_fields!(@get self.0.get($key), $kind)
+                }
+            )*
+        }
+
+        impl ActorMut<'_> {
+            $(
+                #[doc = concat!("The `", $key, "` field.")]
+                #[inline]
+                pub fn $get(&self) -> Option<actor_fields!(@ret '_ $kind)> {
+                    actor_fields!(@get self.0.get($key), $kind)
+                }
+
+                #[doc = concat!("Set the `", $key, "` field.")]
+                #[inline]
+                pub fn $set(&mut self, value: actor_fields!(@arg $kind)) {
+                    self.0.insert($key.into(), value.into());
+                }
+            )*
+        }
+    };
+    (@ret $lt:lifetime str) => { &$lt str };
+    (@ret $lt:lifetime $kind:ident) => { $kind };
+    (@arg str) => { &str };
+    (@arg $kind:ident) => { $kind };
+    (@get $value:expr, str) => {
+        $value.and_then(|v| v.as_string().ok()).map(|v| v.as_str())
+    };
+    (@get $value:expr, i32) => { $value.and_then(|v| v.as_i32().ok()) };
+    (@get $value:expr, f32) => { $value.and_then(|v| v.as_float().ok()) };
+    (@get $value:expr, bool) => { $value.and_then(|v| v.as_bool().ok()) };
+}
+
+actor_fields! {
+    "profile" => profile, set_profile: str;
+    "instSize" => inst_size, set_inst_size: i32;
+    "bfres" => bfres, set_bfres: str;
+    "mainModel" => main_model, set_main_model: str;
+    "elink" => elink, set_elink: str;
+    "slink" => slink, set_slink: str;
+    "xlink" => xlink, set_xlink: str;
+    "sortKey" => sort_key, set_sort_key: i32;
+    "bugMask" => bug_mask, set_bug_mask: i32;
+    "yLimitAlgorithm" => y_limit_algorithm, set_y_limit_algorithm: str;
+    "actorScale" => actor_scale, set_actor_scale: f32;
+    "boundingForTraverse" => bounding_for_traverse, set_bounding_for_traverse: f32;
+    "traverseDist" => traverse_dist, set_traverse_dist: f32;
+    "cursorOffsetY" => cursor_offset_y, set_cursor_offset_y: f32;
+    "generalLife" => general_life, set_general_life: i32;
+    "attackPower" => attack_power, set_attack_power: i32;
+    "itemBuyingPrice" => item_buying_price, set_item_buying_price: i32;
+    "itemSellingPrice" => item_selling_price, set_item_selling_price: i32;
+    "systemSameGroupActorName" => same_group_actor_name, set_same_group_actor_name: str;
+    "systemIsGetItemSelf" => is_get_item_self, set_is_get_item_self: bool;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load() -> ActorInfo {
+        ActorInfo::from_binary(std::fs::read("test/byml/ActorInfo.product.byml").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let data = std::fs::read("test/byml/ActorInfo.product.byml").unwrap();
+        let byml = Byml::from_binary(&data).unwrap();
+        let info = ActorInfo::from_byml(byml.clone()).unwrap();
+        assert_eq!(info.len(), byml["Actors"].as_array().unwrap().len());
+        assert!(info.hashes.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(info.to_byml(), byml);
+        for actor in byml["Actors"].as_array().unwrap() {
+            let name = actor["name"].as_string().unwrap();
+            assert_eq!(info.get(name).unwrap().as_map(), actor.as_map().unwrap());
+        }
+        let actor = info.get("Enemy_Lizalfos_Electric").unwrap();
+        assert_eq!(actor.name(), "Enemy_Lizalfos_Electric");
+        assert_eq!(actor.profile(), Some("Enemy"));
+        assert_eq!(actor.inst_size(), Some(777968));
+        assert_eq!(actor.bfres(), Some("Enemy_Lizalfos"));
+        assert_eq!(actor.main_model(), Some("Lizalfos"));
+        assert_eq!(actor.traverse_dist(), Some(150.0));
+        assert!(actor.tag_hashes().any(|h| h == 0x994aef4b));
+        assert_eq!(actor.actor_scale(), None);
+        assert!(info.get("Enemy_Lizalfos_Electric_Missing").is_none());
+
+        let mut bad = byml.clone();
+        bad["Hashes"].as_mut_array().unwrap().pop();
+        assert!(ActorInfo::from_byml(bad).is_err());
+        let mut bad = byml;
+        bad["Hashes"].as_mut_array().unwrap()[0] = Byml::I32(0);
+        assert!(ActorInfo::from_byml(bad).is_err());
+    }
+
+    #[test]
+    fn edit() {
+        let mut info = load();
+        let count = info.len();
+        let mut actor = info
+            .get("Enemy_Lizalfos_Electric")
+            .unwrap()
+            .as_map()
+            .clone();
+        actor.insert("name".into(), "Enemy_Lizalfos_Custom".into());
+        assert_eq!(info.insert(actor).unwrap(), None);
+        assert_eq!(info.len(), count + 1);
+        {
+            let mut actor = info.get_mut("Enemy_Lizalfos_Custom").unwrap();
+            actor.set_inst_size(1000);
+            actor.set_profile("EnemySwarm");
+            actor.add_tag("Dummy");
+            assert!(actor.set("name", "Other").is_err());
+            assert!(actor.remove("name").is_err());
+        }
+        let actor = info.get("Enemy_Lizalfos_Custom").unwrap();
+        assert_eq!(actor.inst_size(), Some(1000));
+        assert_eq!(actor.profile(), Some("EnemySwarm"));
+        assert!(actor.has_tag("Dummy"));
+
+        let data = info.to_binary(Endian::Little);
+        let info2 = ActorInfo::from_binary(data).unwrap();
+        assert_eq!(info, info2);
+        assert!(info2.hashes.windows(2).all(|w| w[0] < w[1]));
+
+        let mut replacement = Map::default();
+        replacement.insert("name".into(), "Enemy_Lizalfos_Custom".into());
+        assert!(info.insert(replacement).unwrap().is_some());
+        assert_eq!(info.len(), count + 1);
+        assert!(info.remove("Enemy_Lizalfos_Custom").is_some());
+        assert!(info.remove("Enemy_Lizalfos_Custom").is_none());
+        assert_eq!(info.len(), count);
+        assert_eq!(info, load());
+        assert!(info.insert(Map::default()).is_err());
+    }
+}
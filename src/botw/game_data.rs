@@ -0,0 +1,1087 @@
+use super::hash_value;
+use crate::{
+    byml::{Byml, Map},
+    sarc::{Sarc, SarcWriter},
+    types::{Vector2f, Vector3f, Vector4f},
+    util::crc32,
+    Endian, Error, Result,
+};
+
+/// The number of flags written to each `.bgdata` file. The game splits
+/// `bool_data`, the only type this large in the base game, into files of
+/// this size.
+const FLAGS_PER_FILE: usize = 4096;
+/// The number of flags written to each `saveformat_*.bgsvdata` file.
+const SAVE_FLAGS_PER_FILE: usize = 8192;
+/// The save file whose format is generated from the game data flags.
+const SAVE_FILE_NAME: &str = "game_data.sav";
+
+/// The type of a game data flag, which decides the file it is stored in and
+/// the type of its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FlagType {
+    Bool,
+    S32,
+    F32,
+    String32,
+    String64,
+    String256,
+    Vector2f,
+    Vector3f,
+    Vector4f,
+    BoolArray,
+    S32Array,
+    F32Array,
+    String64Array,
+    String256Array,
+    Vector2fArray,
+    Vector3fArray,
+    Vector4fArray,
+}
+
+impl FlagType {
+    /// All flag types, in the order their files are written.
+    pub const ALL: [FlagType; 17] = [
+        FlagType::Bool,
+        FlagType::S32,
+        FlagType::F32,
+        FlagType::String32,
+        FlagType::String64,
+        FlagType::String256,
+        FlagType::Vector2f,
+        FlagType::Vector3f,
+        FlagType::Vector4f,
+        FlagType::BoolArray,
+        FlagType::S32Array,
+        FlagType::F32Array,
+        FlagType::String64Array,
+        FlagType::String256Array,
+        FlagType::Vector2fArray,
+        FlagType::Vector3fArray,
+        FlagType::Vector4fArray,
+    ];
+
+    /// The root key of the flag array in a `.bgdata` file, e.g.
+    /// `bool_data`.
+    pub const fn key(self) -> &'static str {
+        match self {
+            FlagType::Bool => "bool_data",
+            FlagType::S32 => "s32_data",
+            FlagType::F32 => "f32_data",
+            FlagType::String32 => "string_data",
+            FlagType::String64 => "string64_data",
+            FlagType::String256 => "string256_data",
+            FlagType::Vector2f => "vector2f_data",
+            FlagType::Vector3f => "vector3f_data",
+            FlagType::Vector4f => "vector4f_data",
+            FlagType::BoolArray => "bool_array_data",
+            FlagType::S32Array => "s32_array_data",
+            FlagType::F32Array => "f32_array_data",
+            FlagType::String64Array => "string64_array_data",
+            FlagType::String256Array => "string256_array_data",
+            FlagType::Vector2fArray => "vector2f_array_data",
+            FlagType::Vector3fArray => "vector3f_array_data",
+            FlagType::Vector4fArray => "vector4f_array_data",
+        }
+    }
+
+    /// Look up a flag type by its root key.
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ty| ty.key() == key)
+    }
+
+    /// The file name prefix, which differs from the key only for
+    /// [`FlagType::String32`].
+    const fn file_prefix(self) -> &'static str {
+        match self {
+            FlagType::String32 => "string32_data",
+            _ => self.key(),
+        }
+    }
+
+    /// Returns `true` for the types which can be stored as revival flags,
+    /// which the game resets on a blood moon.
+    #[inline]
+    pub const fn can_revive(self) -> bool {
+        matches!(self, FlagType::Bool | FlagType::S32)
+    }
+
+    /// The initial value of a new flag of this type.
+    pub fn default_value(self) -> FlagValue {
+        match self {
+            FlagType::Bool => FlagValue::Bool(false),
+            FlagType::S32 => FlagValue::S32(0),
+            FlagType::F32 => FlagValue::F32(0.0),
+            FlagType::String32 | FlagType::String64 | FlagType::String256 => {
+                FlagValue::String(String::new())
+            }
+            FlagType::Vector2f => FlagValue::Vector2f(Vector2f { x: 0.0, y: 0.0 }),
+            FlagType::Vector3f => {
+                FlagValue::Vector3f(Vector3f {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                })
+            }
+            FlagType::Vector4f => {
+                FlagValue::Vector4f(Vector4f {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                    t: 0.0,
+                })
+            }
+            FlagType::BoolArray => FlagValue::BoolArray(vec![]),
+            FlagType::S32Array => FlagValue::S32Array(vec![]),
+            FlagType::F32Array => FlagValue::F32Array(vec![]),
+            FlagType::String64Array | FlagType::String256Array => FlagValue::StringArray(vec![]),
+            FlagType::Vector2fArray => FlagValue::Vector2fArray(vec![]),
+            FlagType::Vector3fArray => FlagValue::Vector3fArray(vec![]),
+            FlagType::Vector4fArray => FlagValue::Vector4fArray(vec![]),
+        }
+    }
+
+    /// The maximum length of a string value, if this is a string type.
+    const fn max_string_len(self) -> Option<usize> {
+        match self {
+            FlagType::String32 => Some(32),
+            FlagType::String64 | FlagType::String64Array => Some(64),
+            FlagType::String256 | FlagType::String256Array => Some(256),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FlagType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// The value of a game data flag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlagValue {
+    Bool(bool),
+    S32(i32),
+    F32(f32),
+    /// The value of a `String32`, `String64` or `String256` flag.
+    String(String),
+    Vector2f(Vector2f),
+    Vector3f(Vector3f),
+    Vector4f(Vector4f),
+    BoolArray(Vec<bool>),
+    S32Array(Vec<i32>),
+    F32Array(Vec<f32>),
+    /// The value of a `String64Array` or `String256Array` flag.
+    StringArray(Vec<String>),
+    Vector2fArray(Vec<Vector2f>),
+    Vector3fArray(Vec<Vector3f>),
+    Vector4fArray(Vec<Vector4f>),
+}
+
+impl FlagValue {
+    /// Returns `true` if this value can be stored in a flag of the given
+    /// type.
+    pub fn is_type(&self, ty: FlagType) -> bool {
+        match (self, ty) {
+            (FlagValue::String(s), _) => {
+                matches!(
+                    ty,
+                    FlagType::String32 | FlagType::String64 | FlagType::String256
+                ) && ty.max_string_len().is_some_and(|max| s.len() < max)
+            }
+            (FlagValue::StringArray(strings), _) => {
+                matches!(ty, FlagType::String64Array | FlagType::String256Array)
+                    && ty
+                        .max_string_len()
+                        .is_some_and(|max| strings.iter().all(|s| s.len() < max))
+            }
+            (FlagValue::Bool(_), FlagType::Bool)
+            | (FlagValue::S32(_), FlagType::S32)
+            | (FlagValue::F32(_), FlagType::F32)
+            | (FlagValue::Vector2f(_), FlagType::Vector2f)
+            | (FlagValue::Vector3f(_), FlagType::Vector3f)
+            | (FlagValue::Vector4f(_), FlagType::Vector4f)
+            | (FlagValue::BoolArray(_), FlagType::BoolArray)
+            | (FlagValue::S32Array(_), FlagType::S32Array)
+            | (FlagValue::F32Array(_), FlagType::F32Array)
+            | (FlagValue::Vector2fArray(_), FlagType::Vector2fArray)
+            | (FlagValue::Vector3fArray(_), FlagType::Vector3fArray)
+            | (FlagValue::Vector4fArray(_), FlagType::Vector4fArray) => true,
+            _ => false,
+        }
+    }
+
+    /// Read a value of the given type from its BYML form.
+    ///
+    /// Vectors are stored wrapped in a single item array
+    /// (`[[x, y, z]]`), and arrays as `[{Values: [...]}]`. Unwrapped forms
+    /// are accepted as well.
+    pub fn from_byml(ty: FlagType, value: &Byml) -> Result<Self> {
+        let value = unwrap_value(value);
+        Ok(match ty {
+            FlagType::Bool => FlagValue::Bool(bool_value(value)?),
+            FlagType::S32 => FlagValue::S32(value.as_i32()?),
+            FlagType::F32 => FlagValue::F32(value.as_float()?),
+            FlagType::String32 | FlagType::String64 | FlagType::String256 => {
+                FlagValue::String(value.as_string()?.to_string())
+            }
+            FlagType::Vector2f => FlagValue::Vector2f(vector2f(value)?),
+            FlagType::Vector3f => FlagValue::Vector3f(vector3f(value)?),
+            FlagType::Vector4f => FlagValue::Vector4f(vector4f(value)?),
+            FlagType::BoolArray => FlagValue::BoolArray(array_values(value, bool_value)?),
+            FlagType::S32Array => FlagValue::S32Array(array_values(value, Byml::as_i32)?),
+            FlagType::F32Array => FlagValue::F32Array(array_values(value, Byml::as_float)?),
+            FlagType::String64Array | FlagType::String256Array => {
+                FlagValue::StringArray(array_values(value, |v| {
+                    v.as_string().map(|s| s.to_string())
+                })?)
+            }
+            FlagType::Vector2fArray => FlagValue::Vector2fArray(array_values(value, vector2f)?),
+            FlagType::Vector3fArray => FlagValue::Vector3fArray(array_values(value, vector3f)?),
+            FlagType::Vector4fArray => FlagValue::Vector4fArray(array_values(value, vector4f)?),
+        })
+    }
+
+    /// Convert the value into the BYML form the game uses for `InitValue`.
+    pub fn to_byml(&self) -> Byml {
+        fn wrap(value: Byml) -> Byml {
+            Byml::Array(vec![value])
+        }
+        fn values<T>(items: &[T], f: impl Fn(&T) -> Byml) -> Byml {
+            wrap(Byml::Map(
+                [("Values".into(), items.iter().map(f).collect())]
+                    .into_iter()
+                    .collect(),
+            ))
+        }
+        match self {
+            FlagValue::Bool(b) => Byml::I32(*b as i32),
+            FlagValue::S32(i) => Byml::I32(*i),
+            FlagValue::F32(f) => Byml::Float(*f),
+            FlagValue::String(s) => s.as_str().into(),
+            FlagValue::Vector2f(v) => wrap(vec2_node(v)),
+            FlagValue::Vector3f(v) => wrap(vec3_node(v)),
+            FlagValue::Vector4f(v) => wrap(vec4_node(v)),
+            FlagValue::BoolArray(items) => values(items, |b| Byml::I32(*b as i32)),
+            FlagValue::S32Array(items) => values(items, |i| Byml::I32(*i)),
+            FlagValue::F32Array(items) => values(items, |f| Byml::Float(*f)),
+            FlagValue::StringArray(items) => values(items, |s| s.as_str().into()),
+            FlagValue::Vector2fArray(items) => values(items, vec2_node),
+            FlagValue::Vector3fArray(items) => values(items, vec3_node),
+            FlagValue::Vector4fArray(items) => values(items, vec4_node),
+        }
+    }
+}
+
+/// Unwrap the single item array or `{Values: [...]}` map around a value.
+fn unwrap_value(value: &Byml) -> &Byml {
+    match value {
+        Byml::Array(items) if items.len() == 1 && !is_scalar(&items[0]) => {
+            match &items[0] {
+                Byml::Map(map) => map.get("Values").unwrap_or(&items[0]),
+                item => item,
+            }
+        }
+        _ => value,
+    }
+}
+
+#[inline]
+fn is_scalar(value: &Byml) -> bool {
+    !matches!(value, Byml::Array(_) | Byml::Map(_))
+}
+
+/// Bools are stored as `I32` in flag values, but accept `Bool` too.
+fn bool_value(value: &Byml) -> Result<bool> {
+    match value {
+        Byml::Bool(b) => Ok(*b),
+        Byml::I32(i) => Ok(*i != 0),
+        _ => Err(Error::TypeError(value.type_name(), "Bool or I32")),
+    }
+}
+
+fn array_values<T>(value: &Byml, f: impl Fn(&Byml) -> Result<T>) -> Result<Vec<T>> {
+    value.as_array()?.iter().map(f).collect()
+}
+
+fn floats<const N: usize>(value: &Byml) -> Result<[f32; N]> {
+    let items = value.as_array()?;
+    if items.len() != N {
+        return Err(Error::InvalidDataD(format!(
+            "Expected a vector of {N} floats, found {} items",
+            items.len()
+        )));
+    }
+    let mut out = [0.0; N];
+    for (out, item) in out.iter_mut().zip(items) {
+        *out = item.as_float()?;
+    }
+    Ok(out)
+}
+
+fn vector2f(value: &Byml) -> Result<Vector2f> {
+    let [x, y] = floats(value)?;
+    Ok(Vector2f { x, y })
+}
+
+fn vector3f(value: &Byml) -> Result<Vector3f> {
+    let [x, y, z] = floats(value)?;
+    Ok(Vector3f { x, y, z })
+}
+
+fn vector4f(value: &Byml) -> Result<Vector4f> {
+    let [x, y, z, t] = floats(value)?;
+    Ok(Vector4f { x, y, z, t })
+}
+
+fn vec2_node(v: &Vector2f) -> Byml {
+    Byml::Array(vec![Byml::Float(v.x), Byml::Float(v.y)])
+}
+
+fn vec3_node(v: &Vector3f) -> Byml {
+    Byml::Array(vec![Byml::Float(v.x), Byml::Float(v.y), Byml::Float(v.z)])
+}
+
+fn vec4_node(v: &Vector4f) -> Byml {
+    Byml::Array(vec![
+        Byml::Float(v.x),
+        Byml::Float(v.y),
+        Byml::Float(v.z),
+        Byml::Float(v.t),
+    ])
+}
+
+/// A game data flag definition.
+///
+/// The definition is kept as its BYML map, so unknown fields survive a round
+/// trip. The name and hash cannot be changed, as the game looks flags up by
+/// hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flag {
+    ty:      FlagType,
+    revival: bool,
+    entry:   Map,
+}
+
+impl Flag {
+    /// Create a flag with the default value for its type and the settings
+    /// most flags use: no reset, not saved, readable and writable by the
+    /// program.
+    pub fn new(name: &str, ty: FlagType) -> Self {
+        const MAX: f32 = 1000000.0;
+        fn float_array(value: f32, len: usize) -> Byml {
+            Byml::Array(vec![Byml::Float(value); len])
+        }
+        let (max, min) = match ty {
+            FlagType::Bool | FlagType::BoolArray => (Byml::Bool(true), Byml::Bool(false)),
+            FlagType::S32 | FlagType::S32Array => (Byml::I32(i32::MAX), Byml::I32(i32::MIN)),
+            FlagType::F32 | FlagType::F32Array => (Byml::Float(MAX), Byml::Float(-MAX)),
+            FlagType::String32
+            | FlagType::String64
+            | FlagType::String256
+            | FlagType::String64Array
+            | FlagType::String256Array => ("".into(), "".into()),
+            FlagType::Vector2f | FlagType::Vector2fArray => {
+                (float_array(MAX, 2), float_array(-MAX, 2))
+            }
+            FlagType::Vector3f | FlagType::Vector3fArray => {
+                (float_array(MAX, 3), float_array(-MAX, 3))
+            }
+            FlagType::Vector4f | FlagType::Vector4fArray => {
+                (float_array(MAX, 4), float_array(-MAX, 4))
+            }
+        };
+        let entry = [
+            ("DataName", name.into()),
+            ("DeleteRev", Byml::I32(-1)),
+            ("HashValue", Byml::I32(GameData::hash_name(name) as i32)),
+            ("InitValue", ty.default_value().to_byml()),
+            ("IsEventAssociated", Byml::Bool(false)),
+            ("IsOneTrigger", Byml::Bool(false)),
+            ("IsProgramReadable", Byml::Bool(true)),
+            ("IsProgramWritable", Byml::Bool(true)),
+            ("IsSave", Byml::Bool(false)),
+            ("MaxValue", max),
+            ("MinValue", min),
+            ("ResetType", Byml::I32(0)),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.into(), v))
+        .collect();
+        Self {
+            ty,
+            revival: false,
+            entry,
+        }
+    }
+
+    /// Wrap a flag definition map from a file of the given type.
+    ///
+    /// Returns an error if it has no `DataName` string or if its `HashValue`
+    /// does not match the name.
+    pub fn from_map(ty: FlagType, revival: bool, entry: Map) -> Result<Self> {
+        let name = flag_name(&entry)?;
+        let hash = entry
+            .get("HashValue")
+            .and_then(hash_value)
+            .ok_or(Error::InvalidData("Game data flag has no HashValue"))?;
+        if GameData::hash_name(name) != hash {
+            return Err(Error::InvalidDataD(format!(
+                "Game data flag hash {hash:#010x} does not match flag {name}"
+            )));
+        }
+        Ok(Self {
+            ty,
+            revival: revival && ty.can_revive(),
+            entry,
+        })
+    }
+
+    /// The flag name (`DataName`).
+    pub fn name(&self) -> &str {
+        flag_name(&self.entry).unwrap_or_default()
+    }
+
+    /// The CRC32 hash of the flag name (`HashValue`).
+    pub fn hash(&self) -> u32 {
+        GameData::hash_name(self.name())
+    }
+
+    /// The flag type.
+    #[inline]
+    pub fn flag_type(&self) -> FlagType {
+        self.ty
+    }
+
+    /// Returns `true` if the flag is stored in a `revival_*` file.
+    #[inline]
+    pub fn is_revival(&self) -> bool {
+        self.revival
+    }
+
+    /// Set whether the flag is stored in a `revival_*` file. Only bool and s32
+    /// flags can be revival flags; for other types this does nothing.
+    pub fn set_revival(&mut self, revival: bool) {
+        self.revival = revival && self.ty.can_revive();
+    }
+
+    /// The initial value (`InitValue`).
+    pub fn init_value(&self) -> Result<FlagValue> {
+        let value = self
+            .entry
+            .get("InitValue")
+            .ok_or(Error::InvalidData("Game data flag has no InitValue"))?;
+        FlagValue::from_byml(self.ty, value)
+    }
+
+    /// Set the initial value (`InitValue`). Returns an error if the value
+    /// does not match the flag type, including strings which are too long.
+    pub fn set_init_value(&mut self, value: FlagValue) -> Result<()> {
+        if !value.is_type(self.ty) {
+            return Err(Error::InvalidDataD(format!(
+                "Value {value:?} cannot be stored in a {} flag",
+                self.ty
+            )));
+        }
+        self.entry.insert("InitValue".into(), value.to_byml());
+        Ok(())
+    }
+
+    /// When the game resets the flag to its initial value (`ResetType`).
+    /// `0` means it is never reset.
+    pub fn reset_type(&self) -> i32 {
+        self.entry
+            .get("ResetType")
+            .and_then(|v| v.as_i32().ok())
+            .unwrap_or_default()
+    }
+
+    /// Set when the game resets the flag (`ResetType`).
+    pub fn set_reset_type(&mut self, reset_type: i32) {
+        self.entry.insert("ResetType".into(), Byml::I32(reset_type));
+    }
+
+    /// The `DeleteRev` field, the game revision in which the flag was
+    /// deleted, or `-1`.
+    pub fn delete_rev(&self) -> i32 {
+        self.entry
+            .get("DeleteRev")
+            .and_then(|v| v.as_i32().ok())
+            .unwrap_or(-1)
+    }
+
+    /// Get a field by key.
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&Byml> {
+        self.entry.get(key)
+    }
+
+    /// Set a field, returning the old value. Returns an error for `DataName`
+    /// and `HashValue`, which cannot be changed in place, and for values of
+    /// the wrong type for `InitValue`.
+    pub fn set(&mut self, key: &str, value: impl Into<Byml>) -> Result<Option<Byml>> {
+        let value = value.into();
+        match key {
+            "DataName" | "HashValue" => {
+                Err(Error::InvalidData(
+                    "Game data flag names cannot be changed in place",
+                ))
+            }
+            "InitValue" => {
+                FlagValue::from_byml(self.ty, &value)?;
+                Ok(self.entry.insert(key.into(), value))
+            }
+            _ => Ok(self.entry.insert(key.into(), value)),
+        }
+    }
+
+    /// The underlying map of fields.
+    #[inline]
+    pub fn as_map(&self) -> &Map {
+        &self.entry
+    }
+}
+
+macro_rules! flag_bools {
+    ($($key:literal => $get:ident, $set:ident;)*) => {
+        impl Flag {
+            $(
+                #[doc = concat!("The `", $key, "` field.")]
+                #[inline]
+                pub fn $get(&self) -> bool {
+                    self.entry
+                        .get($key)
+                        .and_then(|v| v.as_bool().ok())
+                        .unwrap_or_default()
+                }
+
+                #[doc = concat!("Set the `", $key, "` field.")]
+                #[inline]
+                pub fn $set(&mut self, value: bool) {
+                    self.entry.insert($key.into(), Byml::Bool(value));
+                }
+            )*
+        }
+    };
+}
+
+flag_bools! {
+    "IsEventAssociated" => is_event_associated, set_event_associated;
+    "IsOneTrigger" => is_one_trigger, set_one_trigger;
+    "IsProgramReadable" => is_program_readable, set_program_readable;
+    "IsProgramWritable" => is_program_writable, set_program_writable;
+    "IsSave" => is_save, set_save;
+}
+
+fn flag_name(entry: &Map) -> Result<&str> {
+    match entry.get("DataName") {
+        Some(Byml::String(name)) => Ok(name.as_str()),
+        Some(other) => Err(Error::TypeError(other.type_name(), "String")),
+        None => Err(Error::InvalidData("Game data flag has no DataName")),
+    }
+}
+
+/// The game data flag definitions (`GameData/gamedata.ssarc`).
+///
+/// The game splits its flags by type into BYML files (`bool_data_0.bgdata`,
+/// `s32_data_0.bgdata`, `vector3f_data_0.bgdata` and so on), with the
+/// flags in each file sorted by hash and large types split into several
+/// files. This type loads all of them into one table, looked up by name or
+/// hash, and writes them back split the same way.
+///
+/// Flags which are saved (`IsSave`) are also listed in the save data format
+/// (`GameData/savedataformat.ssarc`), which
+/// [`GameData::update_savedata`] regenerates.
+///
+/// Both archives are Yaz0 compressed in the game files, and must be
+/// decompressed before loading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameData {
+    endian: Endian,
+    /// Sorted by hash.
+    flags:  Vec<Flag>,
+}
+
+impl GameData {
+    /// Create an empty flag table.
+    pub fn new(endian: Endian) -> Self {
+        Self {
+            endian,
+            flags: vec![],
+        }
+    }
+
+    /// Hash a flag name the way the game does.
+    #[inline]
+    pub const fn hash_name(name: &str) -> u32 {
+        crc32(name.as_bytes())
+    }
+
+    /// Load the flags from all `.bgdata` files in a game data archive. Files
+    /// whose names start with `revival_` hold revival flags.
+    ///
+    /// Returns an error if a file is not a map of known flag types to flag
+    /// arrays, if a flag is invalid, or if two flags have the same hash.
+    pub fn from_sarc(sarc: &Sarc) -> Result<Self> {
+        let mut flags = vec![];
+        for file in sarc.files() {
+            let Some(name) = file.name() else {
+                continue;
+            };
+            let name = name.trim_start_matches('/');
+            if !name.ends_with(".bgdata") {
+                continue;
+            }
+            let revival = name.starts_with("revival_");
+            let Byml::Map(root) = Byml::from_binary(file.data())? else {
+                return Err(Error::InvalidDataD(format!(
+                    "Game data file {name} is not a map"
+                )));
+            };
+            for (key, entries) in root {
+                let ty = FlagType::from_key(&key).ok_or_else(|| {
+                    Error::InvalidDataD(format!("Unknown game data flag type {key} in {name}"))
+                })?;
+                let Byml::Array(entries) = entries else {
+                    return Err(Error::TypeError(entries.type_name(), "Array"));
+                };
+                for entry in entries {
+                    let Byml::Map(entry) = entry else {
+                        return Err(Error::TypeError(entry.type_name(), "Map"));
+                    };
+                    flags.push(Flag::from_map(ty, revival, entry)?);
+                }
+            }
+        }
+        flags.sort_by_key(Flag::hash);
+        if let Some(w) = flags.windows(2).find(|w| w[0].hash() == w[1].hash()) {
+            return Err(Error::InvalidDataD(format!(
+                "Game data flags {} and {} have the same hash",
+                w[0].name(),
+                w[1].name()
+            )));
+        }
+        Ok(Self {
+            endian: sarc.endian(),
+            flags,
+        })
+    }
+
+    /// Parse a decompressed game data archive.
+    pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::from_sarc(&Sarc::new(data.as_ref())?)
+    }
+
+    /// The endianness used when writing.
+    #[inline]
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// Set the endianness used when writing.
+    #[inline]
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    /// Return the number of flags.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.flags.len()
+    }
+
+    /// Returns `true` if there are no flags.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    fn position(&self, hash: u32) -> Option<usize> {
+        self.flags.binary_search_by_key(&hash, Flag::hash).ok()
+    }
+
+    /// Returns `true` if there is a flag with the given name.
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Get a flag by name.
+    pub fn get(&self, name: &str) -> Option<&Flag> {
+        self.get_by_hash(Self::hash_name(name))
+            .filter(|flag| flag.name() == name)
+    }
+
+    /// Get a mutable reference to a flag by name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Flag> {
+        self.get_by_hash_mut(Self::hash_name(name))
+            .filter(|flag| flag.name() == name)
+    }
+
+    /// Get a flag by the hash of its name.
+    pub fn get_by_hash(&self, hash: u32) -> Option<&Flag> {
+        self.position(hash).map(|i| &self.flags[i])
+    }
+
+    /// Get a mutable reference to a flag by the hash of its name.
+    pub fn get_by_hash_mut(&mut self, hash: u32) -> Option<&mut Flag> {
+        self.position(hash).map(|i| &mut self.flags[i])
+    }
+
+    /// Iterate over all flags in hash order.
+    pub fn iter(&self) -> impl Iterator<Item = &Flag> {
+        self.flags.iter()
+    }
+
+    /// Iterate over the flags of one type in hash order.
+    pub fn iter_type(&self, ty: FlagType) -> impl Iterator<Item = &Flag> {
+        self.flags.iter().filter(move |flag| flag.ty == ty)
+    }
+
+    /// Iterate over the flags which are saved (`IsSave`) in hash order.
+    pub fn save_flags(&self) -> impl Iterator<Item = &Flag> {
+        self.flags.iter().filter(|flag| flag.is_save())
+    }
+
+    /// Add a flag. If a flag with the same name exists, it is replaced and
+    /// the old flag is returned.
+    ///
+    /// Returns an error if a flag with a different name has the same hash.
+    pub fn insert(&mut self, flag: Flag) -> Result<Option<Flag>> {
+        let hash = flag.hash();
+        match self.flags.binary_search_by_key(&hash, Flag::hash) {
+            Ok(index) if self.flags[index].name() == flag.name() => {
+                Ok(Some(std::mem::replace(&mut self.flags[index], flag)))
+            }
+            Ok(index) => {
+                Err(Error::InvalidDataD(format!(
+                    "Game data flag {} has the same hash as {}",
+                    flag.name(),
+                    self.flags[index].name()
+                )))
+            }
+            Err(index) => {
+                self.flags.insert(index, flag);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Remove a flag by name, returning it if it was present.
+    pub fn remove(&mut self, name: &str) -> Option<Flag> {
+        let index = self
+            .position(Self::hash_name(name))
+            .filter(|i| self.flags[*i].name() == name)?;
+        Some(self.flags.remove(index))
+    }
+
+    /// Write the flags into a new game data archive.
+    ///
+    /// Each type (and revival type) with any flags is written to files named
+    /// `/{type}_{index}.bgdata`, sorted by hash and split every 4096 flags.
+    pub fn to_sarc_writer(&self) -> SarcWriter {
+        let mut writer = SarcWriter::new(self.endian);
+        for revival in [false, true] {
+            for ty in FlagType::ALL {
+                let flags = self
+                    .flags
+                    .iter()
+                    .filter(|flag| flag.ty == ty && flag.revival == revival)
+                    .map(|flag| Byml::Map(flag.entry.clone()))
+                    .collect::<Vec<_>>();
+                let prefix = if revival { "revival_" } else { "" };
+                for (i, chunk) in flags.chunks(FLAGS_PER_FILE).enumerate() {
+                    let root: Map = [(ty.key().into(), Byml::Array(chunk.to_vec()))]
+                        .into_iter()
+                        .collect();
+                    writer.add_file(
+                        format!("/{prefix}{}_{i}.bgdata", ty.file_prefix()),
+                        Byml::Map(root).to_binary(self.endian),
+                    );
+                }
+            }
+        }
+        writer
+    }
+
+    /// Serialize the flags to an uncompressed game data archive.
+    pub fn to_binary(&self) -> Vec<u8> {
+        self.to_sarc_writer().to_binary()
+    }
+
+    /// Regenerate the save data format archive (`savedataformat.ssarc`) so
+    /// that the `game_data.sav` format lists exactly the flags which are
+    /// saved.
+    ///
+    /// The `game_data.sav` files in `savedata` are replaced by new ones with
+    /// the saved flags sorted by hash, split every 8192 flags, using the
+    /// header and `save_info` of the first existing one. Files for other save
+    /// files (such as `caption.sav`) are kept unchanged and numbered after
+    /// them.
+    pub fn update_savedata(&self, savedata: &Sarc) -> Result<SarcWriter> {
+        let mut files = savedata
+            .files()
+            .filter_map(|file| {
+                let name = file.name()?.trim_start_matches('/');
+                let index = name
+                    .strip_prefix("saveformat_")?
+                    .strip_suffix(".bgsvdata")?
+                    .parse::<usize>()
+                    .ok()?;
+                Some((index, file.data()))
+            })
+            .collect::<Vec<_>>();
+        files.sort_by_key(|(index, _)| *index);
+        let mut template = None;
+        let mut others = vec![];
+        for (_, data) in files {
+            let root = Byml::from_binary(data)?;
+            let header = save_file_header(&root)?;
+            let file_name = header.get("file_name").and_then(|n| n.as_string().ok());
+            if file_name.map(|n| n.as_str()) == Some(SAVE_FILE_NAME) {
+                if template.is_none() {
+                    template = Some((header.clone(), root.get("save_info").cloned()));
+                }
+            } else {
+                others.push(data.to_vec());
+            }
+        }
+        let (header, save_info) = template.ok_or(Error::InvalidData(
+            "Save data format has no game_data.sav files",
+        ))?;
+        let save_info = save_info.ok_or(Error::InvalidData("Save data format has no save_info"))?;
+
+        let mut writer = SarcWriter::new(self.endian);
+        let flags = self
+            .save_flags()
+            .map(|flag| {
+                Byml::Map(
+                    [
+                        ("DataName".into(), flag.name().into()),
+                        ("HashValue".into(), Byml::I32(flag.hash() as i32)),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut count = 0;
+        for chunk in flags.chunks(SAVE_FLAGS_PER_FILE) {
+            let root: Map = [
+                (
+                    "file_list".into(),
+                    Byml::Array(vec![
+                        Byml::Map(header.clone()),
+                        Byml::Array(chunk.to_vec()),
+                    ]),
+                ),
+                ("save_info".into(), save_info.clone()),
+            ]
+            .into_iter()
+            .collect();
+            writer.add_file(
+                format!("/saveformat_{count}.bgsvdata"),
+                Byml::Map(root).to_binary(self.endian),
+            );
+            count += 1;
+        }
+        for data in others {
+            writer.add_file(format!("/saveformat_{count}.bgsvdata"), data);
+            count += 1;
+        }
+        Ok(writer)
+    }
+}
+
+impl Default for GameData {
+    fn default() -> Self {
+        Self::new(Endian::Little)
+    }
+}
+
+/// The header map of a save data format file, the first item of its
+/// `file_list`.
+fn save_file_header(root: &Byml) -> Result<&Map> {
+    root.get("file_list")
+        .and_then(|list| list.as_array().ok())
+        .and_then(|list| list.first())
+        .ok_or(Error::InvalidData("Save data format file has no file_list"))?
+        .as_map()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> GameData {
+        let mut data = GameData::new(Endian::Big);
+        let mut flag = Flag::new("Open_StartPoint", FlagType::Bool);
+        flag.set_save(true);
+        flag.set_init_value(FlagValue::Bool(true)).unwrap();
+        data.insert(flag).unwrap();
+        let mut flag = Flag::new("KorokNuts_Count", FlagType::S32);
+        flag.set_revival(true);
+        flag.set_reset_type(1);
+        data.insert(flag).unwrap();
+        let mut flag = Flag::new("PlayerSavePos", FlagType::Vector3f);
+        flag.set_save(true);
+        flag.set_init_value(FlagValue::Vector3f(Vector3f {
+            x: -1130.0,
+            y: 238.0,
+            z: 1930.0,
+        }))
+        .unwrap();
+        data.insert(flag).unwrap();
+        let mut flag = Flag::new("PorchItem", FlagType::String64Array);
+        flag.set_init_value(FlagValue::StringArray(vec!["Weapon_Sword_001".into(); 3]))
+            .unwrap();
+        data.insert(flag).unwrap();
+        data
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = sample();
+        let sarc_data = data.to_binary();
+        let sarc = Sarc::new(&sarc_data).unwrap();
+        assert!(sarc.get("/bool_data_0.bgdata").is_some());
+        assert!(sarc.get("/revival_s32_data_0.bgdata").is_some());
+        assert!(sarc.get("/string64_array_data_0.bgdata").is_some());
+        let data2 = GameData::from_sarc(&sarc).unwrap();
+        assert_eq!(data, data2);
+
+        let flag = data2.get("PlayerSavePos").unwrap();
+        assert_eq!(flag.flag_type(), FlagType::Vector3f);
+        assert_eq!(
+            flag.init_value().unwrap(),
+            FlagValue::Vector3f(Vector3f {
+                x: -1130.0,
+                y: 238.0,
+                z: 1930.0,
+            })
+        );
+        let flag = data2
+            .get_by_hash(GameData::hash_name("KorokNuts_Count"))
+            .unwrap();
+        assert!(flag.is_revival());
+        assert_eq!(flag.reset_type(), 1);
+        assert_eq!(
+            data2.get("PorchItem").unwrap().init_value().unwrap(),
+            FlagValue::StringArray(vec!["Weapon_Sword_001".into(); 3])
+        );
+        assert!(data2.get("Missing").is_none());
+    }
+
+    #[test]
+    fn edit() {
+        let mut data = sample();
+        let flag = data.get_mut("Open_StartPoint").unwrap();
+        assert!(flag.set_init_value(FlagValue::S32(1)).is_err());
+        assert!(flag.set("DataName", "Other").is_err());
+        assert!(flag.set("InitValue", "Other").is_err());
+        assert!(flag.set("InitValue", 0).unwrap().is_some());
+        assert_eq!(flag.init_value().unwrap(), FlagValue::Bool(false));
+
+        let mut flag = Flag::new("Location_Test", FlagType::String32);
+        assert!(flag
+            .set_init_value(FlagValue::String("x".repeat(32)))
+            .is_err());
+        flag.set_init_value(FlagValue::String("Hateno".into()))
+            .unwrap();
+        assert_eq!(data.insert(flag.clone()).unwrap(), None);
+        assert_eq!(data.insert(flag).unwrap().unwrap().name(), "Location_Test");
+        assert_eq!(data.len(), 5);
+        assert!(data.remove("Location_Test").is_some());
+        assert!(data.remove("Location_Test").is_none());
+        assert_eq!(data.len(), 4);
+    }
+
+    #[test]
+    fn split() {
+        let mut data = GameData::new(Endian::Little);
+        for i in 0..FLAGS_PER_FILE + 10 {
+            data.insert(Flag::new(&format!("Flag_{i}"), FlagType::Bool))
+                .unwrap();
+        }
+        let sarc_data = data.to_binary();
+        let sarc = Sarc::new(&sarc_data).unwrap();
+        assert_eq!(sarc.len(), 2);
+        let first = Byml::from_binary(sarc.get_data("/bool_data_0.bgdata").unwrap()).unwrap();
+        let hashes = first["bool_data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|flag| hash_value(&flag["HashValue"]).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(hashes.len(), FLAGS_PER_FILE);
+        assert!(hashes.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(GameData::from_sarc(&sarc).unwrap(), data);
+    }
+
+    #[test]
+    fn savedata() {
+        let save_file = |name: &str, flags: Vec<Byml>| {
+            let header: Map = [
+                ("IsCommon", Byml::Bool(false)),
+                ("IsCommonAtSameAccount", Byml::Bool(false)),
+                ("IsSaveSecureCode", Byml::Bool(true)),
+                ("file_name", name.into()),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.into(), v))
+            .collect();
+            let save_info: Map = [
+                ("directory_num", Byml::I32(8)),
+                ("is_build_machine", Byml::Bool(true)),
+                ("revision", Byml::I32(18203)),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.into(), v))
+            .collect();
+            Byml::Map(
+                [
+                    (
+                        "file_list".into(),
+                        Byml::Array(vec![Byml::Map(header), Byml::Array(flags)]),
+                    ),
+                    (
+                        "save_info".into(),
+                        Byml::Array(vec![Byml::Map(save_info)]),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            )
+            .to_binary(Endian::Big)
+        };
+        let caption = save_file("caption.sav", vec![]);
+        let old = SarcWriter::new(Endian::Big)
+            .with_file("/saveformat_0.bgsvdata", save_file(SAVE_FILE_NAME, vec![]))
+            .with_file("/saveformat_1.bgsvdata", caption.clone())
+            .to_binary();
+
+        let mut data = sample();
+        data.get_mut("PorchItem").unwrap().set_save(true);
+        data.remove("Open_StartPoint");
+        let new = data
+            .update_savedata(&Sarc::new(&old).unwrap())
+            .unwrap()
+            .to_binary();
+        let new = Sarc::new(&new).unwrap();
+        assert_eq!(new.len(), 2);
+        assert_eq!(new.get_data("/saveformat_1.bgsvdata"), Some(caption.as_slice()));
+        let format = Byml::from_binary(new.get_data("/saveformat_0.bgsvdata").unwrap()).unwrap();
+        let names = format["file_list"][1]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|flag| flag["DataName"].as_string().unwrap().as_str())
+            .collect::<Vec<_>>();
+        let expected = data.save_flags().map(Flag::name).collect::<Vec<_>>();
+        assert_eq!(names, expected);
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"PorchItem"));
+        assert_eq!(format["save_info"][0]["revision"], Byml::I32(18203));
+    }
+}
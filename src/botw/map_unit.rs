@@ -0,0 +1,763 @@
+use super::{hash_value, take};
+use crate::{
+    byml::{Byml, Map},
+    types::Vector3f,
+    util::crc32,
+    Endian, Error, Result,
+};
+
+/// A map unit (`*_Static.smubin` or `*_Dynamic.smubin`), the placement file
+/// for one map area or dungeon.
+///
+/// The objects and rails are kept in their original order. Map units from the
+/// game have objects sorted by hash ID; [`MapUnit::insert_object`] keeps that
+/// order. Any other root keys are kept as is, and `Objs` and `Rails` are only
+/// written if they were present or are not empty.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MapUnit {
+    /// The placed objects (`Objs`).
+    pub objs: Vec<MapObject>,
+    /// The rails (`Rails`).
+    pub rails: Vec<Rail>,
+    // The other root keys. `Objs` and `Rails` stay here as empty arrays if
+    // they were present, to keep their position.
+    extra: Map,
+}
+
+/// A placed object in a map unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapObject(Map);
+
+/// A rail in a map unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rail(Map);
+
+/// Which list a link points into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkTarget {
+    /// An object (`LinksToObj`).
+    Obj,
+    /// A rail (`LinksToRail`).
+    Rail,
+}
+
+impl LinkTarget {
+    #[inline]
+    fn key(self) -> &'static str {
+        match self {
+            LinkTarget::Obj => "LinksToObj",
+            LinkTarget::Rail => "LinksToRail",
+        }
+    }
+}
+
+/// A link from an object or rail to another one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Link {
+    /// The link type (`DefinitionName`), e.g. `BasicSig`.
+    pub definition_name: String,
+    /// The hash ID of the destination (`DestUnitHashId`).
+    pub dest_hash_id: u32,
+    /// Whether the destination is an object or a rail.
+    pub target: LinkTarget,
+}
+
+/// A link whose destination could not be found, from
+/// [`MapUnit::validate_links`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BrokenLink {
+    /// The hash ID of the object or rail which has the link.
+    pub source: u32,
+    /// The broken link.
+    pub link:   Link,
+}
+
+impl std::fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} link from {:#010x} to missing {} {:#010x}",
+            self.link.definition_name,
+            self.source,
+            match self.link.target {
+                LinkTarget::Obj => "object",
+                LinkTarget::Rail => "rail",
+            },
+            self.link.dest_hash_id
+        )
+    }
+}
+
+impl MapUnit {
+    /// Create an empty map unit.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Wrap a map unit BYML document.
+    ///
+    /// Returns an error if the document is not a map, or if an object or rail
+    /// has no `HashId` (or an object has no `UnitConfigName`). Missing `Objs`
+    /// or `Rails` arrays are treated as empty.
+    pub fn from_byml(byml: Byml) -> Result<Self> {
+        let Byml::Map(mut root) = byml else {
+            return Err(Error::TypeError(byml.type_name(), "Map"));
+        };
+        let objs = take_array(&mut root, "Objs")?
+            .into_iter()
+            .map(MapObject::try_from)
+            .collect::<Result<_>>()?;
+        let rails = take_array(&mut root, "Rails")?
+            .into_iter()
+            .map(Rail::try_from)
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            objs,
+            rails,
+            extra: root,
+        })
+    }
+
+    /// Parse a map unit from binary BYML data.
+    pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::from_byml(Byml::from_binary(data)?)
+    }
+
+    /// Convert the map unit back into a BYML document.
+    pub fn to_byml(&self) -> Byml {
+        self.clone().into_byml()
+    }
+
+    /// Convert the map unit into a BYML document.
+    pub fn into_byml(self) -> Byml {
+        let mut root = self.extra;
+        let mut put = |key: &str, array: Vec<Byml>| {
+            if !array.is_empty() || root.contains_key(key) {
+                root.insert(key.into(), Byml::Array(array));
+            }
+        };
+        put("Objs", self.objs.into_iter().map(Byml::from).collect());
+        put("Rails", self.rails.into_iter().map(Byml::from).collect());
+        Byml::Map(root)
+    }
+
+    /// Serialize the map unit to binary BYML data.
+    pub fn to_binary(&self, endian: Endian) -> Vec<u8> {
+        self.to_byml().to_binary(endian)
+    }
+
+    /// Get an object by hash ID.
+    pub fn object(&self, hash_id: u32) -> Option<&MapObject> {
+        self.objs.iter().find(|obj| obj.hash_id() == hash_id)
+    }
+
+    /// Get a mutable reference to an object by hash ID.
+    pub fn object_mut(&mut self, hash_id: u32) -> Option<&mut MapObject> {
+        self.objs.iter_mut().find(|obj| obj.hash_id() == hash_id)
+    }
+
+    /// Get a rail by hash ID.
+    pub fn rail(&self, hash_id: u32) -> Option<&Rail> {
+        self.rails.iter().find(|rail| rail.hash_id() == hash_id)
+    }
+
+    /// Get a mutable reference to a rail by hash ID.
+    pub fn rail_mut(&mut self, hash_id: u32) -> Option<&mut Rail> {
+        self.rails.iter_mut().find(|rail| rail.hash_id() == hash_id)
+    }
+
+    /// Add an object, keeping the objects sorted by hash ID. If an object with
+    /// the same hash ID exists, it is replaced and the old object is
+    /// returned.
+    pub fn insert_object(&mut self, obj: MapObject) -> Option<MapObject> {
+        let hash_id = obj.hash_id();
+        if let Some(old) = self.object_mut(hash_id) {
+            return Some(std::mem::replace(old, obj));
+        }
+        let index = self.objs.partition_point(|o| o.hash_id() < hash_id);
+        self.objs.insert(index, obj);
+        None
+    }
+
+    /// Remove an object by hash ID, returning it if it was present.
+    pub fn remove_object(&mut self, hash_id: u32) -> Option<MapObject> {
+        let index = self.objs.iter().position(|obj| obj.hash_id() == hash_id)?;
+        Some(self.objs.remove(index))
+    }
+
+    /// Iterate over the hash IDs of all objects and rails.
+    pub fn hash_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.objs
+            .iter()
+            .map(MapObject::hash_id)
+            .chain(self.rails.iter().map(Rail::hash_id))
+    }
+
+    /// Find a hash ID which is not used in this map unit or any of `others`.
+    ///
+    /// The static and dynamic units of an area share one set of hash IDs, and
+    /// links may point from one into the other, so the units for the same
+    /// area should be passed as `others`. The new ID is one greater than the
+    /// largest ID in use, or the smallest unused ID if that would overflow.
+    pub fn allocate_hash_id(&self, others: &[&MapUnit]) -> u32 {
+        let used: rustc_hash::FxHashSet<u32> = std::iter::once(self)
+            .chain(others.iter().copied())
+            .flat_map(MapUnit::hash_ids)
+            .collect();
+        match used.iter().max() {
+            None => 1,
+            Some(&max) if max < u32::MAX => max + 1,
+            Some(_) => (1..).find(|id| !used.contains(id)).unwrap_or_default(),
+        }
+    }
+
+    /// Check that all links from objects and rails in this map unit point to
+    /// an object or rail which exists, either here or in one of `others`.
+    pub fn validate_links(&self, others: &[&MapUnit]) -> Vec<BrokenLink> {
+        let units = || std::iter::once(self).chain(others.iter().copied());
+        let objs: rustc_hash::FxHashSet<u32> = units()
+            .flat_map(|unit| unit.objs.iter().map(MapObject::hash_id))
+            .collect();
+        let rails: rustc_hash::FxHashSet<u32> = units()
+            .flat_map(|unit| unit.rails.iter().map(Rail::hash_id))
+            .collect();
+        self.objs
+            .iter()
+            .map(|obj| (obj.hash_id(), obj.links()))
+            .chain(self.rails.iter().map(|rail| (rail.hash_id(), rail.links())))
+            .flat_map(|(source, links)| {
+                links
+                    .into_iter()
+                    .filter(|link| {
+                        !match link.target {
+                            LinkTarget::Obj => &objs,
+                            LinkTarget::Rail => &rails,
+                        }
+                        .contains(&link.dest_hash_id)
+                    })
+                    .map(move |link| BrokenLink { source, link })
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<Byml> for MapUnit {
+    type Error = Error;
+
+    fn try_from(byml: Byml) -> Result<Self> {
+        Self::from_byml(byml)
+    }
+}
+
+impl From<MapUnit> for Byml {
+    fn from(unit: MapUnit) -> Self {
+        unit.into_byml()
+    }
+}
+
+impl MapObject {
+    /// Create an object with a hash ID and unit config name, placed at the
+    /// origin.
+    pub fn new(hash_id: u32, unit_config_name: &str) -> Self {
+        let mut map = Map::default();
+        map.insert("HashId".into(), Byml::U32(hash_id));
+        map.insert("SRTHash".into(), Byml::I32(0));
+        map.insert("Translate".into(), vector_node(ZERO));
+        map.insert(
+            "UnitConfigName".into(),
+            Byml::String(unit_config_name.into()),
+        );
+        let mut obj = Self(map);
+        obj.update_srt_hash();
+        obj
+    }
+
+    /// The actor name (`UnitConfigName`).
+    pub fn unit_config_name(&self) -> &str {
+        match self.0.get("UnitConfigName") {
+            Some(Byml::String(name)) => name.as_str(),
+            _ => "",
+        }
+    }
+
+    /// Set the actor name (`UnitConfigName`).
+    pub fn set_unit_config_name(&mut self, name: &str) {
+        self.0
+            .insert("UnitConfigName".into(), Byml::String(name.into()));
+    }
+}
+
+impl TryFrom<Byml> for MapObject {
+    type Error = Error;
+
+    fn try_from(byml: Byml) -> Result<Self> {
+        let map = placement_map(byml)?;
+        match map.get("UnitConfigName") {
+            Some(Byml::String(_)) => Ok(Self(map)),
+            Some(other) => Err(Error::TypeError(other.type_name(), "String")),
+            None => Err(Error::InvalidData("Map object has no UnitConfigName")),
+        }
+    }
+}
+
+impl Rail {
+    /// Create an empty rail with a hash ID.
+    pub fn new(hash_id: u32) -> Self {
+        let mut map = Map::default();
+        map.insert("HashId".into(), Byml::U32(hash_id));
+        map.insert("IsClosed".into(), Byml::Bool(false));
+        map.insert("RailPoints".into(), Byml::Array(vec![]));
+        Self(map)
+    }
+
+    /// The rail type name (`UnitConfigName`), if any.
+    pub fn unit_config_name(&self) -> Option<&str> {
+        match self.0.get("UnitConfigName") {
+            Some(Byml::String(name)) => Some(name.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Whether the rail loops back to its first point (`IsClosed`).
+    pub fn is_closed(&self) -> bool {
+        matches!(self.0.get("IsClosed"), Some(Byml::Bool(true)))
+    }
+
+    /// Set whether the rail loops back to its first point.
+    pub fn set_closed(&mut self, closed: bool) {
+        self.0.insert("IsClosed".into(), Byml::Bool(closed));
+    }
+
+    /// The positions of the rail points.
+    pub fn points(&self) -> Result<Vec<Vector3f>> {
+        match self.0.get("RailPoints") {
+            None => Ok(vec![]),
+            Some(points) => {
+                points
+                    .as_array()?
+                    .iter()
+                    .map(|point| {
+                        match point.as_map()?.get("Translate") {
+                            Some(translate) => vector(translate, 0.0),
+                            None => Err(Error::InvalidData("Rail point has no Translate")),
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl TryFrom<Byml> for Rail {
+    type Error = Error;
+
+    fn try_from(byml: Byml) -> Result<Self> {
+        placement_map(byml).map(Self)
+    }
+}
+
+macro_rules! impl_placement {
+    ($type:ty) => {
+        impl $type {
+            /// The hash ID (`HashId`), unique within a map area.
+            pub fn hash_id(&self) -> u32 {
+                self.0
+                    .get("HashId")
+                    .and_then(hash_value)
+                    .unwrap_or_default()
+            }
+
+            /// Set the hash ID (`HashId`).
+            pub fn set_hash_id(&mut self, hash_id: u32) {
+                self.0.insert("HashId".into(), Byml::U32(hash_id));
+            }
+
+            /// Get a field by key.
+            #[inline]
+            pub fn get(&self, key: &str) -> Option<&Byml> {
+                self.0.get(key)
+            }
+
+            /// Set a field, returning the old value. Returns an error for
+            /// `HashId`, which must be set with `set_hash_id`.
+            pub fn set(&mut self, key: &str, value: impl Into<Byml>) -> Result<Option<Byml>> {
+                if key == "HashId" {
+                    return Err(Error::InvalidData("Use set_hash_id to change hash IDs"));
+                }
+                Ok(self.0.insert(key.into(), value.into()))
+            }
+
+            /// The underlying map of fields.
+            #[inline]
+            pub fn as_map(&self) -> &Map {
+                &self.0
+            }
+
+            /// The instance parameters (`!Parameters`), if any.
+            pub fn params(&self) -> Option<&Map> {
+                self.0.get("!Parameters").and_then(|p| p.as_map().ok())
+            }
+
+            /// A mutable reference to the instance parameters
+            /// (`!Parameters`), which are added if missing.
+            pub fn params_mut(&mut self) -> Result<&mut Map> {
+                self.0
+                    .entry("!Parameters".into())
+                    .or_insert_with(|| Byml::Map(Default::default()))
+                    .as_mut_map()
+            }
+
+            /// The position (`Translate`).
+            pub fn translate(&self) -> Result<Vector3f> {
+                self.0
+                    .get("Translate")
+                    .map_or(Ok(ZERO), |value| vector(value, 0.0))
+            }
+
+            /// The rotation in radians (`Rotate`). Placements which only
+            /// rotate around the Y axis store a single value.
+            pub fn rotate(&self) -> Result<Vector3f> {
+                match self.0.get("Rotate") {
+                    None => Ok(ZERO),
+                    Some(Byml::Float(y)) => {
+                        Ok(Vector3f {
+                            x: 0.0,
+                            y: *y,
+                            z: 0.0,
+                        })
+                    }
+                    Some(value) => vector(value, 0.0),
+                }
+            }
+
+            /// The scale (`Scale`). Placements with a uniform scale store a
+            /// single value, and those with a scale of 1 omit it.
+            pub fn scale(&self) -> Result<Vector3f> {
+                match self.0.get("Scale") {
+                    None => Ok(ONE),
+                    Some(Byml::Float(s)) => {
+                        Ok(Vector3f {
+                            x: *s,
+                            y: *s,
+                            z: *s,
+                        })
+                    }
+                    Some(value) => vector(value, 1.0),
+                }
+            }
+
+            /// Set the position (`Translate`). This does not update the SRT
+            /// hash; see [`Self::update_srt_hash`].
+            pub fn set_translate(&mut self, translate: Vector3f) {
+                self.0.insert("Translate".into(), vector_node(translate));
+            }
+
+            /// Set the rotation in radians (`Rotate`), in the compact form if
+            /// it only rotates around the Y axis. This does not update the SRT
+            /// hash; see [`Self::update_srt_hash`].
+            pub fn set_rotate(&mut self, rotate: Vector3f) {
+                let value = if rotate.x == 0.0 && rotate.z == 0.0 {
+                    Byml::Float(rotate.y)
+                } else {
+                    vector_node(rotate)
+                };
+                self.0.insert("Rotate".into(), value);
+            }
+
+            /// Set the scale (`Scale`), in the compact form if it is uniform.
+            /// This does not update the SRT hash; see
+            /// [`Self::update_srt_hash`].
+            pub fn set_scale(&mut self, scale: Vector3f) {
+                if scale.x == scale.y && scale.y == scale.z {
+                    if scale.x == 1.0 {
+                        take(&mut self.0, "Scale");
+                    } else {
+                        self.0.insert("Scale".into(), Byml::Float(scale.x));
+                    }
+                } else {
+                    self.0.insert("Scale".into(), vector_node(scale));
+                }
+            }
+
+            /// The SRT hash (`SRTHash`), if any.
+            pub fn srt_hash(&self) -> Option<u32> {
+                self.0.get("SRTHash").and_then(hash_value)
+            }
+
+            /// Compute a change marker for the current scale, rotation and
+            /// position, for use as the SRT hash.
+            ///
+            /// **This is not the game's SRT hash algorithm**, which is not
+            /// known, and it will not reproduce the hashes in unmodified map
+            /// units. It is the CRC32 of the scale, rotation and position as
+            /// little endian floats, which is only useful because the game
+            /// treats `SRTHash` as a marker of whether a placement has
+            /// changed.
+            pub fn compute_srt_hash(&self) -> Result<u32> {
+                let mut bytes = [0u8; 36];
+                let values = [self.scale()?, self.rotate()?, self.translate()?];
+                for (chunk, value) in bytes.chunks_exact_mut(12).zip(values) {
+                    chunk[..4].copy_from_slice(&value.x.to_le_bytes());
+                    chunk[4..8].copy_from_slice(&value.y.to_le_bytes());
+                    chunk[8..].copy_from_slice(&value.z.to_le_bytes());
+                }
+                Ok(crc32(&bytes))
+            }
+
+            /// Replace the SRT hash (`SRTHash`) with the change marker from
+            /// [`Self::compute_srt_hash`] after changing the placement.
+            /// Invalid placement values leave it unchanged.
+            pub fn update_srt_hash(&mut self) {
+                if let Ok(hash) = self.compute_srt_hash() {
+                    self.0.insert("SRTHash".into(), Byml::I32(hash as i32));
+                }
+            }
+
+            /// The links from this placement to other objects and rails
+            /// (`LinksToObj` and `LinksToRail`). Malformed links are skipped.
+            pub fn links(&self) -> Vec<Link> {
+                [LinkTarget::Obj, LinkTarget::Rail]
+                    .into_iter()
+                    .flat_map(|target| {
+                        self.0
+                            .get(target.key())
+                            .and_then(|links| links.as_array().ok())
+                            .unwrap_or_default()
+                            .iter()
+                            .filter_map(move |link| {
+                                let link = link.as_map().ok()?;
+                                Some(Link {
+                                    definition_name: link
+                                        .get("DefinitionName")?
+                                        .as_string()
+                                        .ok()?
+                                        .to_string(),
+                                    dest_hash_id: hash_value(link.get("DestUnitHashId")?)?,
+                                    target,
+                                })
+                            })
+                    })
+                    .collect()
+            }
+
+            /// Add a link to another object or rail.
+            pub fn add_link(&mut self, link: Link) -> Result<()> {
+                let mut entry = Map::default();
+                entry.insert(
+                    "DefinitionName".into(),
+                    Byml::String(link.definition_name.into()),
+                );
+                entry.insert("DestUnitHashId".into(), Byml::U32(link.dest_hash_id));
+                self.0
+                    .entry(link.target.key().into())
+                    .or_insert_with(|| Byml::Array(vec![]))
+                    .as_mut_array()?
+                    .push(Byml::Map(entry));
+                Ok(())
+            }
+
+            /// Remove all links to a hash ID, returning the number removed.
+            pub fn remove_links_to(&mut self, dest_hash_id: u32) -> usize {
+                let mut removed = 0;
+                for target in [LinkTarget::Obj, LinkTarget::Rail] {
+                    if let Some(Byml::Array(links)) = self.0.get_mut(target.key()) {
+                        let len = links.len();
+                        links.retain(|link| {
+                            link.as_map()
+                                .ok()
+                                .and_then(|l| l.get("DestUnitHashId"))
+                                .and_then(hash_value)
+                                != Some(dest_hash_id)
+                        });
+                        removed += len - links.len();
+                    }
+                }
+                removed
+            }
+        }
+
+        impl From<$type> for Byml {
+            fn from(value: $type) -> Self {
+                Byml::Map(value.0)
+            }
+        }
+    };
+}
+
+impl_placement!(MapObject);
+impl_placement!(Rail);
+
+const ZERO: Vector3f = Vector3f {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+const ONE: Vector3f = Vector3f {
+    x: 1.0,
+    y: 1.0,
+    z: 1.0,
+};
+
+/// Check that a placement is a map with a hash ID.
+fn placement_map(byml: Byml) -> Result<Map> {
+    let Byml::Map(map) = byml else {
+        return Err(Error::TypeError(byml.type_name(), "Map"));
+    };
+    match map.get("HashId") {
+        Some(Byml::U32(_) | Byml::I32(_)) => Ok(map),
+        Some(other) => Err(Error::TypeError(other.type_name(), "U32")),
+        None => Err(Error::InvalidData("Map placement has no HashId")),
+    }
+}
+
+/// Take the items of an array in a map, leaving it empty.
+fn take_array(map: &mut Map, key: &str) -> Result<Vec<Byml>> {
+    match map.get_mut(key) {
+        None => Ok(vec![]),
+        Some(Byml::Array(array)) => Ok(std::mem::take(array)),
+        Some(other) => Err(Error::TypeError(other.type_name(), "Array")),
+    }
+}
+
+/// Read a vector stored as an array of up to three floats. Missing components
+/// take the default.
+fn vector(value: &Byml, default: f32) -> Result<Vector3f> {
+    let array = value.as_array()?;
+    if array.len() > 3 {
+        return Err(Error::InvalidDataD(format!(
+            "Expected a vector of 3 floats, found {} values",
+            array.len()
+        )));
+    }
+    let component = |i: usize| array.get(i).map_or(Ok(default), Byml::as_float);
+    Ok(Vector3f {
+        x: component(0)?,
+        y: component(1)?,
+        z: component(2)?,
+    })
+}
+
+fn vector_node(value: Vector3f) -> Byml {
+    Byml::Array(vec![
+        Byml::Float(value.x),
+        Byml::Float(value.y),
+        Byml::Float(value.z),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load() -> MapUnit {
+        MapUnit::from_binary(std::fs::read("test/byml/A-1_Dynamic.byml").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let data = std::fs::read("test/byml/A-1_Dynamic.byml").unwrap();
+        let byml = Byml::from_binary(data).unwrap();
+        let unit = MapUnit::from_byml(byml.clone()).unwrap();
+        assert_eq!(unit.objs.len(), 545);
+        assert!(unit.rails.is_empty());
+        assert_eq!(unit.to_byml(), byml);
+
+        let obj = unit.object(0x00af0d14).unwrap();
+        assert_eq!(obj.unit_config_name(), "Obj_TreeConiferous_A_Snow_01");
+        assert_eq!(obj.srt_hash(), Some(-135675777i32 as u32));
+        assert_eq!(obj.translate().unwrap(), Vector3f {
+            x: -4046.6135,
+            y: 300.5849,
+            z: -3327.3423,
+        });
+        assert_eq!(obj.rotate().unwrap(), Vector3f {
+            x: 0.0,
+            y: 3.0060024,
+            z: 0.0,
+        });
+        assert_eq!(obj.scale().unwrap(), ONE);
+        assert_eq!(
+            obj.params().unwrap()["DropTable"],
+            Byml::String("Normal".into())
+        );
+        let obj = unit.object(0x05212fb6).unwrap();
+        assert_eq!(obj.scale().unwrap(), Vector3f {
+            x: 20.0,
+            y: 75.998215,
+            z: 20.0,
+        });
+        assert!(unit.validate_links(&[]).is_empty());
+    }
+
+    #[test]
+    fn edit() {
+        let mut unit = load();
+        let count = unit.objs.len();
+        let id = unit.allocate_hash_id(&[]);
+        assert!(unit.hash_ids().all(|used| used < id));
+
+        let mut obj = MapObject::new(id, "Enemy_Lizalfos_Electric");
+        let old_hash = obj.srt_hash();
+        obj.set_translate(Vector3f {
+            x: -4000.0,
+            y: 300.0,
+            z: -3300.0,
+        });
+        obj.set_rotate(Vector3f {
+            x: 0.0,
+            y: 1.5,
+            z: 0.0,
+        });
+        obj.set_scale(ONE);
+        obj.update_srt_hash();
+        assert_ne!(obj.srt_hash(), old_hash);
+        assert_eq!(obj.get("Rotate"), Some(&Byml::Float(1.5)));
+        assert_eq!(obj.get("Scale"), None);
+        obj.add_link(Link {
+            definition_name: "BasicSig".into(),
+            dest_hash_id: 0x00af0d14,
+            target: LinkTarget::Obj,
+        })
+        .unwrap();
+        obj.add_link(Link {
+            definition_name: "Reference".into(),
+            dest_hash_id: 0x1234,
+            target: LinkTarget::Rail,
+        })
+        .unwrap();
+        assert!(obj.set("HashId", 0u32).is_err());
+        assert!(unit.insert_object(obj).is_none());
+        assert_eq!(unit.objs.len(), count + 1);
+        assert_eq!(unit.objs.last().unwrap().hash_id(), id);
+
+        let broken = unit.validate_links(&[]);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].source, id);
+        assert_eq!(broken[0].link.dest_hash_id, 0x1234);
+
+        let mut other = MapUnit::new();
+        other.rails.push(Rail::new(0x1234));
+        assert!(unit.validate_links(&[&other]).is_empty());
+        assert!(unit.allocate_hash_id(&[&other]) > id);
+
+        assert_eq!(unit.object_mut(id).unwrap().remove_links_to(0x1234), 1);
+        assert!(unit.validate_links(&[]).is_empty());
+        let unit2 = MapUnit::from_binary(unit.to_binary(Endian::Big)).unwrap();
+        assert_eq!(unit, unit2);
+
+        unit.remove_object(0x00af0d14);
+        assert_eq!(unit.validate_links(&[]).len(), 1);
+    }
+
+    #[test]
+    fn missing_lists() {
+        assert_eq!(MapUnit::new().into_byml(), Byml::Map(Map::default()));
+        let byml = crate::byml!({"LocationPosX": 1.0, "Objs": [], "LocationPosZ": 2.0});
+        let mut unit = MapUnit::from_byml(byml.clone()).unwrap();
+        assert_eq!(unit.to_byml(), byml);
+        unit.rails.push(Rail::new(0x1234));
+        let byml = unit.into_byml();
+        let keys: Vec<_> = byml.as_map().unwrap().keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, ["LocationPosX", "Objs", "LocationPosZ", "Rails"]);
+    }
+}
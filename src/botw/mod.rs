@@ -0,0 +1,54 @@
+//! Typed wrappers for *Breath of the Wild* data files.
+//!
+//! Many of BOTW's game data files are plain BYML documents with a fixed
+//! layout and invariants that the generic [`Byml`](crate::byml::Byml) type
+//! knows nothing about, such as arrays which must be kept sorted by the CRC32
+//! hash of a name. The types in this module wrap those documents and keep
+//! their invariants intact while they are edited:
+//!
+//! - [`ActorInfo`]: the actor info table (`ActorInfo.product.sbyml`)
+//! - [`MapUnit`]: map unit placement files (`*_Static.smubin` and `*_Dynamic.smubin`)
+//! - [`GameData`]: the game data flags (`gamedata.ssarc`), with the `sarc`
+//!   feature
+//! - [`Save`]: game data save files (`game_data.sav`), with the `sarc`
+//!   feature
+//!
+//! Sample usage:
+//! ```
+//! # use roead::botw::ActorInfo;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let data = std::fs::read("test/byml/ActorInfo.product.byml")?;
+//! let info = ActorInfo::from_binary(data)?;
+//! let actor = info.get("Enemy_Lizalfos_Electric").unwrap();
+//! assert_eq!(actor.profile(), Some("Enemy"));
+//! # Ok(())
+//! # }
+//! ```
+use crate::byml::{Byml, Map};
+
+mod actor_info;
+#[cfg(feature = "sarc")]
+mod game_data;
+mod map_unit;
+#[cfg(feature = "sarc")]
+mod save;
+pub use actor_info::{Actor, ActorInfo, ActorMut};
+#[cfg(feature = "sarc")]
+pub use game_data::{Flag, FlagType, FlagValue, GameData};
+pub use map_unit::{BrokenLink, Link, LinkTarget, MapObject, MapUnit, Rail};
+#[cfg(feature = "sarc")]
+pub use save::{Save, SaveEntry};
+
+/// Read a hash, which the game stores as either `I32` or `U32`.
+fn hash_value(value: &Byml) -> Option<u32> {
+    match value {
+        Byml::I32(hash) => Some(*hash as u32),
+        Byml::U32(hash) => Some(*hash),
+        _ => None,
+    }
+}
+
+/// Remove a key from a map, keeping the order of the other keys.
+fn take(map: &mut Map, key: &str) -> Option<Byml> {
+    map.shift_remove(key)
+}
@@ -0,0 +1,572 @@
+use super::{Flag, FlagType, FlagValue, GameData};
+use crate::{
+    types::{Vector2f, Vector3f, Vector4f},
+    Endian, Error, Result,
+};
+
+/// The size of the header: the save version, `0xFFFFFFFF` and one more
+/// word.
+const HEADER_SIZE: usize = 0xC;
+
+/// A game data save file (`game_data.sav`).
+///
+/// The file is a flat list of (flag hash, value) word pairs. Values larger
+/// than a word, such as strings and vectors, and each item of an array flag
+/// take several consecutive pairs with the same hash. The pairs carry no type
+/// information, so reading typed values requires the flag definitions from a
+/// [`GameData`] table.
+///
+/// Wii U saves are big endian and Switch saves little endian; the endianness
+/// is detected when parsing and kept when writing. Entries keep their order,
+/// so an unedited save is written back byte for byte.
+///
+/// Sample usage:
+/// ```no_run
+/// # use roead::botw::{GameData, Save, FlagValue};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // The decompressed `gamedata.ssarc`
+/// let gamedata = GameData::from_binary(std::fs::read("gamedata.sarc")?)?;
+/// let mut save = Save::from_binary(std::fs::read("game_data.sav")?)?;
+/// let rupees = save.get(&gamedata, "CurrentRupee")?;
+/// save.set(&gamedata, "CurrentRupee", FlagValue::S32(999))?;
+/// std::fs::write("game_data.sav", save.to_binary())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Save {
+    endian:  Endian,
+    version: u32,
+    header:  u32,
+    trailer: u32,
+    entries: Vec<SaveEntry>,
+}
+
+/// The raw words stored for one flag in a save file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SaveEntry {
+    /// The flag hash.
+    pub hash:  u32,
+    /// The value words, in file order.
+    pub words: Vec<u32>,
+}
+
+impl FlagType {
+    /// The number of save words used by a value, or by each item of an
+    /// array.
+    const fn save_words(self) -> usize {
+        match self {
+            FlagType::Bool
+            | FlagType::S32
+            | FlagType::F32
+            | FlagType::BoolArray
+            | FlagType::S32Array
+            | FlagType::F32Array => 1,
+            FlagType::String32 => 8,
+            FlagType::String64 | FlagType::String64Array => 16,
+            FlagType::String256 | FlagType::String256Array => 64,
+            FlagType::Vector2f | FlagType::Vector2fArray => 2,
+            FlagType::Vector3f | FlagType::Vector3fArray => 3,
+            FlagType::Vector4f | FlagType::Vector4fArray => 4,
+        }
+    }
+}
+
+impl Save {
+    /// Create an empty save with the given endianness and save version.
+    pub fn new(endian: Endian, version: u32) -> Self {
+        Self {
+            endian,
+            version,
+            header: 1,
+            trailer: 0,
+            entries: vec![],
+        }
+    }
+
+    /// Parse a save file. The endianness is detected from the version
+    /// number.
+    pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        let data = data.as_ref();
+        if data.len() < HEADER_SIZE + 4 || !data.len().is_multiple_of(4) {
+            return Err(Error::InsufficientData(data.len(), HEADER_SIZE + 4));
+        }
+        if data[4..8] != [0xFF; 4] {
+            return Err(Error::InvalidData("Invalid game_data.sav header"));
+        }
+        // Save versions are small numbers, so the first two bytes are zero
+        // in big endian saves.
+        let endian = if data[0] == 0 && data[1] == 0 {
+            Endian::Big
+        } else {
+            Endian::Little
+        };
+        let read = |offset: usize| -> u32 {
+            let bytes = [
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ];
+            match endian {
+                Endian::Big => u32::from_be_bytes(bytes),
+                Endian::Little => u32::from_le_bytes(bytes),
+            }
+        };
+        let end = data.len() - 4;
+        if !(end - HEADER_SIZE).is_multiple_of(8) {
+            return Err(Error::InvalidDataD(format!(
+                "game_data.sav entries have an invalid size {:#x}",
+                end - HEADER_SIZE
+            )));
+        }
+        let mut entries: Vec<SaveEntry> = vec![];
+        for offset in (HEADER_SIZE..end).step_by(8) {
+            let hash = read(offset);
+            let word = read(offset + 4);
+            match entries.last_mut() {
+                Some(entry) if entry.hash == hash => entry.words.push(word),
+                _ => {
+                    entries.push(SaveEntry {
+                        hash,
+                        words: vec![word],
+                    })
+                }
+            }
+        }
+        Ok(Self {
+            endian,
+            version: read(0),
+            header: read(8),
+            trailer: read(end),
+            entries,
+        })
+    }
+
+    /// Serialize the save file.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let words = self.entries.iter().map(|e| e.words.len()).sum::<usize>();
+        let mut buf = Vec::with_capacity(HEADER_SIZE + words * 8 + 4);
+        let mut write = |word: u32| {
+            buf.extend_from_slice(&match self.endian {
+                Endian::Big => word.to_be_bytes(),
+                Endian::Little => word.to_le_bytes(),
+            })
+        };
+        write(self.version);
+        write(0xFFFFFFFF);
+        write(self.header);
+        for entry in &self.entries {
+            for word in &entry.words {
+                write(entry.hash);
+                write(*word);
+            }
+        }
+        write(self.trailer);
+        buf
+    }
+
+    /// The endianness of the save.
+    #[inline]
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// The save version, which identifies the game version.
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Set the save version.
+    #[inline]
+    pub fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    /// Return the number of flags in the save.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the save has no flags.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the raw entries in file order.
+    pub fn entries(&self) -> impl Iterator<Item = &SaveEntry> {
+        self.entries.iter()
+    }
+
+    /// Get the raw value words for a flag hash.
+    pub fn get_raw(&self, hash: u32) -> Option<&[u32]> {
+        self.entries
+            .iter()
+            .find(|e| e.hash == hash)
+            .map(|e| e.words.as_slice())
+    }
+
+    /// Get the value of a flag by name, using `gamedata` for its type.
+    ///
+    /// Returns `None` if the save has no value for the flag, and an error if
+    /// `gamedata` has no such flag or the value has the wrong size for its
+    /// type.
+    pub fn get(&self, gamedata: &GameData, name: &str) -> Result<Option<FlagValue>> {
+        let flag = find_flag(gamedata, name)?;
+        self.get_raw(flag.hash())
+            .map(|words| decode(flag.flag_type(), words, self.endian))
+            .transpose()
+    }
+
+    /// Get a flag definition and its value by hash. Returns `None` if the save
+    /// or `gamedata` has no such flag.
+    pub fn get_by_hash<'g>(
+        &self,
+        gamedata: &'g GameData,
+        hash: u32,
+    ) -> Result<Option<(&'g Flag, FlagValue)>> {
+        let (Some(flag), Some(words)) = (gamedata.get_by_hash(hash), self.get_raw(hash)) else {
+            return Ok(None);
+        };
+        Ok(Some((flag, decode(flag.flag_type(), words, self.endian)?)))
+    }
+
+    /// Set the value of a flag by name, using `gamedata` for its type. Flags
+    /// not yet in the save are added in hash order.
+    ///
+    /// Returns an error if `gamedata` has no such flag or the value does not
+    /// match its type.
+    pub fn set(&mut self, gamedata: &GameData, name: &str, value: FlagValue) -> Result<()> {
+        let flag = find_flag(gamedata, name)?;
+        if !value.is_type(flag.flag_type()) {
+            return Err(Error::InvalidDataD(format!(
+                "Value {value:?} cannot be stored in {} flag {name}",
+                flag.flag_type()
+            )));
+        }
+        let hash = flag.hash();
+        let words = encode(&value, flag.flag_type(), self.endian);
+        match self.entries.iter_mut().find(|e| e.hash == hash) {
+            Some(entry) => entry.words = words,
+            None => {
+                let index = self.entries.partition_point(|e| e.hash < hash);
+                self.entries.insert(index, SaveEntry { hash, words });
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a flag from the save by hash, returning its raw words if it
+    /// was present.
+    pub fn remove(&mut self, hash: u32) -> Option<Vec<u32>> {
+        let index = self.entries.iter().position(|e| e.hash == hash)?;
+        Some(self.entries.remove(index).words)
+    }
+
+    /// Resolve every entry to its flag name and typed value. Entries with no
+    /// flag in `gamedata` resolve to `None`.
+    pub fn resolve<'s, 'g>(
+        &'s self,
+        gamedata: &'g GameData,
+    ) -> impl Iterator<Item = (&'s SaveEntry, Option<Result<(&'g Flag, FlagValue)>>)> + 's
+    where
+        'g: 's,
+    {
+        self.entries.iter().map(move |entry| {
+            let resolved = gamedata.get_by_hash(entry.hash).map(|flag| {
+                decode(flag.flag_type(), &entry.words, self.endian).map(|value| (flag, value))
+            });
+            (entry, resolved)
+        })
+    }
+}
+
+fn find_flag<'g>(gamedata: &'g GameData, name: &str) -> Result<&'g Flag> {
+    gamedata
+        .get(name)
+        .ok_or_else(|| Error::InvalidDataD(format!("Unknown game data flag {name}")))
+}
+
+/// Convert a string word back to its bytes in file order.
+#[inline]
+fn word_bytes(word: u32, endian: Endian) -> [u8; 4] {
+    match endian {
+        Endian::Big => word.to_be_bytes(),
+        Endian::Little => word.to_le_bytes(),
+    }
+}
+
+fn decode_string(words: &[u32], endian: Endian) -> Result<String> {
+    let bytes = words
+        .iter()
+        .flat_map(|w| word_bytes(*w, endian))
+        .take_while(|b| *b != 0)
+        .collect::<Vec<_>>();
+    Ok(std::str::from_utf8(&bytes)?.to_owned())
+}
+
+fn decode(ty: FlagType, words: &[u32], endian: Endian) -> Result<FlagValue> {
+    let size = ty.save_words();
+    let is_array = matches!(
+        ty,
+        FlagType::BoolArray
+            | FlagType::S32Array
+            | FlagType::F32Array
+            | FlagType::String64Array
+            | FlagType::String256Array
+            | FlagType::Vector2fArray
+            | FlagType::Vector3fArray
+            | FlagType::Vector4fArray
+    );
+    if (is_array && !words.len().is_multiple_of(size)) || (!is_array && words.len() != size) {
+        return Err(Error::InvalidDataD(format!(
+            "Save value for {ty} flag has {} words",
+            words.len()
+        )));
+    }
+    let f = |i: usize| f32::from_bits(words[i]);
+    let items = words.chunks(size);
+    Ok(match ty {
+        FlagType::Bool => FlagValue::Bool(words[0] != 0),
+        FlagType::S32 => FlagValue::S32(words[0] as i32),
+        FlagType::F32 => FlagValue::F32(f(0)),
+        FlagType::String32 | FlagType::String64 | FlagType::String256 => {
+            FlagValue::String(decode_string(words, endian)?)
+        }
+        FlagType::Vector2f => FlagValue::Vector2f(Vector2f { x: f(0), y: f(1) }),
+        FlagType::Vector3f => {
+            FlagValue::Vector3f(Vector3f {
+                x: f(0),
+                y: f(1),
+                z: f(2),
+            })
+        }
+        FlagType::Vector4f => {
+            FlagValue::Vector4f(Vector4f {
+                x: f(0),
+                y: f(1),
+                z: f(2),
+                t: f(3),
+            })
+        }
+        FlagType::BoolArray => FlagValue::BoolArray(words.iter().map(|w| *w != 0).collect()),
+        FlagType::S32Array => FlagValue::S32Array(words.iter().map(|w| *w as i32).collect()),
+        FlagType::F32Array => {
+            FlagValue::F32Array(words.iter().map(|w| f32::from_bits(*w)).collect())
+        }
+        FlagType::String64Array | FlagType::String256Array => {
+            FlagValue::StringArray(
+                items
+                    .map(|item| decode_string(item, endian))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        FlagType::Vector2fArray => {
+            FlagValue::Vector2fArray(
+                items
+                    .map(|v| {
+                        Vector2f {
+                            x: f32::from_bits(v[0]),
+                            y: f32::from_bits(v[1]),
+                        }
+                    })
+                    .collect(),
+            )
+        }
+        FlagType::Vector3fArray => {
+            FlagValue::Vector3fArray(
+                items
+                    .map(|v| {
+                        Vector3f {
+                            x: f32::from_bits(v[0]),
+                            y: f32::from_bits(v[1]),
+                            z: f32::from_bits(v[2]),
+                        }
+                    })
+                    .collect(),
+            )
+        }
+        FlagType::Vector4fArray => {
+            FlagValue::Vector4fArray(
+                items
+                    .map(|v| {
+                        Vector4f {
+                            x: f32::from_bits(v[0]),
+                            y: f32::from_bits(v[1]),
+                            z: f32::from_bits(v[2]),
+                            t: f32::from_bits(v[3]),
+                        }
+                    })
+                    .collect(),
+            )
+        }
+    })
+}
+
+fn encode_string(s: &str, size: usize, endian: Endian, out: &mut Vec<u32>) {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.resize(size * 4, 0);
+    out.extend(bytes.chunks(4).map(|chunk| {
+        let chunk = [chunk[0], chunk[1], chunk[2], chunk[3]];
+        match endian {
+            Endian::Big => u32::from_be_bytes(chunk),
+            Endian::Little => u32::from_le_bytes(chunk),
+        }
+    }));
+}
+
+/// Encode a value which has already been checked against the flag type.
+fn encode(value: &FlagValue, ty: FlagType, endian: Endian) -> Vec<u32> {
+    let size = ty.save_words();
+    let mut out = vec![];
+    match value {
+        FlagValue::Bool(b) => out.push(*b as u32),
+        FlagValue::S32(i) => out.push(*i as u32),
+        FlagValue::F32(f) => out.push(f.to_bits()),
+        FlagValue::String(s) => encode_string(s, size, endian, &mut out),
+        FlagValue::Vector2f(v) => out.extend([v.x, v.y].map(f32::to_bits)),
+        FlagValue::Vector3f(v) => out.extend([v.x, v.y, v.z].map(f32::to_bits)),
+        FlagValue::Vector4f(v) => out.extend([v.x, v.y, v.z, v.t].map(f32::to_bits)),
+        FlagValue::BoolArray(items) => out.extend(items.iter().map(|b| *b as u32)),
+        FlagValue::S32Array(items) => out.extend(items.iter().map(|i| *i as u32)),
+        FlagValue::F32Array(items) => out.extend(items.iter().map(|f| f.to_bits())),
+        FlagValue::StringArray(items) => {
+            for s in items {
+                encode_string(s, size, endian, &mut out);
+            }
+        }
+        FlagValue::Vector2fArray(items) => {
+            out.extend(items.iter().flat_map(|v| [v.x, v.y].map(f32::to_bits)))
+        }
+        FlagValue::Vector3fArray(items) => {
+            out.extend(items.iter().flat_map(|v| [v.x, v.y, v.z].map(f32::to_bits)))
+        }
+        FlagValue::Vector4fArray(items) => {
+            out.extend(
+                items
+                    .iter()
+                    .flat_map(|v| [v.x, v.y, v.z, v.t].map(f32::to_bits)),
+            )
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gamedata() -> GameData {
+        let mut data = GameData::new(Endian::Big);
+        for (name, ty) in [
+            ("IsGet_Obj_Maracas", FlagType::Bool),
+            ("CurrentRupee", FlagType::S32),
+            ("PlayerSavePos", FlagType::Vector3f),
+            ("PlayReport_CtrlMode_Ext", FlagType::String32),
+            ("PorchItem", FlagType::String64Array),
+            ("PorchItem_Value1", FlagType::S32Array),
+        ] {
+            data.insert(Flag::new(name, ty)).unwrap();
+        }
+        data
+    }
+
+    #[test]
+    fn roundtrip() {
+        let gamedata = gamedata();
+        for endian in [Endian::Big, Endian::Little] {
+            let mut save = Save::new(endian, 0x471b);
+            save.set(&gamedata, "CurrentRupee", FlagValue::S32(999))
+                .unwrap();
+            save.set(&gamedata, "IsGet_Obj_Maracas", FlagValue::Bool(true))
+                .unwrap();
+            let pos = Vector3f {
+                x: -1130.0,
+                y: 238.0,
+                z: 1930.0,
+            };
+            save.set(&gamedata, "PlayerSavePos", FlagValue::Vector3f(pos))
+                .unwrap();
+            save.set(
+                &gamedata,
+                "PlayReport_CtrlMode_Ext",
+                FlagValue::String("Handheld".into()),
+            )
+            .unwrap();
+            let items = vec!["Weapon_Sword_001".to_string(), "Armor_001_Head".into()];
+            save.set(
+                &gamedata,
+                "PorchItem",
+                FlagValue::StringArray(items.clone()),
+            )
+            .unwrap();
+            save.set(&gamedata, "PorchItem_Value1", FlagValue::S32Array(vec![40, 1]))
+                .unwrap();
+            assert!(save
+                .set(&gamedata, "CurrentRupee", FlagValue::F32(1.0))
+                .is_err());
+            assert!(save.set(&gamedata, "Missing", FlagValue::S32(1)).is_err());
+            assert!(save.entries().map(|e| e.hash).is_sorted());
+
+            let data = save.to_binary();
+            assert_eq!(data.len(), HEADER_SIZE + (1 + 1 + 3 + 8 + 32 + 2) * 8 + 4);
+            let parsed = Save::from_binary(&data).unwrap();
+            assert_eq!(parsed, save);
+            assert_eq!(parsed.endian(), endian);
+            assert_eq!(parsed.version(), 0x471b);
+            assert_eq!(parsed.to_binary(), data);
+            assert_eq!(
+                parsed.get(&gamedata, "CurrentRupee").unwrap(),
+                Some(FlagValue::S32(999))
+            );
+            assert_eq!(
+                parsed.get(&gamedata, "PlayerSavePos").unwrap(),
+                Some(FlagValue::Vector3f(pos))
+            );
+            assert_eq!(
+                parsed.get(&gamedata, "PlayReport_CtrlMode_Ext").unwrap(),
+                Some(FlagValue::String("Handheld".into()))
+            );
+            assert_eq!(
+                parsed.get(&gamedata, "PorchItem").unwrap(),
+                Some(FlagValue::StringArray(items))
+            );
+            let (flag, value) = parsed
+                .get_by_hash(&gamedata, GameData::hash_name("PorchItem_Value1"))
+                .unwrap()
+                .unwrap();
+            assert_eq!(flag.name(), "PorchItem_Value1");
+            assert_eq!(value, FlagValue::S32Array(vec![40, 1]));
+            assert!(parsed
+                .resolve(&gamedata)
+                .all(|(_, flag)| flag.is_some_and(|r| r.is_ok())));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Save::from_binary([0; 8]).is_err());
+        let mut data = Save::new(Endian::Big, 0x471b).to_binary();
+        assert_eq!(data.len(), HEADER_SIZE + 4);
+        data[4] = 0;
+        assert!(Save::from_binary(&data).is_err());
+
+        let gamedata = gamedata();
+        let mut save = Save::new(Endian::Little, 0x471e);
+        save.set(&gamedata, "PlayerSavePos", FlagValue::Vector3f(Vector3f {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }))
+        .unwrap();
+        save.entries[0].words.pop();
+        assert!(save.get(&gamedata, "PlayerSavePos").is_err());
+        assert!(save
+            .remove(GameData::hash_name("PlayerSavePos"))
+            .is_some());
+        assert!(save.is_empty());
+    }
+}
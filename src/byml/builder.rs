@@ -0,0 +1,851 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use binrw::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::{
+    version::{min_type_version, pointer, version_error},
+    *,
+};
+use crate::{
+    util::{align, u24},
+    Endian, Error, Result,
+};
+
+/// Builds a binary BYML document incrementally, for documents too large to
+/// hold as a [`Byml`] tree.
+///
+/// Containers are opened with [`begin_array`](Self::begin_array),
+/// [`begin_map`](Self::begin_map) and so on, filled with
+/// [`value`](Self::value) (preceded by [`key`](Self::key) or
+/// [`hash_key`](Self::hash_key) in maps), and closed with
+/// [`end`](Self::end). Each container is written to a spill region as soon as
+/// it is closed, with strings and keys stored as indices into tables that
+/// are sorted only when the document is finished. Peak memory use is
+/// therefore proportional to the string tables and the open containers, not
+/// the whole document.
+///
+/// By default the spill region is in memory, which already takes a fraction
+/// of the space of a `Byml` tree. Pass a file to
+/// [`with_spill`](Self::with_spill) to keep it on disk instead.
+///
+/// Unlike [`Byml::write`], identical nodes are not shared, which keeps the
+/// builder from having to remember every node it has written. Like
+/// [`Byml::to_binary_checked`], the builder returns an error for node types
+/// which its BYML version does not support, and it also rejects duplicate
+/// keys in a map.
+///
+/// ```
+/// # use roead::{byml::{Byml, BymlBuilder}, Endian};
+/// # fn main() -> roead::Result<()> {
+/// let mut builder = BymlBuilder::new(Endian::Little, 2)?;
+/// builder.begin_map()?;
+/// builder.key("Objs")?;
+/// builder.begin_array()?;
+/// for i in 0..1000 {
+///     builder.begin_map()?;
+///     builder.key("HashId")?;
+///     builder.value(Byml::U32(i))?;
+///     builder.key("UnitConfigName")?;
+///     builder.value("Obj_TreeApple_A_01")?;
+///     builder.end()?;
+/// }
+/// builder.end()?;
+/// builder.end()?;
+/// let data = builder.finish_to_vec()?;
+/// let doc = Byml::from_binary(data)?;
+/// assert_eq!(doc["Objs"].as_array()?.len(), 1000);
+/// # Ok(())
+/// # }
+/// ```
+pub struct BymlBuilder<S = Cursor<Vec<u8>>> {
+    spill:   S,
+    endian:  binrw::Endian,
+    version: u16,
+    strings: Interner,
+    keys:    Interner,
+    stack:   Vec<Frame>,
+    key:     Option<PendingKey>,
+    root:    Option<u32>,
+}
+
+impl<S> std::fmt::Debug for BymlBuilder<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BymlBuilder")
+            .field("endian", &self.endian)
+            .field("version", &self.version)
+            .field("strings", &self.strings.0.len())
+            .field("keys", &self.keys.0.len())
+            .field("depth", &self.stack.len())
+            .finish()
+    }
+}
+
+/// Assigns each distinct string an index in order of first use.
+#[derive(Debug, Default)]
+struct Interner(FxHashMap<std::string::String, u32>);
+
+impl Interner {
+    /// Look up the string with an index.
+    fn find(&self, index: u32) -> Option<&str> {
+        self.0
+            .iter()
+            .find_map(|(s, i)| (*i == index).then_some(s.as_str()))
+    }
+
+    fn get(&mut self, s: &str) -> u32 {
+        if let Some(index) = self.0.get(s) {
+            return *index;
+        }
+        let index = self.0.len() as u32;
+        self.0.insert(s.to_owned(), index);
+        index
+    }
+
+    /// Sort the strings, returning them and a map from first use indices to
+    /// sorted indices.
+    fn build(self) -> (Vec<std::string::String>, Vec<u32>) {
+        let mut strings = self.0.into_iter().collect::<Vec<_>>();
+        strings.sort_unstable();
+        let mut remap = vec![0; strings.len()];
+        for (sorted, (_, index)) in strings.iter().enumerate() {
+            remap[*index as usize] = sorted as u32;
+        }
+        (strings.into_iter().map(|(s, _)| s).collect(), remap)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PendingKey {
+    String(u32),
+    Hash(u32),
+    ValueHash(u32, u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Item {
+    /// The key index or hash.
+    key:   u32,
+    /// The extra value of a value hash map entry.
+    extra: u32,
+    ty:    NodeType,
+    /// The inline value, string index or spill offset.
+    value: u32,
+}
+
+#[derive(Debug)]
+struct Frame {
+    ty:    NodeType,
+    items: Vec<Item>,
+    /// The keys used so far, if the container is a map.
+    used:  FxHashSet<u32>,
+    /// The key of the container in its parent.
+    key:   Option<PendingKey>,
+}
+
+impl BymlBuilder {
+    /// Create a builder with an in-memory spill region.
+    pub fn new(endian: Endian, version: u16) -> Result<Self> {
+        Self::with_spill(Cursor::new(Vec::new()), endian, version)
+    }
+}
+
+impl<S: Read + Write + Seek> BymlBuilder<S> {
+    /// Create a builder which spills closed containers to the given stream,
+    /// such as a temporary file. The stream should be empty.
+    pub fn with_spill(spill: S, endian: Endian, version: u16) -> Result<Self> {
+        if !is_valid_version(version) {
+            return Err(Error::InvalidData("Unsupported BYML version (1-7 only)"));
+        }
+        Ok(Self {
+            spill,
+            endian: match endian {
+                Endian::Little => binrw::Endian::Little,
+                Endian::Big => binrw::Endian::Big,
+            },
+            version,
+            strings: Interner::default(),
+            keys: Interner::default(),
+            stack: Vec::new(),
+            key: None,
+            root: None,
+        })
+    }
+
+    /// Open an array.
+    pub fn begin_array(&mut self) -> Result<()> {
+        self.begin(NodeType::Array)
+    }
+
+    /// Open a map with string keys. Each value must be preceded by
+    /// [`key`](Self::key).
+    pub fn begin_map(&mut self) -> Result<()> {
+        self.begin(NodeType::Map)
+    }
+
+    /// Open a map with hash keys. Each value must be preceded by
+    /// [`hash_key`](Self::hash_key).
+    pub fn begin_hash_map(&mut self) -> Result<()> {
+        self.begin(NodeType::HashMap)
+    }
+
+    /// Open a map with hash keys and extra values. Each value must be
+    /// preceded by [`value_hash_key`](Self::value_hash_key).
+    pub fn begin_value_hash_map(&mut self) -> Result<()> {
+        self.begin(NodeType::ValueHashMap)
+    }
+
+    fn begin(&mut self, ty: NodeType) -> Result<()> {
+        if self.stack.is_empty() && self.root.is_some() {
+            return Err(Error::InvalidData("BYML document already has a root node"));
+        }
+        self.check_key()?;
+        self.check_version(ty)?;
+        self.stack.push(Frame {
+            ty,
+            items: vec![],
+            used: Default::default(),
+            key: self.key.take(),
+        });
+        Ok(())
+    }
+
+    /// Set the key of the next value in a map.
+    pub fn key(&mut self, key: &str) -> Result<()> {
+        self.expect_key(NodeType::Map)?;
+        let index = self.keys.get(key);
+        self.check_duplicate(index, key)?;
+        self.key = Some(PendingKey::String(index));
+        Ok(())
+    }
+
+    /// Set the key of the next value in a hash map.
+    pub fn hash_key(&mut self, hash: u32) -> Result<()> {
+        self.expect_key(NodeType::HashMap)?;
+        self.check_duplicate(hash, hash)?;
+        self.key = Some(PendingKey::Hash(hash));
+        Ok(())
+    }
+
+    /// Set the key and extra value of the next value in a value hash map.
+    pub fn value_hash_key(&mut self, hash: u32, extra: u32) -> Result<()> {
+        self.expect_key(NodeType::ValueHashMap)?;
+        self.check_duplicate(hash, hash)?;
+        self.key = Some(PendingKey::ValueHash(hash, extra));
+        Ok(())
+    }
+
+    /// Make sure the open map has no value for a key yet.
+    fn check_duplicate(&self, key: u32, name: impl std::fmt::Display) -> Result<()> {
+        match self.stack.last() {
+            Some(frame) if frame.used.contains(&key) => {
+                Err(Error::InvalidDataD(format!(
+                    "Duplicate key in BYML builder map: {name}"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Make sure the BYML version supports a node type, returning the same
+    /// error as [`Byml::to_binary_checked`] if it does not.
+    fn check_version(&self, ty: NodeType) -> Result<()> {
+        let min_version = min_type_version(ty);
+        if min_version <= self.version {
+            return Ok(());
+        }
+        Err(version_error(self.version, &[VersionIssue {
+            path: self.next_path(),
+            node_type: format!("{ty:?}").into(),
+            min_version,
+        }]))
+    }
+
+    /// The pointer to the next value.
+    fn next_path(&self) -> String {
+        pointer(self.stack.iter().enumerate().map(|(i, frame)| {
+            let key = match self.stack.get(i + 1) {
+                Some(child) => child.key,
+                None => self.key,
+            };
+            match key {
+                Some(PendingKey::String(index)) => {
+                    BymlIndex::StringIdx(self.keys.find(index).unwrap_or_default())
+                }
+                Some(PendingKey::Hash(hash)) | Some(PendingKey::ValueHash(hash, _)) => {
+                    BymlIndex::HashIdx(hash)
+                }
+                None => BymlIndex::ArrayIdx(frame.items.len()),
+            }
+        }))
+    }
+
+    /// Make sure a key for the given container type can be set now.
+    fn expect_key(&self, expected: NodeType) -> Result<()> {
+        match self.stack.last() {
+            Some(frame) if frame.ty == expected && self.key.is_none() => Ok(()),
+            Some(frame) if frame.ty == expected => {
+                Err(Error::InvalidData("BYML builder key has no value"))
+            }
+            _ => {
+                Err(Error::InvalidDataD(format!(
+                    "BYML builder key is not valid here (expected a {expected:?} container)"
+                )))
+            }
+        }
+    }
+
+    /// Make sure the next item has a key if and only if it needs one.
+    fn check_key(&self) -> Result<()> {
+        match self.stack.last() {
+            Some(frame) if frame.ty != NodeType::Array && self.key.is_none() => {
+                Err(Error::InvalidData("BYML builder map value has no key"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn push(&mut self, ty: NodeType, value: u32) -> Result<()> {
+        let Some(frame) = self.stack.last_mut() else {
+            if !is_container_type(ty) {
+                return Err(Error::InvalidData(
+                    "BYML document root must be a container",
+                ));
+            }
+            self.root = Some(value);
+            return Ok(());
+        };
+        let (key, extra) = match self.key.take() {
+            Some(PendingKey::String(key)) | Some(PendingKey::Hash(key)) => (key, 0),
+            Some(PendingKey::ValueHash(key, extra)) => (key, extra),
+            None if frame.ty == NodeType::Array => (0, 0),
+            None => return Err(Error::InvalidData("BYML builder map value has no key")),
+        };
+        if frame.ty != NodeType::Array {
+            frame.used.insert(key);
+        }
+        frame.items.push(Item {
+            key,
+            extra,
+            ty,
+            value,
+        });
+        Ok(())
+    }
+
+    /// Add a value to the open container. Containers in `value` are written
+    /// as a whole, so this can be used to add small subtrees.
+    pub fn value(&mut self, value: impl Into<Byml>) -> Result<()> {
+        self.value_ref(&value.into())
+    }
+
+    /// Add a value to the open container by reference.
+    pub fn value_ref(&mut self, value: &Byml) -> Result<()> {
+        self.check_key()?;
+        self.check_version(value.get_node_type())?;
+        match value {
+            Byml::Array(array) => {
+                self.begin_array()?;
+                for item in array {
+                    self.value_ref(item)?;
+                }
+                self.end()
+            }
+            Byml::Map(map) => {
+                self.begin_map()?;
+                for (key, item) in map {
+                    self.key(key)?;
+                    self.value_ref(item)?;
+                }
+                self.end()
+            }
+            Byml::HashMap(map) => {
+                self.begin_hash_map()?;
+                for (hash, item) in map {
+                    self.hash_key(*hash)?;
+                    self.value_ref(item)?;
+                }
+                self.end()
+            }
+            Byml::ValueHashMap(map) => {
+                self.begin_value_hash_map()?;
+                for (hash, (item, extra)) in map {
+                    self.value_hash_key(*hash, *extra)?;
+                    self.value_ref(item)?;
+                }
+                self.end()
+            }
+            Byml::String(s) => {
+                let index = self.strings.get(s);
+                self.push(NodeType::String, index)
+            }
+            Byml::Null => self.push(NodeType::Null, 0),
+            Byml::Bool(b) => self.push(NodeType::Bool, *b as u32),
+            Byml::I32(i) => self.push(NodeType::I32, *i as u32),
+            Byml::U32(u) => self.push(NodeType::U32, *u),
+            Byml::Float(f) => self.push(NodeType::Float, f.to_bits()),
+            Byml::I64(_)
+            | Byml::U64(_)
+            | Byml::Double(_)
+            | Byml::BinaryData(_)
+            | Byml::FileData(_) => {
+                let offset = self.spill_offset()?;
+                let ty = value.get_node_type();
+                match value {
+                    Byml::I64(i) => self.spill_write(*i)?,
+                    Byml::U64(u) => self.spill_write(*u)?,
+                    Byml::Double(d) => self.spill_write(d.to_bits())?,
+                    Byml::BinaryData(data) => {
+                        self.spill_write(data.len() as u32)?;
+                        self.spill.write_all(data)?;
+                    }
+                    Byml::FileData(data) => {
+                        self.spill_write(data.len() as u32)?;
+                        self.spill_write(DEFAULT_FILE_DATA_EXTRA)?;
+                        self.spill.write_all(data)?;
+                    }
+                    _ => unreachable!(),
+                }
+                self.push(ty, offset)
+            }
+        }
+    }
+
+    /// Close the open container.
+    pub fn end(&mut self) -> Result<()> {
+        if self.key.is_some() {
+            return Err(Error::InvalidData("BYML builder key has no value"));
+        }
+        let Frame { ty, mut items, key, .. } = self
+            .stack
+            .pop()
+            .ok_or(Error::InvalidData("BYML builder has no open container"))?;
+        self.key = key;
+        if items.len() > 0xFFFFFF {
+            return Err(Error::InvalidData("BYML container has too many items"));
+        }
+        let offset = self.spill_offset()?;
+        self.spill_write(ty)?;
+        self.spill_write(u24(items.len() as u32))?;
+        match ty {
+            NodeType::Array => {
+                for item in &items {
+                    self.spill_write(item.ty)?;
+                }
+                self.spill_align()?;
+                for item in &items {
+                    self.spill_write(item.value)?;
+                }
+            }
+            NodeType::Map => {
+                // Sorted when the document is finished, once the final key
+                // indices are known.
+                for item in &items {
+                    self.spill_write(u24(item.key))?;
+                    self.spill_write(item.ty)?;
+                    self.spill_write(item.value)?;
+                }
+            }
+            NodeType::HashMap => {
+                items.sort_by_key(|item| item.key);
+                for item in &items {
+                    self.spill_write(item.key)?;
+                    self.spill_write(item.value)?;
+                }
+                for item in &items {
+                    self.spill_write(item.ty)?;
+                }
+            }
+            NodeType::ValueHashMap => {
+                items.sort_by_key(|item| item.key);
+                for item in &items {
+                    self.spill_write(item.value)?;
+                    self.spill_write(item.key)?;
+                    self.spill_write(item.extra)?;
+                }
+                for item in &items {
+                    self.spill_write(item.ty)?;
+                }
+            }
+            _ => unreachable!("only containers are pushed"),
+        }
+        self.push(ty, offset)
+    }
+
+    /// The aligned end of the spill region, where the next node goes.
+    fn spill_offset(&mut self) -> Result<u32> {
+        self.spill_align()?;
+        Ok(self.spill.stream_position()? as u32)
+    }
+
+    fn spill_align(&mut self) -> Result<()> {
+        let pos = self.spill.seek(SeekFrom::End(0))? as u32;
+        let padding = align(pos, 4) - pos;
+        self.spill.write_all(&[0; 3][..padding as usize])?;
+        Ok(())
+    }
+
+    #[inline]
+    fn spill_write<'b, T: BinWrite<Args<'b> = ()>>(&mut self, value: T) -> Result<()> {
+        value.write_options(&mut self.spill, self.endian, ())?;
+        Ok(())
+    }
+
+    /// Finish the document and return it as bytes.
+    pub fn finish_to_vec(self) -> Result<Vec<u8>> {
+        let mut buf = Cursor::new(Vec::new());
+        self.finish(&mut buf)?;
+        Ok(buf.into_inner())
+    }
+
+    /// Assemble the final document, with its string tables, into the given
+    /// writer.
+    ///
+    /// Returns an error if any container is still open.
+    pub fn finish<W: Write + Seek>(mut self, writer: &mut W) -> Result<()> {
+        if !self.stack.is_empty() {
+            return Err(Error::InvalidData("BYML builder has unclosed containers"));
+        }
+        let (keys, key_remap) = std::mem::take(&mut self.keys).build();
+        let (strings, string_remap) = std::mem::take(&mut self.strings).build();
+        let endian = self.endian;
+        let start = writer.stream_position()?;
+        match endian {
+            binrw::Endian::Little => b"YB",
+            binrw::Endian::Big => b"BY",
+        }
+        .write_options(writer, endian, ())?;
+        self.version.write_options(writer, endian, ())?;
+        [0u32; 3].write_options(writer, endian, ())?; // Table and root offsets
+        let Some(root) = self.root else {
+            return Ok(());
+        };
+        let mut out = Assembler {
+            writer,
+            start,
+            endian,
+            key_remap,
+            string_remap,
+            base: 0,
+        };
+        if !keys.is_empty() {
+            let pos = out.position()?;
+            out.write_at(pos, 0x4)?;
+            out.write_string_table(&keys)?;
+        }
+        if !strings.is_empty() {
+            let pos = out.position()?;
+            out.write_at(pos, 0x8)?;
+            out.write_string_table(&strings)?;
+        }
+        out.align()?;
+        out.base = out.position()?;
+        out.write_at(out.base + root, 0xC)?;
+        // Copy the spilled nodes as they are, then rewrite the containers in
+        // place. Value nodes stored out of line need no changes.
+        self.spill.seek(SeekFrom::Start(0))?;
+        let len = std::io::copy(&mut self.spill, out.writer)? as u32;
+        let padding = align(len, 4) - len;
+        out.writer.write_all(&[0; 3][..padding as usize])?;
+        out.rewrite_container(&mut self.spill, root)?;
+        out.writer
+            .seek(SeekFrom::Start(start + (out.base + len + padding) as u64))?;
+        out.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes spilled nodes into the output, resolving string and key indices
+/// and relocating offsets.
+struct Assembler<'w, W> {
+    writer: &'w mut W,
+    /// The stream position of the document start.
+    start: u64,
+    endian: binrw::Endian,
+    key_remap: Vec<u32>,
+    string_remap: Vec<u32>,
+    /// The document offset of the spilled nodes.
+    base: u32,
+}
+
+impl<W: Write + Seek> Assembler<'_, W> {
+    fn position(&mut self) -> Result<u32> {
+        Ok((self.writer.stream_position()? - self.start) as u32)
+    }
+
+    fn write<'b, T: BinWrite<Args<'b> = ()>>(&mut self, value: T) -> Result<()> {
+        value.write_options(self.writer, self.endian, ())?;
+        Ok(())
+    }
+
+    fn write_at<'b, T: BinWrite<Args<'b> = ()>>(&mut self, value: T, offset: u32) -> Result<()> {
+        let old = self.writer.stream_position()?;
+        self.writer
+            .seek(SeekFrom::Start(self.start + offset as u64))?;
+        self.write(value)?;
+        self.writer.seek(SeekFrom::Start(old))?;
+        Ok(())
+    }
+
+    fn align(&mut self) -> Result<()> {
+        let pos = self.position()?;
+        self.writer
+            .seek(SeekFrom::Start(self.start + align(pos, 4) as u64))?;
+        Ok(())
+    }
+
+    fn write_string_table(&mut self, strings: &[std::string::String]) -> Result<()> {
+        let start = self.position()?;
+        self.write(NodeType::StringTable)?;
+        self.write(u24(strings.len() as u32))?;
+        let mut offset = 4 + 4 * (strings.len() as u32 + 1);
+        for s in strings {
+            self.write(offset)?;
+            offset += s.len() as u32 + 1;
+        }
+        self.write(offset)?;
+        for s in strings {
+            self.writer.write_all(s.as_bytes())?;
+            self.writer.write_all(&[0])?;
+        }
+        debug_assert_eq!(self.position()?, start + offset);
+        self.align()
+    }
+
+    /// Resolve an item value for the output.
+    fn resolve(&self, ty: NodeType, value: u32) -> u32 {
+        match ty {
+            NodeType::String => self.string_remap[value as usize],
+            NodeType::Array
+            | NodeType::Map
+            | NodeType::HashMap
+            | NodeType::ValueHashMap
+            | NodeType::Binary
+            | NodeType::File
+            | NodeType::I64
+            | NodeType::U64
+            | NodeType::Double => self.base + value,
+            _ => value,
+        }
+    }
+
+    fn rewrite_container<S: Read + Seek>(&mut self, spill: &mut S, offset: u32) -> Result<()> {
+        spill.seek(SeekFrom::Start(offset as u64))?;
+        let endian = self.endian;
+        let ty = NodeType::read_options(spill, endian, ())?;
+        let len = u24::read_options(spill, endian, ())?.0 as usize;
+        self.writer
+            .seek(SeekFrom::Start(self.start + (self.base + offset) as u64))?;
+        self.write(ty)?;
+        self.write(u24(len as u32))?;
+        let mut children = vec![];
+        match ty {
+            NodeType::Array => {
+                let mut types = vec![0u8; len];
+                spill.read_exact(&mut types)?;
+                let types = types
+                    .into_iter()
+                    .map(|t| NodeType::read_options(&mut Cursor::new([t]), endian, ()))
+                    .collect::<binrw::BinResult<Vec<_>>>()?;
+                spill.seek(SeekFrom::Start(align(offset + 4 + len as u32, 4) as u64))?;
+                for ty in &types {
+                    self.write(*ty)?;
+                }
+                self.align()?;
+                for ty in types {
+                    let value = u32::read_options(spill, endian, ())?;
+                    self.write(self.resolve(ty, value))?;
+                    if is_container_type(ty) {
+                        children.push(value);
+                    }
+                }
+            }
+            NodeType::Map => {
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let key = u24::read_options(spill, endian, ())?.0;
+                    let ty = NodeType::read_options(spill, endian, ())?;
+                    let value = u32::read_options(spill, endian, ())?;
+                    items.push((self.key_remap[key as usize], ty, value));
+                }
+                items.sort_unstable_by_key(|(key, ..)| *key);
+                for (key, ty, value) in items {
+                    self.write(u24(key))?;
+                    self.write(ty)?;
+                    self.write(self.resolve(ty, value))?;
+                    if is_container_type(ty) {
+                        children.push(value);
+                    }
+                }
+            }
+            NodeType::HashMap | NodeType::ValueHashMap => {
+                let words = if ty == NodeType::HashMap { 2 } else { 3 };
+                let entries = (0..len * words)
+                    .map(|_| u32::read_options(spill, endian, ()))
+                    .collect::<binrw::BinResult<Vec<_>>>()?;
+                let types = (0..len)
+                    .map(|_| NodeType::read_options(spill, endian, ()))
+                    .collect::<binrw::BinResult<Vec<_>>>()?;
+                // The value is the second word of hash map entries and the
+                // first of value hash map entries.
+                let value_index = if ty == NodeType::HashMap { 1 } else { 0 };
+                for (entry, ty) in entries.chunks(words).zip(&types) {
+                    for (i, word) in entry.iter().enumerate() {
+                        if i == value_index {
+                            self.write(self.resolve(*ty, *word))?;
+                            if is_container_type(*ty) {
+                                children.push(*word);
+                            }
+                        } else {
+                            self.write(*word)?;
+                        }
+                    }
+                }
+                for ty in types {
+                    self.write(ty)?;
+                }
+            }
+            _ => return Err(Error::InvalidData("Invalid spilled BYML container")),
+        }
+        for child in children {
+            self.rewrite_container(spill, child)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(byml: &Byml, endian: Endian, version: u16) -> Vec<u8> {
+        let mut builder = BymlBuilder::new(endian, version).unwrap();
+        builder.value_ref(byml).unwrap();
+        builder.finish_to_vec().unwrap()
+    }
+
+    #[test]
+    fn matches_writer() {
+        for file in ["ActorInfo.product", "A-1_Dynamic", "EventInfo.product"] {
+            let data = std::fs::read(format!("test/byml/{file}.byml")).unwrap();
+            let byml = Byml::from_binary(&data).unwrap();
+            for endian in [Endian::Big, Endian::Little] {
+                let built = build(&byml, endian, 2);
+                assert_eq!(Byml::from_binary(&built).unwrap(), byml);
+                // Only the sharing of identical nodes differs.
+                assert!(built.len() >= byml.to_binary(endian).len());
+            }
+        }
+    }
+
+    #[test]
+    fn all_node_types() {
+        let mut map = Map::default();
+        map.insert("i64".into(), Byml::I64(-1));
+        map.insert("u64".into(), Byml::U64(u64::MAX));
+        map.insert("f64".into(), Byml::Double(0.5));
+        map.insert("bin".into(), Byml::BinaryData(vec![1, 2, 3]));
+        map.insert("file".into(), Byml::FileData(vec![4, 5]));
+        map.insert("null".into(), Byml::Null);
+        map.insert("str".into(), "zzz".into());
+        map.insert(
+            "hash".into(),
+            Byml::HashMap(
+                [(3, Byml::Bool(true)), (1, Byml::Array(vec!["aaa".into()]))]
+                    .into_iter()
+                    .collect(),
+            ),
+        );
+        map.insert(
+            "vhash".into(),
+            Byml::ValueHashMap(
+                [(2, (Byml::Float(1.5), 7)), (1, (Byml::I64(9), 8))]
+                    .into_iter()
+                    .collect(),
+            ),
+        );
+        let byml = Byml::Map(map);
+        let built = build(&byml, Endian::Little, 7);
+        assert_eq!(Byml::from_binary(built).unwrap(), byml);
+        let built = build(&Byml::Array(vec![]), Endian::Big, 2);
+        assert_eq!(Byml::from_binary(built).unwrap(), Byml::Array(vec![]));
+    }
+
+    #[test]
+    fn file_spill() {
+        let name = format!("roead_byml_builder_spill_{}.bin", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let spill = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        let mut builder = BymlBuilder::with_spill(spill, Endian::Big, 2).unwrap();
+        builder.begin_array().unwrap();
+        for i in 0..100 {
+            builder.begin_map().unwrap();
+            builder.key("Name").unwrap();
+            builder.value(format!("Actor_{}", i % 10)).unwrap();
+            builder.key("Index").unwrap();
+            builder.value(i).unwrap();
+            builder.end().unwrap();
+        }
+        builder.end().unwrap();
+        let data = builder.finish_to_vec().unwrap();
+        std::fs::remove_file(path).unwrap();
+        let byml = Byml::from_binary(data).unwrap();
+        assert_eq!(byml[57]["Name"], Byml::from("Actor_7"));
+        assert_eq!(byml[57]["Index"], Byml::I32(57));
+    }
+
+    #[test]
+    fn misuse() {
+        let mut builder = BymlBuilder::new(Endian::Little, 2).unwrap();
+        assert!(builder.value(1).is_err());
+        assert!(builder.end().is_err());
+        builder.begin_map().unwrap();
+        assert!(builder.value(1).is_err());
+        assert!(builder.hash_key(1).is_err());
+        builder.key("a").unwrap();
+        assert!(builder.key("b").is_err());
+        assert!(builder.end().is_err());
+        builder.value(1).unwrap();
+        assert!(builder.begin_array().is_err());
+        builder.key("c").unwrap();
+        builder.begin_array().unwrap();
+        assert!(builder.key("d").is_err());
+        builder.end().unwrap();
+        builder.end().unwrap();
+        assert!(builder.begin_array().is_err());
+        assert!(BymlBuilder::new(Endian::Little, 8).is_err());
+
+        let mut builder = BymlBuilder::new(Endian::Little, 2).unwrap();
+        builder.begin_map().unwrap();
+        builder.key("a").unwrap();
+        builder.value(1).unwrap();
+        assert!(builder.key("a").is_err());
+        builder.key("b").unwrap();
+        let err = builder.value(Byml::I64(1)).unwrap_err().to_string();
+        let byml: Byml = [("b", Byml::I64(1))].into_iter().collect();
+        let expected = byml.to_binary_checked(Endian::Little, 2).unwrap_err();
+        assert_eq!(err, expected.to_string());
+        assert!(builder.begin_hash_map().is_err());
+        assert!(builder.begin_value_hash_map().is_err());
+        for value in [
+            Byml::U64(1),
+            Byml::Double(1.0),
+            Byml::BinaryData(vec![]),
+            Byml::HashMap(Default::default()),
+        ] {
+            assert!(builder.value(value).is_err());
+        }
+
+        let mut builder = BymlBuilder::new(Endian::Little, 7).unwrap();
+        builder.begin_hash_map().unwrap();
+        builder.hash_key(1).unwrap();
+        builder.value(1).unwrap();
+        assert!(builder.hash_key(1).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use serde::de::{
+    self,
+    value::{MapDeserializer, SeqDeserializer},
+    DeserializeOwned, IntoDeserializer, Visitor,
+};
+
+use super::*;
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Any(msg.to_string())
+    }
+}
+
+/// Convert a BYML node to a value. This is the inverse of
+/// [`to_byml`](super::to_byml).
+///
+/// Numeric nodes can be read into any Rust numeric type which can hold the
+/// value. [`Byml::HashMap`] nodes can be read into maps with `u32` keys.
+/// [`Byml::ValueHashMap`] nodes can be read into maps with `u32` keys and
+/// `(T, u32)` values, the second element being the additional value stored
+/// with each entry. Binary and file data can be read as byte buffers.
+pub fn from_byml<T: DeserializeOwned>(byml: Byml) -> Result<T> {
+    T::deserialize(byml)
+}
+
+/// Parse binary BYML data into a value.
+///
+/// **Note**: If and only if the `yaz0` feature is enabled, this function
+/// automatically decompresses the data when necessary.
+pub fn from_binary<T: DeserializeOwned>(data: impl AsRef<[u8]>) -> Result<T> {
+    from_byml(Byml::from_binary(data)?)
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Byml {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn visit_array<'de, V: Visitor<'de>>(array: Vec<Byml>, visitor: V) -> Result<V::Value> {
+    let mut deserializer = SeqDeserializer::new(array.into_iter());
+    let value = visitor.visit_seq(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+fn visit_map<'de, K, I, V>(iter: I, visitor: V) -> Result<V::Value>
+where
+    K: IntoDeserializer<'de, Error>,
+    I: Iterator<Item = (K, Byml)>,
+    V: Visitor<'de>,
+{
+    let mut deserializer = MapDeserializer::new(iter);
+    let value = visitor.visit_map(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for Byml {
+    type Error = Error;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Byml::String(s) => visitor.visit_string(s.into()),
+            Byml::BinaryData(data) | Byml::FileData(data) => visitor.visit_byte_buf(data),
+            Byml::Array(array) => visit_array(array, visitor),
+            Byml::Map(map) => {
+                visit_map(
+                    map.into_iter()
+                        .map(|(k, v)| (std::string::String::from(k), v)),
+                    visitor,
+                )
+            }
+            Byml::HashMap(map) => visit_map(map.into_iter(), visitor),
+            Byml::ValueHashMap(map) => {
+                visit_map(
+                    map.into_iter()
+                        .map(|(k, (v, extra))| (k, Byml::Array(vec![v, Byml::U32(extra)]))),
+                    visitor,
+                )
+            }
+            Byml::Bool(v) => visitor.visit_bool(v),
+            Byml::I32(v) => visitor.visit_i32(v),
+            Byml::Float(v) => visitor.visit_f32(v),
+            Byml::U32(v) => visitor.visit_u32(v),
+            Byml::I64(v) => visitor.visit_i64(v),
+            Byml::U64(v) => visitor.visit_u64(v),
+            Byml::Double(v) => visitor.visit_f64(v),
+            Byml::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Byml::Null => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Byml::String(variant) => {
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Byml::Null,
+                })
+            }
+            Byml::Map(map) if map.len() == 1 => {
+                let (variant, value) = map
+                    .into_iter()
+                    .next()
+                    .ok_or(Error::InvalidData("Enum map should have one entry"))?;
+                visitor.visit_enum(EnumDeserializer { variant, value })
+            }
+            other => {
+                Err(Error::TypeError(
+                    other.type_name(),
+                    "String or Map with a single entry",
+                ))
+            }
+        }
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value:   Byml,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = Byml;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Byml)> {
+        let variant = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(
+            std::string::String::from(self.variant),
+        ))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Byml {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self {
+            Byml::Null => Ok(()),
+            other => Err(Error::TypeError(other.type_name(), "Null")),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        byml::{self, Byml},
+        Endian,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        Static,
+        Dynamic { radius: f32 },
+        Pair(i32, i32),
+        Named(String),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Actor {
+        name: String,
+        hash: u32,
+        id: u64,
+        offset: i64,
+        count: i16,
+        scale: f32,
+        precise: f64,
+        enabled: bool,
+        tags: Vec<String>,
+        params: BTreeMap<String, i32>,
+        hashes: HashMap<u32, String>,
+        parent: Option<String>,
+        kinds: Vec<Kind>,
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let actor = Actor {
+            name: "Enemy_Lizalfos_Electric".into(),
+            hash: 0xDEADBEEF,
+            id: u64::MAX,
+            offset: -5,
+            count: 3,
+            scale: 1.5,
+            precise: 0.1,
+            enabled: true,
+            tags: vec!["Enemy".into(), "Lizalfos".into()],
+            params: [("Life".to_string(), 60)].into_iter().collect(),
+            hashes: [(1, "One".to_string())].into_iter().collect(),
+            parent: None,
+            kinds: vec![
+                Kind::Static,
+                Kind::Dynamic { radius: 2.0 },
+                Kind::Pair(1, 2),
+                Kind::Named("Test".into()),
+            ],
+        };
+        let byml = byml::to_byml(&actor).unwrap();
+        assert_eq!(byml["hash"], Byml::U32(0xDEADBEEF));
+        assert_eq!(byml["id"], Byml::U64(u64::MAX));
+        assert_eq!(byml["offset"], Byml::I64(-5));
+        assert_eq!(byml["count"], Byml::I32(3));
+        assert_eq!(byml["parent"], Byml::Null);
+        assert_eq!(byml["kinds"][0], Byml::String("Static".into()));
+        assert!(matches!(byml["hashes"], Byml::HashMap(_)));
+        assert_eq!(byml::from_byml::<Actor>(byml).unwrap(), actor);
+        let data = byml::to_binary(&actor, Endian::Little, 7).unwrap();
+        assert_eq!(&data[..4], b"YB\x07\x00");
+        assert_eq!(byml::from_binary::<Actor>(data).unwrap(), actor);
+        // The 64-bit fields need version 3 and the u32-keyed map version 7.
+        assert!(byml::to_binary(&actor, Endian::Little, 2).is_err());
+        assert!(byml::to_binary(&actor, Endian::Little, 6).is_err());
+        let tags = byml::to_binary(&actor.tags, Endian::Big, 2).unwrap();
+        assert_eq!(byml::from_binary::<Vec<String>>(tags).unwrap(), actor.tags);
+    }
+
+    #[test]
+    fn value_hash_map() {
+        let byml = Byml::ValueHashMap([(7, (Byml::I32(1), 2))].into_iter().collect());
+        let map: HashMap<u32, (i32, u32)> = byml::from_byml(byml).unwrap();
+        assert_eq!(map[&7], (1, 2));
+        assert!(byml::from_byml::<u8>(Byml::I32(300)).is_err());
+        assert_eq!(byml::from_byml::<u8>(Byml::I32(30)).unwrap(), 30);
+    }
+}
@@ -0,0 +1,336 @@
+use base64::Engine;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use super::*;
+
+impl Byml {
+    /// Serialize the document to JSON.
+    ///
+    /// Strings, booleans, null, arrays and maps map to the corresponding JSON
+    /// values, I32 nodes to integers and Float nodes to numbers with a decimal
+    /// point. Other types are written as an object with a single key naming
+    /// the type, using the same tags as the YAML format:
+    ///
+    /// | Node           | JSON                                    |
+    /// |----------------|-----------------------------------------|
+    /// | `U32`          | `{"!u": 5}`                             |
+    /// | `I64`          | `{"!l": -5}`                            |
+    /// | `U64`          | `{"!ul": 5}`                            |
+    /// | `Double`       | `{"!f64": 0.5}`                         |
+    /// | NaN or ±∞      | `{"!f32": "nan"}`, `{"!f64": "-inf"}`   |
+    /// | `BinaryData`   | `{"!!binary": "<base64>"}`              |
+    /// | `FileData`     | `{"!!file": "<base64>"}`                |
+    /// | `HashMap`      | `{"!h": {"<hash>": value}}`             |
+    /// | `ValueHashMap` | `{"!vh": {"<hash>": [value, extra]}}`   |
+    ///
+    /// A map which itself has a single key starting with `!` is written as
+    /// `{"!map": {...}}`, so the conversion is lossless. Map keys are written
+    /// in insertion order.
+    pub fn to_json(&self) -> std::string::String {
+        serde_json::to_string_pretty(&Json(self)).expect("BYML should serialize to JSON")
+    }
+
+    /// Parse a document from JSON using the conventions of
+    /// [`Byml::to_json`]. Integers without a tag become I32 nodes, or I64 or
+    /// U64 nodes if they are out of range, and numbers with a fraction or
+    /// exponent become Float nodes. Map keys keep their order in the text.
+    pub fn from_json(text: &str) -> Result<Byml> {
+        from_value(serde_json::from_str(text).map_err(|e| Error::InvalidDataD(e.to_string()))?)
+    }
+}
+
+struct Json<'a>(&'a Byml);
+
+struct Tagged<'a, T: Serialize>(&'static str, &'a T);
+
+impl<T: Serialize> Serialize for Tagged<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(self.0, self.1)?;
+        map.end()
+    }
+}
+
+struct JsonMap<'a>(&'a Map);
+
+impl Serialize for JsonMap<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k.as_str(), Json(v))))
+    }
+}
+
+struct JsonHashMap<'a>(&'a HashMap);
+
+impl Serialize for JsonHashMap<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut items = self.0.iter().collect::<Vec<_>>();
+        items.sort_by_key(|(k, _)| **k);
+        serializer.collect_map(items.into_iter().map(|(k, v)| (k.to_string(), Json(v))))
+    }
+}
+
+struct JsonValueHashMap<'a>(&'a ValueHashMap);
+
+impl Serialize for JsonValueHashMap<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut items = self.0.iter().collect::<Vec<_>>();
+        items.sort_by_key(|(k, _)| **k);
+        serializer.collect_map(
+            items
+                .into_iter()
+                .map(|(k, (v, extra))| (k.to_string(), (Json(v), extra))),
+        )
+    }
+}
+
+impl Serialize for Json<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let base64 = |data: &[u8]| base64::engine::general_purpose::STANDARD.encode(data);
+        match self.0 {
+            Byml::String(s) => serializer.serialize_str(s),
+            Byml::BinaryData(data) => Tagged("!!binary", &base64(data)).serialize(serializer),
+            Byml::FileData(data) => Tagged("!!file", &base64(data)).serialize(serializer),
+            Byml::Array(array) => serializer.collect_seq(array.iter().map(Json)),
+            Byml::Map(map) => {
+                if map.len() == 1 && map.keys().all(|k| k.starts_with('!')) {
+                    Tagged("!map", &JsonMap(map)).serialize(serializer)
+                } else {
+                    JsonMap(map).serialize(serializer)
+                }
+            }
+            Byml::HashMap(map) => Tagged("!h", &JsonHashMap(map)).serialize(serializer),
+            Byml::ValueHashMap(map) => Tagged("!vh", &JsonValueHashMap(map)).serialize(serializer),
+            Byml::Bool(v) => serializer.serialize_bool(*v),
+            Byml::I32(v) => serializer.serialize_i32(*v),
+            Byml::Float(v) => {
+                match non_finite_name(*v as f64) {
+                    Some(name) => Tagged("!f32", &name).serialize(serializer),
+                    None => serializer.serialize_f32(*v),
+                }
+            }
+            Byml::U32(v) => Tagged("!u", v).serialize(serializer),
+            Byml::I64(v) => Tagged("!l", v).serialize(serializer),
+            Byml::U64(v) => Tagged("!ul", v).serialize(serializer),
+            Byml::Double(v) => {
+                match non_finite_name(*v) {
+                    Some(name) => Tagged("!f64", &name).serialize(serializer),
+                    None => Tagged("!f64", v).serialize(serializer),
+                }
+            }
+            Byml::Null => serializer.serialize_unit(),
+        }
+    }
+}
+
+/// The name of a float which JSON numbers cannot represent.
+fn non_finite_name(v: f64) -> Option<&'static str> {
+    if v.is_nan() {
+        Some("nan")
+    } else if v == f64::INFINITY {
+        Some("inf")
+    } else if v == f64::NEG_INFINITY {
+        Some("-inf")
+    } else {
+        None
+    }
+}
+
+fn invalid(tag: &str, expected: &str) -> Error {
+    Error::InvalidDataD(format!("Invalid BYML JSON: {tag} value must be {expected}"))
+}
+
+fn from_object(object: serde_json::Map<std::string::String, Value>) -> Result<Map> {
+    object
+        .into_iter()
+        .map(|(k, v)| Ok((k.into(), from_value(v)?)))
+        .collect()
+}
+
+fn parse_hash(key: &str) -> Result<u32> {
+    key.parse()
+        .map_err(|_| Error::InvalidDataD(format!("Invalid BYML JSON hash key: {key}")))
+}
+
+fn from_tagged(tag: &str, value: Value) -> Result<Byml> {
+    let decode = |value: Value| -> Result<Vec<u8>> {
+        value
+            .as_str()
+            .and_then(|s| base64::engine::general_purpose::STANDARD.decode(s).ok())
+            .ok_or_else(|| invalid(tag, "a base64 string"))
+    };
+    let float = |value: Value| -> Result<f64> {
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => {
+                match s.as_str() {
+                    "nan" => Some(f64::NAN),
+                    "inf" => Some(f64::INFINITY),
+                    "-inf" => Some(f64::NEG_INFINITY),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+        .ok_or_else(|| invalid(tag, "a number, \"nan\", \"inf\" or \"-inf\""))
+    };
+    Ok(match tag {
+        "!u" => {
+            Byml::U32(
+                value
+                    .as_u64()
+                    .and_then(|v| u32::try_from(v).ok())
+                    .ok_or_else(|| invalid(tag, "a u32"))?,
+            )
+        }
+        "!l" => Byml::I64(value.as_i64().ok_or_else(|| invalid(tag, "an i64"))?),
+        "!ul" => Byml::U64(value.as_u64().ok_or_else(|| invalid(tag, "a u64"))?),
+        "!f32" => Byml::Float(float(value)? as f32),
+        "!f64" => Byml::Double(float(value)?),
+        "!!binary" => Byml::BinaryData(decode(value)?),
+        "!!file" => Byml::FileData(decode(value)?),
+        "!map" => {
+            match value {
+                Value::Object(object) => Byml::Map(from_object(object)?),
+                _ => return Err(invalid(tag, "an object")),
+            }
+        }
+        "!h" => {
+            match value {
+                Value::Object(object) => {
+                    Byml::HashMap(
+                        object
+                            .into_iter()
+                            .map(|(k, v)| Ok((parse_hash(&k)?, from_value(v)?)))
+                            .collect::<Result<_>>()?,
+                    )
+                }
+                _ => return Err(invalid(tag, "an object")),
+            }
+        }
+        "!vh" => {
+            match value {
+                Value::Object(object) => {
+                    Byml::ValueHashMap(
+                        object
+                            .into_iter()
+                            .map(|(k, v)| {
+                                let Value::Array(mut entry) = v else {
+                                    return Err(invalid(tag, "an object of [value, extra] pairs"));
+                                };
+                                let extra = entry
+                                    .pop()
+                                    .and_then(|e| e.as_u64())
+                                    .and_then(|e| u32::try_from(e).ok())
+                                    .filter(|_| entry.len() == 1)
+                                    .ok_or_else(|| {
+                                        invalid(tag, "an object of [value, extra] pairs")
+                                    })?;
+                                let value = from_value(entry.pop().expect("Entry has a value"))?;
+                                Ok((parse_hash(&k)?, (value, extra)))
+                            })
+                            .collect::<Result<_>>()?,
+                    )
+                }
+                _ => return Err(invalid(tag, "an object")),
+            }
+        }
+        _ => return Err(Error::InvalidDataD(format!("Unknown BYML JSON tag: {tag}"))),
+    })
+}
+
+fn from_value(value: Value) -> Result<Byml> {
+    Ok(match value {
+        Value::Null => Byml::Null,
+        Value::Bool(v) => Byml::Bool(v),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                i32::try_from(i).map(Byml::I32).unwrap_or(Byml::I64(i))
+            } else if let Some(u) = n.as_u64() {
+                Byml::U64(u)
+            } else {
+                Byml::Float(n.as_f64().unwrap_or_default() as f32)
+            }
+        }
+        Value::String(s) => Byml::String(s.into()),
+        Value::Array(array) => {
+            Byml::Array(array.into_iter().map(from_value).collect::<Result<_>>()?)
+        }
+        Value::Object(object) => {
+            if object.len() == 1 && object.keys().all(|k| k.starts_with('!')) {
+                let (tag, value) = object.into_iter().next().expect("Object has one entry");
+                from_tagged(&tag, value)?
+            } else {
+                Byml::Map(from_object(object)?)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_roundtrip() {
+        let byml: Byml = [
+            ("str", Byml::String("Test".into())),
+            ("i32", Byml::I32(-5)),
+            ("u32", Byml::U32(0xFFFFFFFF)),
+            ("i64", Byml::I64(i64::MIN)),
+            ("u64", Byml::U64(u64::MAX)),
+            ("f32", Byml::Float(0.1)),
+            ("whole", Byml::Float(2.0)),
+            ("f64", Byml::Double(0.1)),
+            ("bin", Byml::BinaryData(vec![0, 1, 2])),
+            ("file", Byml::FileData(vec![3, 4])),
+            ("null", Byml::Null),
+            ("array", Byml::Array(vec![Byml::Bool(true), Byml::Null])),
+            (
+                "hash",
+                Byml::HashMap([(1, Byml::I32(1))].into_iter().collect()),
+            ),
+            (
+                "vhash",
+                Byml::ValueHashMap([(2, (Byml::I32(2), 3))].into_iter().collect()),
+            ),
+            ("tagged", [("!u", Byml::I32(1))].into_iter().collect()),
+        ]
+        .into_iter()
+        .collect();
+        let json = byml.to_json();
+        assert!(json.contains(r#""f32": 0.1,"#));
+        assert!(json.contains(r#""u32": {"#));
+        assert_eq!(Byml::from_json(&json).unwrap(), byml);
+        assert!(Byml::from_json(r#"{"!x": 1}"#).is_err());
+
+        let floats = Byml::Array(vec![
+            Byml::Float(f32::NAN),
+            Byml::Float(f32::INFINITY),
+            Byml::Double(f64::NEG_INFINITY),
+            Byml::Double(f64::NAN),
+        ]);
+        let json = floats.to_json();
+        assert!(!json.contains("null"));
+        let parsed = Byml::from_json(&json).unwrap();
+        assert!(matches!(parsed[0], Byml::Float(f) if f.is_nan()));
+        assert_eq!(parsed[1], Byml::Float(f32::INFINITY));
+        assert_eq!(parsed[2], Byml::Double(f64::NEG_INFINITY));
+        assert!(matches!(parsed[3], Byml::Double(f) if f.is_nan()));
+        assert!(Byml::from_json(r#"{"!f32": "big"}"#).is_err());
+
+        let ordered = Byml::from_json(r#"{"zeta": 1, "alpha": 2, "mid": 3}"#).unwrap();
+        let keys: Vec<&str> = ordered
+            .as_map()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        assert_eq!(keys, ["zeta", "alpha", "mid"]);
+        assert_eq!(Byml::from_json(&ordered.to_json()).unwrap().to_json(), ordered.to_json());
+
+        let actor_info =
+            Byml::from_binary(std::fs::read("test/byml/ActorInfo.product.byml").unwrap()).unwrap();
+        assert_eq!(Byml::from_json(&actor_info.to_json()).unwrap(), actor_info);
+    }
+}
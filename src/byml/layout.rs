@@ -0,0 +1,739 @@
+use std::io::Write;
+
+use rustc_hash::FxHashMap;
+
+use super::{path::escape, view::BymlRef, *};
+use crate::{util::align, Endian, Error, Result};
+
+/// The binary layout of a parsed BYML document: its endianness, version,
+/// string tables, the offset of every node stored outside its parent and the
+/// value stored after the size of file data.
+///
+/// Nintendo's tools do not write documents the same way as [`Byml::write`]:
+/// some place children before their parents, share nodes differently or
+/// align data more strictly. Writing a document with the layout it was read
+/// with, using [`Byml::to_binary_with_layout`], reproduces the original file
+/// byte for byte if the document is unmodified, and keeps the original
+/// arrangement as far as possible if it is not.
+///
+/// ```
+/// # use roead::byml::Byml;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data = std::fs::read("test/byml/Preset0_Field.byml")?;
+/// let (byml, layout) = Byml::from_binary_with_layout(&data)?;
+/// assert_eq!(byml.to_binary_with_layout(&layout), data);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BymlLayout {
+    endian: Endian,
+    version: u16,
+    hash_key_table: u32,
+    string_table: u32,
+    hash_keys: Vec<String>,
+    strings: Vec<String>,
+    /// Offsets of non-inline nodes by pointer (see [`Byml::pointer`]).
+    nodes: FxHashMap<std::string::String, u32>,
+    /// The value after the size of each file data node by offset.
+    file_data_extras: FxHashMap<u32, u32>,
+    size: u32,
+}
+
+impl BymlLayout {
+    /// Record the layout of an uncompressed binary document.
+    fn new(data: &[u8]) -> Result<Self> {
+        struct Visitor {
+            nodes: FxHashMap<std::string::String, u32>,
+            file_data_extras: FxHashMap<u32, u32>,
+            seen: rustc_hash::FxHashSet<u32>,
+        }
+
+        impl Visitor {
+            fn visit(&mut self, node: BymlRef<'_>, path: &mut std::string::String) {
+                let Some(offset) = node.data_offset() else {
+                    return;
+                };
+                self.nodes.insert(path.clone(), offset);
+                // Shared nodes only need to be recorded once.
+                if !self.seen.insert(offset) {
+                    return;
+                }
+                if let Some(extra) = node.file_data_extra() {
+                    self.file_data_extras.insert(offset, extra);
+                }
+                let len = path.len();
+                for (i, child) in node.iter().enumerate() {
+                    path.push_str(&format!("/{i}"));
+                    self.visit(child, path);
+                    path.truncate(len);
+                }
+                for (key, child) in node.map_iter() {
+                    path.push('/');
+                    path.push_str(&escape(key));
+                    self.visit(child, path);
+                    path.truncate(len);
+                }
+                for (hash, child, _) in node.hash_iter() {
+                    path.push_str(&format!("/{hash}"));
+                    self.visit(child, path);
+                    path.truncate(len);
+                }
+            }
+        }
+
+        let root = BymlRef::new(data)?;
+        let doc = view::Document::new(data)?;
+        let version = match doc.endian {
+            Endian::Big => u16::from_be_bytes([data[2], data[3]]),
+            Endian::Little => u16::from_le_bytes([data[2], data[3]]),
+        };
+        let table = |offset| -> Result<Vec<String>> {
+            Ok(doc
+                .table_strs(offset)?
+                .into_iter()
+                .map(String::from)
+                .collect())
+        };
+        let mut visitor = Visitor {
+            nodes: Default::default(),
+            file_data_extras: Default::default(),
+            seen: Default::default(),
+        };
+        visitor.visit(root, &mut std::string::String::new());
+        Ok(Self {
+            endian: doc.endian,
+            version,
+            hash_key_table: doc.hash_key_table,
+            string_table: doc.string_table,
+            hash_keys: table(doc.hash_key_table)?,
+            strings: table(doc.string_table)?,
+            nodes: visitor.nodes,
+            file_data_extras: visitor.file_data_extras,
+            size: data.len() as u32,
+        })
+    }
+
+    /// Endianness of the document.
+    #[inline]
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// BYML version of the document.
+    #[inline]
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+}
+
+impl Byml {
+    /// Load a document from binary data along with its layout, so it can be
+    /// written back identically with [`Byml::to_binary_with_layout`].
+    ///
+    /// **Note**: If and only if the `yaz0` feature is enabled, this function
+    /// automatically decompresses the data when necessary.
+    pub fn from_binary_with_layout(data: impl AsRef<[u8]>) -> Result<(Byml, BymlLayout)> {
+        #[cfg(feature = "yaz0")]
+        {
+            if data.as_ref().starts_with(b"Yaz0") {
+                let data = crate::yaz0::decompress(data.as_ref())?;
+                return Ok((Byml::from_binary(&data)?, BymlLayout::new(&data)?));
+            }
+        }
+        let data = data.as_ref();
+        Ok((Byml::from_binary(data)?, BymlLayout::new(data)?))
+    }
+
+    /// Serialize the document to binary with the endianness, version and
+    /// arrangement of an existing layout, into the given writer. This can only
+    /// be done for Null, Array, or Hash nodes.
+    ///
+    /// Nodes found at the same path as in the original document are written
+    /// in their original order and at their original offsets, unless the data
+    /// before them has grown; new nodes are appended at the end. Such file
+    /// data also keeps the value stored after its size, which
+    /// [`Byml::FileData`] does not hold. Strings from
+    /// the original string tables are kept even if they are no longer used.
+    ///
+    /// The original version number is written as it is, without checking the
+    /// node types against it, since some game files contain nodes newer than
+    /// their header claims.
+    pub fn write_with_layout<W: Write>(&self, writer: &mut W, layout: &BymlLayout) -> Result<()> {
+        if !matches!(
+            self,
+            Byml::Map(_) | Byml::HashMap(_) | Byml::ValueHashMap(_) | Byml::Array(_) | Byml::Null
+        ) {
+            return Err(Error::TypeError(
+                format!("{:?}", self).into(),
+                "Map, HashMap, ValueHashMap, Array, or Null",
+            ));
+        }
+        writer.write_all(&LayoutWriter::new(self, layout).write())?;
+        Ok(())
+    }
+
+    /// Serialize the document to bytes with the endianness, version and
+    /// arrangement of an existing layout. See [`Byml::write_with_layout`].
+    pub fn to_binary_with_layout(&self, layout: &BymlLayout) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_with_layout(&mut buf, layout)
+            .expect("BYML should serialize to binary without error");
+        buf
+    }
+}
+
+/// How a container refers to a child.
+enum ChildKey<'a> {
+    Index(usize),
+    Key(&'a String),
+    Hash(u32, Option<u32>),
+}
+
+/// The children of a container node in the order they are written.
+fn sorted_children(node: &Byml) -> Vec<(ChildKey<'_>, &Byml)> {
+    match node {
+        Byml::Array(array) => {
+            array
+                .iter()
+                .enumerate()
+                .map(|(i, child)| (ChildKey::Index(i), child))
+                .collect()
+        }
+        Byml::Map(map) => {
+            let mut items = map.iter().collect::<Vec<_>>();
+            items.sort_by(|a, b| a.0.cmp(b.0));
+            items
+                .into_iter()
+                .map(|(key, child)| (ChildKey::Key(key), child))
+                .collect()
+        }
+        Byml::HashMap(map) => {
+            let mut items = map.iter().collect::<Vec<_>>();
+            items.sort_by_key(|(hash, _)| **hash);
+            items
+                .into_iter()
+                .map(|(hash, child)| (ChildKey::Hash(*hash, None), child))
+                .collect()
+        }
+        Byml::ValueHashMap(map) => {
+            let mut items = map.iter().collect::<Vec<_>>();
+            items.sort_by_key(|(hash, _)| **hash);
+            items
+                .into_iter()
+                .map(|(hash, (child, extra))| (ChildKey::Hash(*hash, Some(*extra)), child))
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// A node compared by exact equality, so that new nodes are only shared if
+/// they are identical. [`Byml`]'s `PartialEq` treats nearly equal floats as
+/// equal.
+struct Exact<'a>(&'a Byml);
+
+impl PartialEq for Exact<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        exact_eq(self.0, other.0)
+    }
+}
+
+impl Eq for Exact<'_> {}
+
+impl std::hash::Hash for Exact<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Floats are hashed by their bits, which is consistent with
+        // `exact_eq`.
+        self.0.hash(state)
+    }
+}
+
+/// Like `Byml`'s `PartialEq`, but comparing floats bitwise.
+fn exact_eq(a: &Byml, b: &Byml) -> bool {
+    match (a, b) {
+        (Byml::Float(a), Byml::Float(b)) => a.to_bits() == b.to_bits(),
+        (Byml::Double(a), Byml::Double(b)) => a.to_bits() == b.to_bits(),
+        (Byml::Array(a), Byml::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| exact_eq(a, b))
+        }
+        (Byml::Map(a), Byml::Map(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, a)| b.get(k).is_some_and(|b| exact_eq(a, b)))
+        }
+        (Byml::HashMap(a), Byml::HashMap(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, a)| b.get(k).is_some_and(|b| exact_eq(a, b)))
+        }
+        (Byml::ValueHashMap(a), Byml::ValueHashMap(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|(k, (a, extra))| {
+                    b.get(k)
+                        .is_some_and(|(b, other)| extra == other && exact_eq(a, b))
+                })
+        }
+        _ => a == b,
+    }
+}
+
+/// A node stored outside its parent.
+struct Slot<'a> {
+    node: &'a Byml,
+    /// Offset in the original document, if the node was found there.
+    original: Option<u32>,
+    /// Slots of the node's children in written order, `None` for children
+    /// stored inline.
+    children: Vec<Option<usize>>,
+    offset: u32,
+}
+
+/// A string table to be written.
+struct Table<'a> {
+    strings:  Vec<&'a str>,
+    original: u32,
+    offset:   u32,
+}
+
+impl<'a> Table<'a> {
+    fn new(original: &'a [String], used: rustc_hash::FxHashSet<&'a str>, offset: u32) -> Self {
+        let mut strings = original
+            .iter()
+            .map(|s| s.as_str())
+            .chain(used)
+            .collect::<Vec<_>>();
+        strings.sort_unstable();
+        strings.dedup();
+        Self {
+            strings,
+            original: offset,
+            offset: 0,
+        }
+    }
+
+    fn is_written(&self) -> bool {
+        !self.strings.is_empty() || self.original != 0
+    }
+
+    fn size(&self) -> u32 {
+        let n = self.strings.len() as u32;
+        align(
+            4 + 4 * (n + 1) + self.strings.iter().map(|s| s.len() as u32 + 1).sum::<u32>(),
+            4,
+        )
+    }
+
+    fn index(&self, s: &str) -> u32 {
+        self.strings
+            .binary_search(&s)
+            .expect("String should be in table") as u32
+    }
+}
+
+struct LayoutWriter<'a> {
+    layout: &'a BymlLayout,
+    root: &'a Byml,
+    slots: Vec<Slot<'a>>,
+    by_offset: FxHashMap<u32, usize>,
+    new_nodes: FxHashMap<Exact<'a>, usize>,
+    hash_keys: Table<'a>,
+    strings: Table<'a>,
+    data: Vec<u8>,
+}
+
+impl<'a> LayoutWriter<'a> {
+    fn new(root: &'a Byml, layout: &'a BymlLayout) -> Self {
+        let mut writer = Self {
+            layout,
+            root,
+            slots: vec![],
+            by_offset: Default::default(),
+            new_nodes: Default::default(),
+            hash_keys: Table::new(&[], Default::default(), 0),
+            strings: Table::new(&[], Default::default(), 0),
+            data: vec![],
+        };
+        let mut hash_keys = Default::default();
+        let mut strings = Default::default();
+        if !root.is_null() {
+            writer.assign(
+                root,
+                &mut std::string::String::new(),
+                &mut hash_keys,
+                &mut strings,
+            );
+        }
+        writer.hash_keys = Table::new(&layout.hash_keys, hash_keys, layout.hash_key_table);
+        writer.strings = Table::new(&layout.strings, strings, layout.string_table);
+        writer
+    }
+
+    /// Assign a slot to a non-inline node and its descendants, reusing the
+    /// slot of an identical node at the same original offset or of an
+    /// identical new node.
+    fn assign(
+        &mut self,
+        node: &'a Byml,
+        path: &mut std::string::String,
+        hash_keys: &mut rustc_hash::FxHashSet<&'a str>,
+        strings: &mut rustc_hash::FxHashSet<&'a str>,
+    ) -> usize {
+        let mut original = self.layout.nodes.get(path.as_str()).copied();
+        if let Some(offset) = original {
+            if let Some(&slot) = self.by_offset.get(&offset) {
+                if exact_eq(self.slots[slot].node, node) {
+                    return slot;
+                }
+                original = None;
+            }
+        }
+        if original.is_none() {
+            if let Some(&slot) = self.new_nodes.get(&Exact(node)) {
+                return slot;
+            }
+        }
+        let slot = self.slots.len();
+        self.slots.push(Slot {
+            node,
+            original,
+            children: vec![],
+            offset: 0,
+        });
+        match original {
+            Some(offset) => self.by_offset.insert(offset, slot),
+            None => self.new_nodes.insert(Exact(node), slot),
+        };
+        let len = path.len();
+        let children = sorted_children(node)
+            .into_iter()
+            .map(|(key, child)| {
+                match key {
+                    ChildKey::Index(i) => path.push_str(&format!("/{i}")),
+                    ChildKey::Key(key) => {
+                        hash_keys.insert(key.as_str());
+                        path.push('/');
+                        path.push_str(&escape(key));
+                    }
+                    ChildKey::Hash(hash, _) => path.push_str(&format!("/{hash}")),
+                }
+                let child_slot = if let Byml::String(s) = child {
+                    strings.insert(s.as_str());
+                    None
+                } else if child.is_non_inline_type() {
+                    Some(self.assign(child, path, hash_keys, strings))
+                } else {
+                    None
+                };
+                path.truncate(len);
+                child_slot
+            })
+            .collect();
+        self.slots[slot].children = children;
+        slot
+    }
+
+    fn slot_size(&self, slot: &Slot) -> u32 {
+        let n = slot.children.len() as u32;
+        match slot.node {
+            Byml::Array(_) => 4 + align(n, 4) + 4 * n,
+            Byml::Map(_) => 4 + 8 * n,
+            Byml::HashMap(_) => align(4 + 9 * n, 4),
+            Byml::ValueHashMap(_) => align(4 + 13 * n, 4),
+            Byml::BinaryData(data) => 4 + data.len() as u32,
+            Byml::FileData(data) => 8 + data.len() as u32,
+            _ => 8,
+        }
+    }
+
+    /// Decide where every table and node goes: in their original order, at
+    /// their original offsets unless earlier data has grown past them, with
+    /// new nodes at the end. Returns the size of the document.
+    fn place(&mut self) -> u32 {
+        enum Item {
+            HashKeys,
+            Strings,
+            Node(usize),
+        }
+        let mut items = vec![];
+        if self.hash_keys.is_written() {
+            items.push((Some(self.hash_keys.original), Item::HashKeys));
+        }
+        if self.strings.is_written() {
+            items.push((Some(self.strings.original), Item::Strings));
+        }
+        for (i, slot) in self.slots.iter().enumerate() {
+            items.push((slot.original, Item::Node(i)));
+        }
+        // New tables and nodes have no original offset and sort last.
+        items.sort_by_key(|(original, _)| original.map(|o| (0, o)).unwrap_or((1, 0)));
+
+        let mut end = 0x10;
+        for (original, item) in items {
+            // Nintendo's tools do not always align binary data.
+            let pos = match original {
+                Some(original) if original >= end => original,
+                _ => align(end, 4),
+            };
+            end = pos
+                + match item {
+                    Item::HashKeys => {
+                        self.hash_keys.offset = pos;
+                        self.hash_keys.size()
+                    }
+                    Item::Strings => {
+                        self.strings.offset = pos;
+                        self.strings.size()
+                    }
+                    Item::Node(i) => {
+                        self.slots[i].offset = pos;
+                        self.slot_size(&self.slots[i])
+                    }
+                };
+        }
+        let end = align(end, 4);
+        if end <= self.layout.size {
+            self.layout.size
+        } else {
+            end
+        }
+    }
+
+    fn put(&mut self, offset: u32, bytes: &[u8]) {
+        let offset = offset as usize;
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn put_u32(&mut self, offset: u32, value: u32) {
+        match self.layout.endian {
+            Endian::Big => self.put(offset, &value.to_be_bytes()),
+            Endian::Little => self.put(offset, &value.to_le_bytes()),
+        }
+    }
+
+    fn put_u64(&mut self, offset: u32, value: u64) {
+        match self.layout.endian {
+            Endian::Big => self.put(offset, &value.to_be_bytes()),
+            Endian::Little => self.put(offset, &value.to_le_bytes()),
+        }
+    }
+
+    /// Write a type byte and a 24-bit count or key index.
+    fn put_header(&mut self, offset: u32, first: u8, value: u32) {
+        match self.layout.endian {
+            Endian::Big => {
+                let [_, a, b, c] = value.to_be_bytes();
+                self.put(offset, &[first, a, b, c]);
+            }
+            Endian::Little => {
+                let [a, b, c, _] = value.to_le_bytes();
+                self.put(offset, &[first, a, b, c]);
+            }
+        }
+    }
+
+    fn put_table(&mut self, hash_keys: bool) {
+        let table = if hash_keys {
+            &self.hash_keys
+        } else {
+            &self.strings
+        };
+        let start = table.offset;
+        let strings = table.strings.clone();
+        self.put_header(start, NodeType::StringTable as u8, strings.len() as u32);
+        let mut pos = 4 + 4 * (strings.len() as u32 + 1);
+        for (i, s) in strings.iter().enumerate() {
+            self.put_u32(start + 4 + 4 * i as u32, pos);
+            self.put(start + pos, s.as_bytes());
+            pos += s.len() as u32 + 1;
+        }
+        self.put_u32(start + 4 + 4 * strings.len() as u32, pos);
+    }
+
+    /// The value stored in a container for a child.
+    fn child_value(&self, child: &Byml, slot: Option<usize>) -> u32 {
+        match (child, slot) {
+            (_, Some(slot)) => self.slots[slot].offset,
+            (Byml::String(s), _) => self.strings.index(s),
+            (Byml::Bool(b), _) => *b as u32,
+            (Byml::I32(i), _) => *i as u32,
+            (Byml::U32(u), _) => *u,
+            (Byml::Float(f), _) => f.to_bits(),
+            _ => 0,
+        }
+    }
+
+    fn put_slot(&mut self, slot: usize) {
+        let Slot {
+            node,
+            original,
+            offset,
+            ref children,
+        } = self.slots[slot];
+        let children = children.clone();
+        let entries = sorted_children(node);
+        let n = entries.len() as u32;
+        let node_type = node.get_node_type() as u8;
+        match node {
+            Byml::Array(_) => {
+                self.put_header(offset, node_type, n);
+                let values = offset + 4 + align(n, 4);
+                for (i, ((_, child), slot)) in entries.into_iter().zip(children).enumerate() {
+                    let i = i as u32;
+                    self.put(offset + 4 + i, &[child.get_node_type() as u8]);
+                    let value = self.child_value(child, slot);
+                    self.put_u32(values + 4 * i, value);
+                }
+            }
+            Byml::Map(_) => {
+                self.put_header(offset, node_type, n);
+                for (i, ((key, child), slot)) in entries.into_iter().zip(children).enumerate() {
+                    let entry = offset + 4 + 8 * i as u32;
+                    if let ChildKey::Key(key) = key {
+                        let index = self.hash_keys.index(key);
+                        // A 24-bit key index followed by the type.
+                        let bytes = match self.layout.endian {
+                            Endian::Big => {
+                                let [_, a, b, c] = index.to_be_bytes();
+                                [a, b, c, child.get_node_type() as u8]
+                            }
+                            Endian::Little => {
+                                let [a, b, c, _] = index.to_le_bytes();
+                                [a, b, c, child.get_node_type() as u8]
+                            }
+                        };
+                        self.put(entry, &bytes);
+                    }
+                    let value = self.child_value(child, slot);
+                    self.put_u32(entry + 4, value);
+                }
+            }
+            Byml::HashMap(_) | Byml::ValueHashMap(_) => {
+                self.put_header(offset, node_type, n);
+                let entry_size = if let Byml::HashMap(_) = node { 8 } else { 12 };
+                let types = offset + 4 + entry_size * n;
+                for (i, ((key, child), slot)) in entries.into_iter().zip(children).enumerate() {
+                    let entry = offset + 4 + entry_size * i as u32;
+                    let value = self.child_value(child, slot);
+                    if let ChildKey::Hash(hash, extra) = key {
+                        match extra {
+                            None => {
+                                self.put_u32(entry, hash);
+                                self.put_u32(entry + 4, value);
+                            }
+                            Some(extra) => {
+                                self.put_u32(entry, value);
+                                self.put_u32(entry + 4, hash);
+                                self.put_u32(entry + 8, extra);
+                            }
+                        }
+                    }
+                    self.put(types + i as u32, &[child.get_node_type() as u8]);
+                }
+            }
+            Byml::BinaryData(data) => {
+                self.put_u32(offset, data.len() as u32);
+                self.put(offset + 4, data);
+            }
+            Byml::FileData(data) => {
+                let extra = original
+                    .and_then(|original| self.layout.file_data_extras.get(&original).copied())
+                    .unwrap_or(DEFAULT_FILE_DATA_EXTRA);
+                self.put_u32(offset, data.len() as u32);
+                self.put_u32(offset + 4, extra);
+                self.put(offset + 8, data);
+            }
+            Byml::I64(i) => self.put_u64(offset, *i as u64),
+            Byml::U64(u) => self.put_u64(offset, *u),
+            Byml::Double(d) => self.put_u64(offset, d.to_bits()),
+            _ => unreachable!("Inline nodes have no slot"),
+        }
+    }
+
+    fn write(mut self) -> Vec<u8> {
+        let size = self.place();
+        self.data = vec![0; size as usize];
+        self.put(0, match self.layout.endian {
+            Endian::Big => b"BY",
+            Endian::Little => b"YB",
+        });
+        match self.layout.endian {
+            Endian::Big => self.put(2, &self.layout.version.to_be_bytes()),
+            Endian::Little => self.put(2, &self.layout.version.to_le_bytes()),
+        }
+        if self.hash_keys.is_written() {
+            self.put_u32(0x4, self.hash_keys.offset);
+            self.put_table(true);
+        }
+        if self.strings.is_written() {
+            self.put_u32(0x8, self.strings.offset);
+            self.put_table(false);
+        }
+        if !self.root.is_null() {
+            self.put_u32(0xC, self.slots[0].offset);
+            for slot in 0..self.slots.len() {
+                self.put_slot(slot);
+            }
+        }
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_roundtrip() {
+        for entry in std::fs::read_dir("test/byml").unwrap() {
+            let path = entry.unwrap().path();
+            let data = std::fs::read(&path).unwrap();
+            if path.extension().unwrap() != "byml" || data.starts_with(b"Yaz0") {
+                continue;
+            }
+            let (byml, layout) = Byml::from_binary_with_layout(&data).unwrap();
+            assert!(
+                byml.to_binary_with_layout(&layout) == data,
+                "{}",
+                path.display()
+            );
+        }
+
+        let data = std::fs::read("test/byml/ElectricGenerator.Nin_NX_NVN.esetb.byml").unwrap();
+        let (mut byml, layout) = Byml::from_binary_with_layout(&data).unwrap();
+        assert_eq!(layout.version(), 4);
+        byml.as_mut_map()
+            .unwrap()
+            .insert("New".into(), Byml::Array(vec!["Test".into(), Byml::U64(1)]));
+        let modified = byml.to_binary_with_layout(&layout);
+        assert_eq!(Byml::from_binary(&modified).unwrap(), byml);
+        // The file data is still aligned as in the original.
+        assert_eq!(modified[0x1000..0x1100], data[0x1000..0x1100]);
+    }
+
+    #[test]
+    fn file_data_extra() {
+        let mut data = std::fs::read("test/byml/ElectricGenerator.Nin_NX_NVN.esetb.byml").unwrap();
+        let (_, layout) = Byml::from_binary_with_layout(&data).unwrap();
+        let offset = *layout.file_data_extras.keys().next().unwrap() as usize + 4;
+        assert_eq!(layout.file_data_extras[&(offset as u32 - 4)], 0x1000);
+        data[offset..offset + 4].copy_from_slice(&0x80u32.to_le_bytes());
+        let (byml, layout) = Byml::from_binary_with_layout(&data).unwrap();
+        assert_eq!(byml.to_binary_with_layout(&layout), data);
+    }
+
+    #[test]
+    fn new_nodes_exact() {
+        let data = Byml::Array(vec![]).to_binary(Endian::Little);
+        let (_, layout) = Byml::from_binary_with_layout(&data).unwrap();
+        let byml = Byml::Array(vec![
+            Byml::Array(vec![Byml::Float(1.0)]),
+            Byml::Array(vec![Byml::Float(1.0 + f32::EPSILON)]),
+            Byml::Array(vec![Byml::Float(1.0)]),
+        ]);
+        let written = Byml::from_binary(byml.to_binary_with_layout(&layout)).unwrap();
+        let floats = written
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a.as_array().unwrap()[0].as_float().unwrap().to_bits())
+            .collect::<Vec<_>>();
+        assert_eq!(floats, [1.0f32, 1.0 + f32::EPSILON, 1.0].map(f32::to_bits));
+    }
+}
@@ -0,0 +1,261 @@
+/// Construct a [`Byml`](crate::byml::Byml) document from a JSON-like
+/// literal.
+///
+/// - `null` is [`Byml::Null`](crate::byml::Byml::Null).
+/// - `[a, b, c]` is an array and `{"key": value}` a map. Keys are a single
+///   token: a literal, a variable, or a parenthesized expression.
+/// - `!h {hash: value}` is a hash map with `u32` keys, and `!vh {(hash,
+///   extra): value}` a value hash map.
+/// - `i32(x)`, `u32(x)`, `f32(x)`, `f64(x)`, `i64(x)`, `u64(x)` and
+///   `binary(x)` force the node type of any expression.
+/// - Anything else is converted with `Byml::from`, so literals keep the node
+///   type of their Rust type: `1` is `I32`, `1u32` is `U32`, `1.0f32` is
+///   `Float` and `"text"` is `String`. Note that unsuffixed float literals
+///   are `f64`, and so become `Double` nodes.
+///
+/// # Example
+/// ```
+/// # use roead::{byml, byml::Byml};
+/// let name = "Enemy_Bokoblin_Junior";
+/// let doc = byml!({
+///     "Objs": [
+///         {
+///             "HashId": u32(0x1234),
+///             "UnitConfigName": name,
+///             "Translate": [1.0f32, 2.0f32, 3.0f32],
+///             "!Parameters": {"IsHardModeActor": true},
+///         },
+///     ],
+///     "Hashes": !h {0x10: 1, 0x20: null},
+///     "Raw": binary(vec![1u8, 2, 3]),
+/// });
+/// assert_eq!(doc["Objs"][0]["HashId"], Byml::U32(0x1234));
+/// assert_eq!(doc["Objs"][0]["Translate"][2], Byml::Float(3.0));
+/// ```
+#[macro_export]
+macro_rules! byml {
+    // Entry points
+    (null) => {
+        $crate::byml::Byml::Null
+    };
+    ([]) => {
+        $crate::byml::Byml::Array(::std::vec::Vec::new())
+    };
+    ([ $($tt:tt)+ ]) => {{
+        let mut array = ::std::vec::Vec::new();
+        $crate::byml!(@array array $($tt)+);
+        $crate::byml::Byml::Array(array)
+    }};
+    ({}) => {
+        $crate::byml::Byml::Map($crate::byml::Map::default())
+    };
+    ({ $($tt:tt)+ }) => {{
+        let mut map = $crate::byml::Map::default();
+        $crate::byml!(@map map map $($tt)+);
+        $crate::byml::Byml::Map(map)
+    }};
+    (! h {}) => {
+        $crate::byml::Byml::HashMap($crate::byml::HashMap::default())
+    };
+    (! h { $($tt:tt)+ }) => {{
+        let mut map = $crate::byml::HashMap::default();
+        $crate::byml!(@map hash map $($tt)+);
+        $crate::byml::Byml::HashMap(map)
+    }};
+    (! vh {}) => {
+        $crate::byml::Byml::ValueHashMap($crate::byml::ValueHashMap::default())
+    };
+    (! vh { $($tt:tt)+ }) => {{
+        let mut map = $crate::byml::ValueHashMap::default();
+        $crate::byml!(@map vhash map $($tt)+);
+        $crate::byml::Byml::ValueHashMap(map)
+    }};
+    (i32($value:expr)) => {
+        $crate::byml::Byml::I32($value)
+    };
+    (u32($value:expr)) => {
+        $crate::byml::Byml::U32($value)
+    };
+    (f32($value:expr)) => {
+        $crate::byml::Byml::Float($value)
+    };
+    (f64($value:expr)) => {
+        $crate::byml::Byml::Double($value)
+    };
+    (i64($value:expr)) => {
+        $crate::byml::Byml::I64($value)
+    };
+    (u64($value:expr)) => {
+        $crate::byml::Byml::U64($value)
+    };
+    (binary($value:expr)) => {
+        $crate::byml::Byml::BinaryData(::core::convert::Into::into($value))
+    };
+
+    // Array items
+    (@array $array:ident) => {};
+    (@array $array:ident , $($rest:tt)*) => {
+        $crate::byml!(@array $array $($rest)*);
+    };
+    (@array $array:ident null $($rest:tt)*) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!(null));
+        $crate::byml!(@array $array $($rest)*);
+    };
+    (@array $array:ident [ $($value:tt)* ] $($rest:tt)*) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!([ $($value)* ]));
+        $crate::byml!(@array $array $($rest)*);
+    };
+    (@array $array:ident { $($value:tt)* } $($rest:tt)*) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!({ $($value)* }));
+        $crate::byml!(@array $array $($rest)*);
+    };
+    (@array $array:ident ! $tag:ident { $($value:tt)* } $($rest:tt)*) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!(! $tag { $($value)* }));
+        $crate::byml!(@array $array $($rest)*);
+    };
+    (@array $array:ident $ty:ident ( $($value:tt)* ) , $($rest:tt)*) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!($ty ( $($value)* )));
+        $crate::byml!(@array $array $($rest)*);
+    };
+    (@array $array:ident $ty:ident ( $($value:tt)* )) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!($ty ( $($value)* )));
+    };
+    (@array $array:ident $value:expr , $($rest:tt)*) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!($value));
+        $crate::byml!(@array $array $($rest)*);
+    };
+    (@array $array:ident $value:expr) => {
+        ::std::vec::Vec::push(&mut $array, $crate::byml!($value));
+    };
+
+    // Map entries, where `$kind` is `map`, `hash` or `vhash`
+    (@map $kind:ident $map:ident) => {};
+    (@map $kind:ident $map:ident , $($rest:tt)*) => {
+        $crate::byml!(@map $kind $map $($rest)*);
+    };
+    (@map $kind:ident $map:ident $key:tt : null $($rest:tt)*) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!(null));
+        $crate::byml!(@map $kind $map $($rest)*);
+    };
+    (@map $kind:ident $map:ident $key:tt : [ $($value:tt)* ] $($rest:tt)*) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!([ $($value)* ]));
+        $crate::byml!(@map $kind $map $($rest)*);
+    };
+    (@map $kind:ident $map:ident $key:tt : { $($value:tt)* } $($rest:tt)*) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!({ $($value)* }));
+        $crate::byml!(@map $kind $map $($rest)*);
+    };
+    (@map $kind:ident $map:ident $key:tt : ! $tag:ident { $($value:tt)* } $($rest:tt)*) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!(! $tag { $($value)* }));
+        $crate::byml!(@map $kind $map $($rest)*);
+    };
+    (@map $kind:ident $map:ident $key:tt : $ty:ident ( $($value:tt)* ) , $($rest:tt)*) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!($ty ( $($value)* )));
+        $crate::byml!(@map $kind $map $($rest)*);
+    };
+    (@map $kind:ident $map:ident $key:tt : $ty:ident ( $($value:tt)* )) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!($ty ( $($value)* )));
+    };
+    (@map $kind:ident $map:ident $key:tt : $value:expr , $($rest:tt)*) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!($value));
+        $crate::byml!(@map $kind $map $($rest)*);
+    };
+    (@map $kind:ident $map:ident $key:tt : $value:expr) => {
+        $crate::byml!(@insert $kind $map $key, $crate::byml!($value));
+    };
+
+    (@insert map $map:ident $key:expr, $value:expr) => {
+        $map.insert(::core::convert::Into::into($key), $value);
+    };
+    (@insert hash $map:ident $key:expr, $value:expr) => {
+        $map.insert($key, $value);
+    };
+    (@insert vhash $map:ident $key:expr, $value:expr) => {
+        let (hash, extra) = $key;
+        $map.insert(hash, ($value, extra));
+    };
+
+    // Any other expression
+    ($other:expr) => {
+        $crate::byml::Byml::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byml::{Byml, HashMap, Map, ValueHashMap};
+
+    #[test]
+    fn scalars() {
+        assert_eq!(byml!(null), Byml::Null);
+        assert_eq!(byml!(1), Byml::I32(1));
+        assert_eq!(byml!(1u32), Byml::U32(1));
+        assert_eq!(byml!(-1i64), Byml::I64(-1));
+        assert_eq!(byml!(1u64), Byml::U64(1));
+        assert_eq!(byml!(1.5f32), Byml::Float(1.5));
+        assert_eq!(byml!(1.5), Byml::Double(1.5));
+        assert_eq!(byml!(true), Byml::Bool(true));
+        assert_eq!(byml!("text"), Byml::String("text".into()));
+        let x = 7;
+        assert_eq!(byml!(u32(x as u32 + 1)), Byml::U32(8));
+        assert_eq!(byml!(i32(x)), Byml::I32(7));
+        assert_eq!(byml!(f32(x as f32)), Byml::Float(7.0));
+        assert_eq!(byml!(f64(x.into())), Byml::Double(7.0));
+        assert_eq!(byml!(i64(x.into())), Byml::I64(7));
+        assert_eq!(byml!(u64(7)), Byml::U64(7));
+        assert_eq!(byml!(binary(vec![1u8, 2])), Byml::BinaryData(vec![1, 2]));
+        assert_eq!(byml!(binary(&b"ab"[..])), Byml::BinaryData(b"ab".to_vec()));
+    }
+
+    #[test]
+    fn containers() {
+        assert_eq!(byml!([]), Byml::Array(vec![]));
+        assert_eq!(byml!({}), Byml::Map(Map::default()));
+        assert_eq!(byml!(!h {}), Byml::HashMap(HashMap::default()));
+        assert_eq!(byml!(!vh {}), Byml::ValueHashMap(ValueHashMap::default()));
+
+        let key = "Dynamic";
+        let name = String::from("Obj");
+        let doc = byml!({
+            "a": [1, u32(2), null, [3.0f32], {"b": false}, !h {1: 2}, name.as_str(),],
+            key: i64(5),
+            ("c".to_string() + "d"): {},
+            "e": !vh {(1, 2): "x", (3, 4): [null]},
+            "f": u64(6)
+        });
+        let expected = Byml::Map(
+            [
+                (
+                    "a".into(),
+                    Byml::Array(vec![
+                        Byml::I32(1),
+                        Byml::U32(2),
+                        Byml::Null,
+                        Byml::Array(vec![Byml::Float(3.0)]),
+                        Byml::Map([("b".into(), Byml::Bool(false))].into_iter().collect()),
+                        Byml::HashMap([(1, Byml::I32(2))].into_iter().collect()),
+                        Byml::String("Obj".into()),
+                    ]),
+                ),
+                ("Dynamic".into(), Byml::I64(5)),
+                ("cd".into(), Byml::Map(Map::default())),
+                (
+                    "e".into(),
+                    Byml::ValueHashMap(
+                        [
+                            (1, (Byml::String("x".into()), 2)),
+                            (3, (Byml::Array(vec![Byml::Null]), 4)),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                ),
+                ("f".into(), Byml::U64(6)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(doc, expected);
+        assert_eq!(byml!([[], {}, null]).as_array().unwrap().len(), 3);
+    }
+}
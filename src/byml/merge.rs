@@ -0,0 +1,469 @@
+use std::borrow::Cow;
+
+use rustc_hash::FxHashMap;
+
+use super::{patch::item_keys, path::escape, *};
+
+/// A change made differently by both sides of a [`merge`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    /// Pointer to the conflicting node, as accepted by [`Byml::pointer`].
+    pub path:   String,
+    /// The node in the base document, or `None` if it did not exist.
+    pub base:   Option<Byml>,
+    /// Our node, or `None` if we deleted it.
+    pub ours:   Option<Byml>,
+    /// Their node, or `None` if they deleted it.
+    pub theirs: Option<Byml>,
+}
+
+/// The result of a three-way [`merge`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    /// The merged document. Where there is a conflict, it contains our
+    /// version of the node.
+    pub merged: Byml,
+    /// All conflicts, in document order.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+    /// Checks if the merge completed without conflicts.
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Identity keys for the arrays of maps in common BOTW files, as used by
+/// [`merge`]: `name` for the actors of `ActorInfo`, `HashId` for the objects
+/// and rails of map units, and `DataName` for the flag arrays of `gamedata`
+/// files.
+pub const DEFAULT_MERGE_KEYS: &[(&str, &str)] = &[
+    ("/Actors", "name"),
+    ("/Objs", "HashId"),
+    ("/Rails", "HashId"),
+    ("/*", "DataName"),
+];
+
+/// Merge the changes made to `base` in `ours` and in `theirs`, matching the
+/// items of arrays of maps by [`DEFAULT_MERGE_KEYS`].
+pub fn merge(base: &Byml, ours: &Byml, theirs: &Byml) -> MergeResult {
+    merge_with_keys(base, ours, theirs, DEFAULT_MERGE_KEYS)
+}
+
+/// Merge the changes made to `base` in `ours` and in `theirs`.
+///
+/// Maps and hash maps are merged key by key. Merged maps keep the key order
+/// of `base`, followed by keys added by us and then keys added by them.
+///
+/// `keys` are pairs of an array path pattern and an identity key. Patterns
+/// use the syntax of [`Byml::query`], where a `*` segment matches any key or
+/// item, e.g. `/Objs/*/LinksToObj`. An array whose path matches a pattern is
+/// merged item by item if the pattern's key has a unique string or integer
+/// value in every item of all three arrays: items added by either side are
+/// kept, in our order followed by their new items next to their neighbours.
+/// If several patterns match, the first usable one is taken. Other arrays are
+/// merged by position if all three have the same length. Any node changed by
+/// both sides in different ways, or changed by one side and deleted by the
+/// other, is a conflict.
+///
+/// ```
+/// # use roead::byml::{self, Byml};
+/// let base: Byml = [("a", Byml::I32(1)), ("b", Byml::I32(2))]
+///     .into_iter()
+///     .collect();
+/// let ours: Byml = [("a", Byml::I32(3)), ("b", Byml::I32(2))]
+///     .into_iter()
+///     .collect();
+/// let theirs: Byml = [("a", Byml::I32(1)), ("b", Byml::I32(4))]
+///     .into_iter()
+///     .collect();
+/// let result = byml::merge(&base, &ours, &theirs);
+/// assert!(result.is_clean());
+/// assert_eq!(result.merged["a"], Byml::I32(3));
+/// assert_eq!(result.merged["b"], Byml::I32(4));
+/// ```
+pub fn merge_with_keys(
+    base: &Byml,
+    ours: &Byml,
+    theirs: &Byml,
+    keys: &[(&str, &str)],
+) -> MergeResult {
+    let mut merger = Merger {
+        keys,
+        conflicts: vec![],
+    };
+    let merged = merger
+        .merge("", Some(base), Some(ours), Some(theirs))
+        .unwrap_or(Byml::Null);
+    MergeResult {
+        merged,
+        conflicts: merger.conflicts,
+    }
+}
+
+struct Merger<'a> {
+    keys: &'a [(&'a str, &'a str)],
+    conflicts: Vec<MergeConflict>,
+}
+
+/// An empty container of the same type as a node, used as the base when both
+/// sides add the same key.
+fn empty_like(node: &Byml) -> Option<Byml> {
+    match node {
+        Byml::Array(_) => Some(Byml::Array(vec![])),
+        Byml::Map(_) => Some(Byml::Map(Default::default())),
+        Byml::HashMap(_) => Some(Byml::HashMap(Default::default())),
+        Byml::ValueHashMap(_) => Some(Byml::ValueHashMap(Default::default())),
+        _ => None,
+    }
+}
+
+/// Checks if a pointer matches a pattern in which `*` segments match any
+/// segment.
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let (mut pattern, mut path) = (pattern.split('/'), path.split('/'));
+    loop {
+        match (pattern.next(), path.next()) {
+            (None, None) => return true,
+            (Some(p), Some(s)) if p == "*" || p == s => (),
+            _ => return false,
+        }
+    }
+}
+
+fn sorted_keys<'k, K: Ord + 'k>(keys: impl Iterator<Item = &'k K>) -> Vec<&'k K> {
+    let mut keys: Vec<&K> = keys.collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+impl Merger<'_> {
+    fn conflict(
+        &mut self,
+        path: &str,
+        base: Option<&Byml>,
+        ours: Option<&Byml>,
+        theirs: Option<&Byml>,
+    ) -> Option<Byml> {
+        self.conflicts.push(MergeConflict {
+            path:   path.into(),
+            base:   base.cloned(),
+            ours:   ours.cloned(),
+            theirs: theirs.cloned(),
+        });
+        ours.cloned()
+    }
+
+    fn merge(
+        &mut self,
+        path: &str,
+        base: Option<&Byml>,
+        ours: Option<&Byml>,
+        theirs: Option<&Byml>,
+    ) -> Option<Byml> {
+        if ours == theirs || base == theirs {
+            return ours.cloned();
+        }
+        if base == ours {
+            return theirs.cloned();
+        }
+        let (Some(o), Some(t)) = (ours, theirs) else {
+            return self.conflict(path, base, ours, theirs);
+        };
+        let empty;
+        let b = match base {
+            Some(b) => b,
+            None => {
+                match empty_like(o) {
+                    Some(e) => {
+                        empty = e;
+                        &empty
+                    }
+                    None => return self.conflict(path, base, ours, theirs),
+                }
+            }
+        };
+        match (b, o, t) {
+            (Byml::Map(b), Byml::Map(o), Byml::Map(t)) => {
+                let mut merged = Map::default();
+                let keys = b
+                    .keys()
+                    .chain(o.keys().filter(|k| !b.contains_key(*k)))
+                    .chain(t.keys().filter(|k| !b.contains_key(*k) && !o.contains_key(*k)));
+                for key in keys {
+                    if let Some(value) = self.merge(
+                        &format!("{path}/{}", escape(key)),
+                        b.get(key),
+                        o.get(key),
+                        t.get(key),
+                    ) {
+                        merged.insert(key.clone(), value);
+                    }
+                }
+                Some(Byml::Map(merged))
+            }
+            (Byml::HashMap(b), Byml::HashMap(o), Byml::HashMap(t)) => {
+                let mut merged = HashMap::default();
+                for &key in sorted_keys(b.keys().chain(o.keys()).chain(t.keys())) {
+                    if let Some(value) = self.merge(
+                        &format!("{path}/{key}"),
+                        b.get(&key),
+                        o.get(&key),
+                        t.get(&key),
+                    ) {
+                        merged.insert(key, value);
+                    }
+                }
+                Some(Byml::HashMap(merged))
+            }
+            (Byml::ValueHashMap(b), Byml::ValueHashMap(o), Byml::ValueHashMap(t)) => {
+                let mut merged = ValueHashMap::default();
+                for &key in sorted_keys(b.keys().chain(o.keys()).chain(t.keys())) {
+                    let child = format!("{path}/{key}");
+                    let (b, o, t) = (b.get(&key), o.get(&key), t.get(&key));
+                    let extra = |entry: Option<&(Byml, u32)>| entry.map(|(_, e)| Byml::U32(*e));
+                    let value = self.merge(
+                        &child,
+                        b.map(|(v, _)| v),
+                        o.map(|(v, _)| v),
+                        t.map(|(v, _)| v),
+                    );
+                    let extra = match (o, t) {
+                        (Some(_), Some(_)) => {
+                            self.merge(
+                                &child,
+                                extra(b).as_ref(),
+                                extra(o).as_ref(),
+                                extra(t).as_ref(),
+                            )
+                        }
+                        _ => extra(o).or_else(|| extra(t)),
+                    };
+                    if let (Some(value), Some(Byml::U32(extra))) = (value, extra) {
+                        merged.insert(key, (value, extra));
+                    }
+                }
+                Some(Byml::ValueHashMap(merged))
+            }
+            (Byml::Array(b), Byml::Array(o), Byml::Array(t)) => {
+                let keyed = self.keys.iter().find_map(|(pattern, key)| {
+                    if !matches_pattern(pattern, path) {
+                        return None;
+                    }
+                    Some((
+                        *key,
+                        item_keys(b, key)?,
+                        item_keys(o, key)?,
+                        item_keys(t, key)?,
+                    ))
+                });
+                match keyed {
+                    Some((key, b_keys, o_keys, t_keys)) => {
+                        Some(Byml::Array(self.merge_keyed_array(
+                            path,
+                            key,
+                            (b, &b_keys),
+                            (o, &o_keys),
+                            (t, &t_keys),
+                        )))
+                    }
+                    None if b.len() == o.len() && o.len() == t.len() => {
+                        Some(Byml::Array(
+                            b.iter()
+                                .zip(o)
+                                .zip(t)
+                                .enumerate()
+                                .filter_map(|(i, ((b, o), t))| {
+                                    self.merge(&format!("{path}/{i}"), Some(b), Some(o), Some(t))
+                                })
+                                .collect(),
+                        ))
+                    }
+                    None => self.conflict(path, base, ours, theirs),
+                }
+            }
+            _ => self.conflict(path, base, ours, theirs),
+        }
+    }
+
+    fn merge_keyed_array(
+        &mut self,
+        path: &str,
+        key: &str,
+        (base, base_keys): (&[Byml], &[Cow<str>]),
+        (ours, our_keys): (&[Byml], &[Cow<str>]),
+        (theirs, their_keys): (&[Byml], &[Cow<str>]),
+    ) -> Vec<Byml> {
+        let index = |keys: &[Cow<str>]| -> FxHashMap<std::string::String, usize> {
+            keys.iter()
+                .enumerate()
+                .map(|(i, k)| (k.to_string(), i))
+                .collect()
+        };
+        let (base_index, our_index, their_index) =
+            (index(base_keys), index(our_keys), index(their_keys));
+        let selector = |value: &str| format!("{path}/{}={}", escape(key), escape(value));
+
+        let mut merged = Vec::with_capacity(ours.len());
+        let mut merged_index = FxHashMap::default();
+        for (item, value) in ours.iter().zip(our_keys) {
+            if let Some(item) = self.merge(
+                &selector(value),
+                base_index.get(value.as_ref()).map(|&i| &base[i]),
+                Some(item),
+                their_index.get(value.as_ref()).map(|&i| &theirs[i]),
+            ) {
+                merged_index.insert(value.as_ref(), merged.len());
+                merged.push(item);
+            }
+        }
+        // Items only in their array go after the merged item which precedes
+        // them in their array.
+        let mut inserts: Vec<(usize, Byml)> = vec![];
+        let mut anchor = 0;
+        for (item, value) in theirs.iter().zip(their_keys) {
+            if let Some(&i) = merged_index.get(value.as_ref()) {
+                anchor = i + 1;
+            } else if !our_index.contains_key(value.as_ref()) {
+                if let Some(item) = self.merge(
+                    &selector(value),
+                    base_index.get(value.as_ref()).map(|&i| &base[i]),
+                    None,
+                    Some(item),
+                ) {
+                    inserts.push((anchor, item));
+                }
+            }
+        }
+        if inserts.is_empty() {
+            return merged;
+        }
+        let mut result = Vec::with_capacity(merged.len() + inserts.len());
+        let mut inserts = inserts.into_iter().peekable();
+        for (i, item) in merged.into_iter().enumerate() {
+            while let Some((_, insert)) = inserts.next_if(|(anchor, _)| *anchor == i) {
+                result.push(insert);
+            }
+            result.push(item);
+        }
+        result.extend(inserts.map(|(_, insert)| insert));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_actor_info() {
+        let base =
+            Byml::from_binary(std::fs::read("test/byml/ActorInfo.product.byml").unwrap()).unwrap();
+        let mut ours = base.clone();
+        let mut theirs = base.clone();
+        *ours.pointer_mut("/Actors/10/instSize").unwrap() = Byml::I32(1);
+        *theirs.pointer_mut("/Actors/20/instSize").unwrap() = Byml::I32(2);
+        let mut new_actor = base["Actors"][0].clone();
+        new_actor["name"] = "Enemy_Test".into();
+        theirs["Actors"]
+            .as_mut_array()
+            .unwrap()
+            .insert(5, new_actor.clone());
+        ours["Actors"].as_mut_array().unwrap().remove(30);
+
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.is_clean());
+        let actors = result.merged["Actors"].as_array().unwrap();
+        assert_eq!(actors.len(), base["Actors"].as_array().unwrap().len());
+        assert_eq!(actors[5], new_actor);
+        assert_eq!(actors[11]["instSize"], Byml::I32(1));
+        assert_eq!(actors[21]["instSize"], Byml::I32(2));
+
+        let name = base["Actors"][10]["name"].as_string().unwrap();
+        *theirs
+            .pointer_mut(&format!("/Actors/name={name}/instSize"))
+            .unwrap() = Byml::I32(3);
+        let result = merge(&base, &ours, &theirs);
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(result.merged.pointer(&conflict.path), Some(&Byml::I32(1)));
+        assert_eq!(conflict.theirs, Some(Byml::I32(3)));
+        assert!(conflict.path.starts_with("/Actors/name="));
+    }
+
+    #[test]
+    fn merge_order() {
+        let base = crate::byml!({"z": 1, "a": 2, "m": 3});
+        let ours = crate::byml!({"z": 1, "new": 4, "a": 5, "m": 3});
+        let theirs = crate::byml!({"z": 1, "a": 2, "m": 6, "b": 7});
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.is_clean());
+        let keys: Vec<&str> = result
+            .merged
+            .as_map()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        assert_eq!(keys, ["z", "a", "m", "new", "b"]);
+        assert_eq!(result.merged, crate::byml!({"z": 1, "a": 5, "m": 6, "new": 4, "b": 7}));
+    }
+
+    #[test]
+    fn merge_keys_by_path() {
+        let base = crate::byml!({
+            "Objs": [{"HashId": 1, "name": "A"}, {"HashId": 2, "name": "B"}],
+            "Other": [{"HashId": 1, "name": "A"}, {"HashId": 2, "name": "B"}],
+        });
+        let add = |doc: &Byml, array: &str, item: Byml| {
+            let mut doc = doc.clone();
+            doc[array].as_mut_array().unwrap().push(item);
+            doc
+        };
+        let ours = add(&base, "Objs", crate::byml!({"HashId": 3, "name": "C"}));
+        let ours = add(&ours, "Other", crate::byml!({"HashId": 3, "name": "C"}));
+        let theirs = add(&base, "Objs", crate::byml!({"HashId": 4, "name": "D"}));
+        let theirs = add(&theirs, "Other", crate::byml!({"HashId": 4, "name": "D"}));
+        // Only `/Objs` has an identity key, so `/Other` is merged by position.
+        let result = merge(&base, &ours, &theirs);
+        assert_eq!(result.merged["Objs"].as_array().unwrap().len(), 4);
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].path, "/Other");
+        let result = merge_with_keys(&base, &ours, &theirs, &[("/*", "name")]);
+        assert!(result.is_clean());
+        assert_eq!(result.merged["Other"].as_array().unwrap().len(), 4);
+        assert_eq!(result.merged["Other"][2]["name"], Byml::from("D"));
+        assert!(matches_pattern("/Objs/*/Links", "/Objs/HashId=1/Links"));
+        assert!(!matches_pattern("/Objs/*", "/Objs"));
+    }
+
+    #[test]
+    fn merge_hash_maps() {
+        let base = Byml::ValueHashMap([(1, (Byml::I32(1), 0))].into_iter().collect());
+        let ours = Byml::ValueHashMap([(1, (Byml::I32(1), 5))].into_iter().collect());
+        let theirs = Byml::ValueHashMap(
+            [(1, (Byml::I32(2), 0)), (2, (Byml::Null, 0))]
+                .into_iter()
+                .collect(),
+        );
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.is_clean());
+        assert_eq!(
+            result.merged,
+            Byml::ValueHashMap(
+                [(1, (Byml::I32(2), 5)), (2, (Byml::Null, 0))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        let result = merge(
+            &Byml::Array(vec![Byml::I32(1)]),
+            &Byml::Array(vec![Byml::I32(2)]),
+            &Byml::Array(vec![Byml::I32(1), Byml::I32(3)]),
+        );
+        assert_eq!(result.conflicts[0].path, "");
+    }
+}
@@ -42,8 +42,16 @@
 //! ```
 //!
 //! Most of the node types are fairly self-explanatory. Arrays are implemented
-//! as `Vec<Byml>`, and maps as `FxHashMap<String, Byml>`. The new v7 hash maps
-//! are `FxHashMap<u32, Byml>` and `FxHashMap<u32, (Byml, u32)>`.
+//! as `Vec<Byml>`, and maps as `IndexMap<String, Byml>`, which keeps keys in
+//! insertion order. Documents parsed from YAML then keep their source order
+//! when emitted again, and documents parsed from binary are in sorted order.
+//! With the `yaml` feature, `Byml::to_text_sorted` emits sorted YAML. The new
+//! v7 hash maps are `FxHashMap<u32, Byml>` and `FxHashMap<u32, (Byml, u32)>`.
+//!
+//! With the `byml-interned` feature, [`Byml::from_binary_interned`] reads a
+//! document into a read-only [`SharedByml`], whose strings and map keys are
+//! reference-counted [`SharedString`]s from a [`StringPool`]. Equal strings in
+//! all documents using the pool then share one allocation.
 //!
 //! For convenience, a `Byml` *known* to be an array or map can be
 //! indexed. **Panics if the node has the wrong type, the index has the wrong
@@ -58,11 +66,59 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! To read or edit deep documents without panicking, use [`Byml::get_path`],
+//! [`Byml::pointer`] or [`Byml::query`], which also supports wildcards:
+//! ```
+//! # use roead::byml::Byml;
+//! # fn docttest() -> Result<(), Box<dyn std::error::Error>> {
+//! # let buf: Vec<u8> = std::fs::read("test/byml/ActorInfo.product.byml")?;
+//! let mut actor_info = Byml::from_binary(&buf)?;
+//! assert!(actor_info.pointer("/Actors/12/name").is_some());
+//! for name in actor_info.query_mut("/Actors/*/name") {
+//!     *name = "Renamed".into();
+//! }
+//! # Ok(())
+//! # }
+//! ```
+mod builder;
+#[cfg(feature = "with-serde")]
+mod de;
+#[cfg(feature = "json")]
+mod json;
+mod layout;
+mod macros;
+mod merge;
+mod names;
+mod patch;
+mod path;
+#[cfg(feature = "with-serde")]
+mod ser;
+#[cfg(feature = "byml-interned")]
+mod shared;
 #[cfg(feature = "yaml")]
 mod text;
+mod version;
+mod visit;
+mod view;
 mod writer;
+pub use builder::BymlBuilder;
+#[cfg(feature = "with-serde")]
+pub use de::{from_binary, from_byml};
+pub use layout::BymlLayout;
+pub use merge::{merge, merge_with_keys, MergeConflict, MergeResult, DEFAULT_MERGE_KEYS};
+pub use names::{get_default_hash_name_table, murmur3, HashNameTable, KeyHash};
 use num_traits::AsPrimitive;
+pub use parser::ParseLimits;
+pub use patch::{diff, diff_with_keys, BymlPatch, PatchOp, DEFAULT_ARRAY_KEYS};
+#[cfg(feature = "with-serde")]
+pub use ser::{to_binary, to_byml, Serializer};
+#[cfg(feature = "byml-interned")]
+pub use shared::{SharedByml, SharedMap, SharedString, StringPool};
 use smartstring::alias::String;
+pub use version::{Conversion, MigrationReport, VersionIssue};
+pub use view::BymlRef;
+pub use visit::{OwnedBymlIndex, Visit, VisitMut};
 
 use crate::{Error, Result};
 mod parser;
@@ -117,12 +173,14 @@ pub enum BymlError {
     ParseError(&'static str),
 }
 
-/// A BYML hash node.
-pub type Map = rustc_hash::FxHashMap<String, Byml>;
+/// A BYML hash node. Keys keep their insertion order.
+pub type Map =
+    indexmap::IndexMap<String, Byml, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
 pub type HashMap = rustc_hash::FxHashMap<u32, Byml>;
 pub type ValueHashMap = rustc_hash::FxHashMap<u32, (Byml, u32)>;
 
 /// Convenience type used for indexing into `Byml`s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BymlIndex<'a> {
     /// Index into a hash node. The key is a string.
     StringIdx(&'a str),
@@ -163,6 +221,11 @@ impl<'a> From<u32> for BymlIndex<'a> {
     }
 }
 
+/// The value written after the size of file data. Known documents always
+/// use 0x1000, which is also the alignment of the data; layout-preserving
+/// writes keep the original value (see [`BymlLayout`]).
+const DEFAULT_FILE_DATA_EXTRA: u32 = 0x1000;
+
 /// Represents a Nintendo binary YAML (BYML) document or node.
 #[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone)]
@@ -200,7 +263,7 @@ pub enum Byml {
 }
 
 impl Byml {
-    fn type_name(&self) -> String {
+    pub(crate) fn type_name(&self) -> String {
         match self {
             Byml::String(_) => "String".into(),
             Byml::BinaryData(_) => "Binary".into(),
@@ -903,6 +966,22 @@ impl PartialEq<Byml> for &Byml {
 
 impl Eq for &Byml {}
 
+/// Hashes map entries independently of their order, since map equality
+/// ignores it.
+fn hash_entries<K: std::hash::Hash, V: std::hash::Hash, H: std::hash::Hasher>(
+    entries: impl ExactSizeIterator<Item = (K, V)>,
+    state: &mut H,
+) {
+    use std::hash::Hasher;
+    state.write_usize(entries.len());
+    let sum = entries.fold(0u64, |sum, entry| {
+        let mut hasher = rustc_hash::FxHasher::default();
+        std::hash::Hash::hash(&entry, &mut hasher);
+        sum.wrapping_add(hasher.finish())
+    });
+    state.write_u64(sum);
+}
+
 impl std::hash::Hash for Byml {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
@@ -910,24 +989,9 @@ impl std::hash::Hash for Byml {
             Byml::BinaryData(b) => b.hash(state),
             Byml::FileData(b) => b.hash(state),
             Byml::Array(a) => a.hash(state),
-            Byml::Map(h) => {
-                for (k, v) in h.iter() {
-                    k.hash(state);
-                    v.hash(state);
-                }
-            }
-            Byml::HashMap(h) => {
-                for (k, v) in h.iter() {
-                    k.hash(state);
-                    v.hash(state);
-                }
-            }
-            Byml::ValueHashMap(h) => {
-                for (k, v) in h.iter() {
-                    k.hash(state);
-                    v.hash(state);
-                }
-            }
+            Byml::Map(h) => hash_entries(h.iter(), state),
+            Byml::HashMap(h) => hash_entries(h.iter(), state),
+            Byml::ValueHashMap(h) => hash_entries(h.iter(), state),
             Byml::Bool(b) => b.hash(state),
             Byml::I32(i) => i.hash(state),
             Byml::Float(f) => {
@@ -1051,4 +1115,18 @@ mod tests {
             assert_eq!(hash["name"].as_string().unwrap(), "test");
         }
     }
+
+    #[test]
+    fn map_hash_ignores_order() {
+        use std::hash::{BuildHasher, BuildHasherDefault};
+        let a: Byml = [("a", Byml::I32(1)), ("b", Byml::I32(2))]
+            .into_iter()
+            .collect();
+        let b: Byml = [("b", Byml::I32(2)), ("a", Byml::I32(1))]
+            .into_iter()
+            .collect();
+        assert_eq!(a, b);
+        let hasher = BuildHasherDefault::<rustc_hash::FxHasher>::default();
+        assert_eq!(hasher.hash_one(&a), hasher.hash_one(&b));
+    }
 }
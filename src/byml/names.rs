@@ -0,0 +1,193 @@
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use rustc_hash::FxHashMap;
+
+/// The hash function used for the keys of BYML v7 hash maps.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyHash {
+    /// 32-bit MurmurHash3 (x86) with seed 0, used for hashed names in
+    /// *Tears of the Kingdom*.
+    #[default]
+    Murmur3,
+    /// CRC32, as used for AAMP names and most *Breath of the Wild* hashes.
+    Crc32,
+}
+
+impl KeyHash {
+    /// Hash a key name.
+    #[inline]
+    pub const fn hash(self, name: &str) -> u32 {
+        match self {
+            KeyHash::Murmur3 => murmur3(name.as_bytes(), 0),
+            KeyHash::Crc32 => crate::util::crc32(name.as_bytes()),
+        }
+    }
+}
+
+/// 32-bit MurmurHash3 (x86 variant).
+pub const fn murmur3(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+    let mut hash = seed;
+    let blocks = data.len() / 4;
+    let mut i = 0;
+    while i < blocks {
+        let mut k = u32::from_le_bytes([
+            data[i * 4],
+            data[i * 4 + 1],
+            data[i * 4 + 2],
+            data[i * 4 + 3],
+        ]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+        i += 1;
+    }
+    let tail = blocks * 4;
+    let mut k = 0u32;
+    let rest = data.len() - tail;
+    if rest >= 3 {
+        k ^= (data[tail + 2] as u32) << 16;
+    }
+    if rest >= 2 {
+        k ^= (data[tail + 1] as u32) << 8;
+    }
+    if rest >= 1 {
+        k ^= data[tail] as u32;
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+    }
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^= hash >> 16;
+    hash
+}
+
+/// A table of names used to recover the original keys of BYML v7 hash maps
+/// (`!h` and `!vh` nodes), which store only key hashes.
+///
+/// This is the BYML counterpart of
+/// [`aamp::NameTable`](crate::aamp::NameTable). When converting to YAML,
+/// keys whose names are known are written as strings and the rest as plain
+/// integers. When parsing YAML with
+/// [`Byml::from_text_with_names`](super::Byml::from_text_with_names), string
+/// keys are hashed with the table's hash function, and the names are added to
+/// the table so they are recovered again.
+/// [`Byml::from_text`](super::Byml::from_text) hashes string keys with the
+/// default table but never adds their names to it.
+///
+/// The default table starts out empty and uses [`KeyHash::Murmur3`].
+#[derive(Debug, Default)]
+pub struct HashNameTable {
+    hash:  KeyHash,
+    names: RwLock<FxHashMap<u32, String>>,
+}
+
+impl HashNameTable {
+    /// Create an empty name table using the given hash function.
+    pub fn new(hash: KeyHash) -> Self {
+        Self {
+            hash,
+            names: Default::default(),
+        }
+    }
+
+    /// The hash function used by this table.
+    #[inline]
+    pub fn key_hash(&self) -> KeyHash {
+        self.hash
+    }
+
+    /// Hash a name with this table's hash function.
+    #[inline]
+    pub fn hash_name(&self, name: &str) -> u32 {
+        self.hash.hash(name)
+    }
+
+    /// Add a known name to the table, returning its hash.
+    pub fn add_name(&self, name: impl Into<String>) -> u32 {
+        let name = name.into();
+        let hash = self.hash_name(&name);
+        self.add_name_with_hash(name, hash);
+        hash
+    }
+
+    /// Add a known name to the table if you already know the hash (to avoid
+    /// computing it).
+    pub fn add_name_with_hash(&self, name: impl Into<String>, hash: u32) {
+        self.names.write().entry(hash).or_insert_with(|| name.into());
+    }
+
+    /// Add each line of a list of names to the table.
+    pub fn add_names(&self, names: &str) {
+        let mut table = self.names.write();
+        for name in names.lines().map(str::trim).filter(|n| !n.is_empty()) {
+            table
+                .entry(self.hash_name(name))
+                .or_insert_with(|| name.into());
+        }
+    }
+
+    /// Look up the name for a hash.
+    pub fn get_name(&self, hash: u32) -> Option<String> {
+        self.with_names(|names| names.get(&hash).cloned())
+    }
+
+    /// Return the number of known names.
+    pub fn len(&self) -> usize {
+        self.with_names(|names| names.len())
+    }
+
+    /// Returns `true` if no names are known.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read the names without cloning them.
+    pub(crate) fn with_names<T>(&self, f: impl FnOnce(&FxHashMap<u32, String>) -> T) -> T {
+        f(&self.names.read())
+    }
+}
+
+static DEFAULT_HASH_NAME_TABLE: Lazy<HashNameTable> = Lazy::new(Default::default);
+
+/// Returns the default name table for hash map keys, which is used when
+/// converting BYML documents to and from YAML. It starts out empty and has
+/// interior mutability, so names can be added to it at any time.
+pub fn get_default_hash_name_table() -> &'static HashNameTable {
+    &DEFAULT_HASH_NAME_TABLE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes() {
+        assert_eq!(murmur3(b"", 0), 0);
+        assert_eq!(murmur3(b"", 1), 0x514e28b7);
+        assert_eq!(murmur3(b"test", 0), 0xba6bd213);
+        assert_eq!(murmur3(b"Hello, world!", 0), 0xc0363e43);
+        assert_eq!(murmur3(b"The quick brown fox jumps over the lazy dog", 0), 0x2e4ff723);
+        assert_eq!(KeyHash::Crc32.hash("Hello"), crate::util::crc32(b"Hello"));
+    }
+
+    #[test]
+    fn table() {
+        let table = HashNameTable::new(KeyHash::Murmur3);
+        assert!(table.is_empty());
+        let hash = table.add_name("test");
+        assert_eq!(hash, 0xba6bd213);
+        assert_eq!(table.get_name(hash).as_deref(), Some("test"));
+        table.add_names("Alpha\nBeta\n\n");
+        assert_eq!(table.len(), 3);
+        assert_eq!(
+            table.get_name(KeyHash::Murmur3.hash("Beta")).as_deref(),
+            Some("Beta")
+        );
+        assert_eq!(table.get_name(0), None);
+    }
+}
@@ -1,34 +1,189 @@
-use std::io::{Read, Seek, SeekFrom};
+use std::{
+    hash::BuildHasherDefault,
+    io::{Read, Seek, SeekFrom},
+};
 
 use binrw::{binrw, BinRead, VecArgs};
+use rustc_hash::{FxHashMap, FxHasher};
 
-use super::*;
+use super::{view::Document, *};
 use crate::{
     util::{align, u24, SeekShim},
     Endian, Error, Result,
 };
 
+/// Limits on the documents accepted by the binary BYML parsers, so that
+/// untrusted files cannot exhaust the stack or memory. Regardless of the
+/// limits, the parsers reject containers which contain themselves and
+/// offsets or sizes which reach past the end of the data.
+///
+/// The default limits are far above what game files need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum nesting depth of container nodes, counting the root.
+    pub max_depth: usize,
+    /// Maximum number of nodes. Nodes shared by several containers are
+    /// counted once for each reference.
+    pub max_nodes: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 256,
+            max_nodes: 1 << 24,
+        }
+    }
+}
+
+/// Enforces [`ParseLimits`] and detects cycles while parsing.
+pub(super) struct Guard {
+    limits: ParseLimits,
+    nodes: usize,
+    /// Offsets of the containers being parsed.
+    ancestors: Vec<u32>,
+}
+
+impl Guard {
+    pub(super) fn new(limits: ParseLimits) -> Self {
+        Self {
+            limits,
+            nodes: 0,
+            ancestors: vec![],
+        }
+    }
+
+    /// Count a node at an offset.
+    pub(super) fn node(&mut self, offset: u32) -> Result<()> {
+        self.nodes += 1;
+        if self.nodes > self.limits.max_nodes {
+            return Err(Error::InvalidDataAt(
+                offset as usize,
+                format!(
+                    "BYML document has more than {} nodes",
+                    self.limits.max_nodes
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Count and start parsing the container at an offset.
+    pub(super) fn enter(&mut self, offset: u32) -> Result<()> {
+        self.node(offset)?;
+        if self.ancestors.contains(&offset) {
+            return Err(Error::InvalidDataAt(
+                offset as usize,
+                "BYML container contains itself".into(),
+            ));
+        }
+        if self.ancestors.len() >= self.limits.max_depth {
+            return Err(Error::InvalidDataAt(
+                offset as usize,
+                format!(
+                    "BYML containers are nested deeper than {}",
+                    self.limits.max_depth
+                ),
+            ));
+        }
+        self.ancestors.push(offset);
+        Ok(())
+    }
+
+    /// Finish parsing the current container.
+    pub(super) fn exit(&mut self) {
+        self.ancestors.pop();
+    }
+}
+
+/// Check that a container node with the given number of entries fits in the
+/// data, before anything is allocated for it.
+pub(super) fn check_container(
+    data_len: u64,
+    offset: u32,
+    node_type: NodeType,
+    len: u32,
+) -> Result<()> {
+    let len64 = len as u64;
+    let size = match node_type {
+        NodeType::Array => 4 + align(len, 4) as u64 + 4 * len64,
+        NodeType::Map => 4 + 8 * len64,
+        NodeType::HashMap => 4 + 9 * len64,
+        NodeType::ValueHashMap => 4 + 13 * len64,
+        _ => {
+            return Err(Error::InvalidDataAt(
+                offset as usize,
+                format!("Invalid BYML container node type: {node_type:?}"),
+            ));
+        }
+    };
+    if offset as u64 + size > data_len.min(u32::MAX as u64) {
+        return Err(Error::InvalidDataAt(
+            offset as usize,
+            format!("BYML {node_type:?} node with {len} entries extends past the end of the data"),
+        ));
+    }
+    Ok(())
+}
+
+/// Check that a binary or file data node fits in the data.
+fn check_data(data_len: u64, offset: u32, header_size: u32, size: u32) -> Result<()> {
+    if offset as u64 + header_size as u64 + size as u64 > data_len {
+        return Err(Error::InvalidDataAt(
+            offset as usize,
+            format!("BYML data node of {size:#x} bytes extends past the end of the data"),
+        ));
+    }
+    Ok(())
+}
+
 impl Byml {
-    /// Read a document from a binary reader.
+    /// Read a document from a binary reader, with the default
+    /// [`ParseLimits`].
     pub fn read<R: Read + Seek>(reader: R) -> Result<Byml> {
-        Parser::new(reader)?.parse()
+        Self::read_with_limits(reader, ParseLimits::default())
+    }
+
+    /// Read a document from a binary reader, rejecting documents which exceed
+    /// the given limits.
+    pub fn read_with_limits<R: Read + Seek>(reader: R, limits: ParseLimits) -> Result<Byml> {
+        Parser::new(reader, limits)?.parse()
     }
 
     /// Load a document from binary data.
     ///
     /// **Note**: If and only if the `yaz0` feature is enabled, this function
     /// automatically decompresses the SARC when necessary.
+    ///
+    /// This uses a parser specialised for in-memory data, which is faster than
+    /// [`Byml::read`] with a cursor. The default [`ParseLimits`] apply.
     pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Byml> {
+        Self::from_binary_with_limits(data, ParseLimits::default())
+    }
+
+    /// Load a document from binary data, rejecting documents which exceed the
+    /// given limits. Use this to load untrusted files:
+    /// ```
+    /// # use roead::byml::{Byml, ParseLimits};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let data = std::fs::read("test/byml/ActorInfo.product.byml")?;
+    /// let limits = ParseLimits {
+    ///     max_depth: 8,
+    ///     max_nodes: 1_000_000,
+    /// };
+    /// let actor_info = Byml::from_binary_with_limits(&data, limits)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_binary_with_limits(data: impl AsRef<[u8]>, limits: ParseLimits) -> Result<Byml> {
         #[cfg(feature = "yaz0")]
         {
             if data.as_ref().starts_with(b"Yaz0") {
-                return Parser::new(std::io::Cursor::new(crate::yaz0::decompress(
-                    data.as_ref(),
-                )?))?
-                .parse();
+                let data = crate::yaz0::decompress(data.as_ref())?;
+                return SliceParser::new(&data, limits, &mut ())?.parse();
             }
         }
-        Parser::new(std::io::Cursor::new(data.as_ref()))?.parse()
+        SliceParser::new(data.as_ref(), limits, &mut ())?.parse()
     }
 }
 
@@ -114,6 +269,13 @@ impl StringTableParser {
                     "string table",
                 ));
             }
+            let len = SeekShim::stream_len(&mut reader.reader)?;
+            if offset as u64 + 8 + 4 * num_entries.as_u32() as u64 > len {
+                return Err(Error::InvalidDataAt(
+                    offset as usize,
+                    "BYML string table extends past the end of the data".into(),
+                ));
+            }
             Ok(Self {
                 offset,
                 size: num_entries.as_u32(),
@@ -125,22 +287,23 @@ impl StringTableParser {
         if index >= self.size {
             return Err(Error::InvalidData("Invalid string table entry index"));
         }
-        let offset: u32 = reader.read_at((self.offset + 4 + 4 * index) as u64)?;
+        let entry = self.offset + 4 + 4 * index;
+        let offset: u32 = reader.read_at(entry as u64)?;
         let next_offset: u32 = reader.read()?;
-        let max_len = (next_offset - offset) as usize;
-        reader.seek((self.offset + offset) as u64)?;
-        let mut string_ = [0; 1024];
+        let max_len = next_offset.checked_sub(offset).ok_or_else(|| {
+            Error::InvalidDataAt(entry as usize, "Invalid BYML string table entry".into())
+        })? as usize;
+        reader.seek(self.offset as u64 + offset as u64)?;
+        let mut string_ = Vec::with_capacity(max_len.min(1024));
         let mut c: u8 = reader.read()?;
-        let mut i = 0;
         while c != 0 {
-            string_[i] = c;
-            i += 1;
-            if i == max_len {
+            string_.push(c);
+            if string_.len() == max_len {
                 break;
             }
             c = reader.read()?;
         }
-        Ok(std::str::from_utf8(&string_[..i])?.into())
+        Ok(std::str::from_utf8(&string_)?.into())
     }
 }
 
@@ -149,11 +312,14 @@ struct Parser<R: Read + Seek> {
     string_table: StringTableParser,
     hash_key_table: StringTableParser,
     root_node_offset: u32,
+    len: u64,
+    guard: Guard,
 }
 
 impl<R: Read + Seek> Parser<R> {
-    fn new(mut reader: R) -> Result<Self> {
-        if SeekShim::stream_len(&mut reader)? < 0x10 {
+    fn new(mut reader: R, limits: ParseLimits) -> Result<Self> {
+        let len = SeekShim::stream_len(&mut reader)?;
+        if len < 0x10 {
             return Err(Error::InvalidData("Insufficient data for header"));
         }
         let header = ResHeader::read_ne(&mut reader)?;
@@ -174,6 +340,8 @@ impl<R: Read + Seek> Parser<R> {
             )?,
             root_node_offset: header.inner.root_node_offset,
             reader,
+            len,
+            guard: Guard::new(limits),
         })
     }
 
@@ -194,6 +362,7 @@ impl<R: Read + Seek> Parser<R> {
             NodeType::String => Byml::String(self.string_table.get_string(raw, &mut self.reader)?),
             NodeType::Binary => {
                 let size: u32 = self.reader.read_at(raw as u64)?;
+                check_data(self.len, raw, 4, size)?;
                 let buf = binrw::BinRead::read_options(
                     &mut self.reader.reader,
                     self.reader.endian,
@@ -207,6 +376,7 @@ impl<R: Read + Seek> Parser<R> {
             NodeType::File => {
                 let size: u32 = self.reader.read_at(raw as u64)?;
                 let _unknown: u32 = self.reader.read_at(raw as u64 + 4)?;
+                check_data(self.len, raw, 8, size)?;
                 let buf = binrw::BinRead::read_options(
                     &mut self.reader.reader,
                     self.reader.endian,
@@ -226,9 +396,10 @@ impl<R: Read + Seek> Parser<R> {
             NodeType::Double => Byml::Double(f64::from_bits(read_long(raw)?)),
             NodeType::Null => Byml::Null,
             _ => {
-                return Err(Error::Any(format!(
-                    "Invalid value node type: {node_type:?}"
-                )));
+                return Err(Error::InvalidDataAt(
+                    offset as usize,
+                    format!("Invalid value node type: {node_type:?}"),
+                ));
             }
         };
         Ok(value)
@@ -239,6 +410,7 @@ impl<R: Read + Seek> Parser<R> {
             let container_offset = self.reader.read_at(offset as u64)?;
             self.parse_container_node(container_offset)
         } else {
+            self.guard.node(offset)?;
             self.parse_value_node(offset, node_type)
         }
     }
@@ -307,15 +479,243 @@ impl<R: Read + Seek> Parser<R> {
 
     fn parse_container_node(&mut self, offset: u32) -> Result<Byml> {
         let node_type: NodeType = self.reader.read_at(offset as u64)?;
-        let size: u24 = self.reader.read()?;
-        match node_type {
-            NodeType::Array => self.parse_array_node(offset, size.as_u32()),
-            NodeType::Map => self.parse_map_node(offset, size.as_u32()),
-            NodeType::HashMap => self.parse_hash_map_node(offset, size.as_u32()),
-            NodeType::ValueHashMap => self.parse_value_hash_map_node(offset, size.as_u32()),
-            _ => unreachable!("Invalid container node type"),
+        let size = self.reader.read::<u24>()?.as_u32();
+        check_container(self.len, offset, node_type, size)?;
+        self.guard.enter(offset)?;
+        let node = match node_type {
+            NodeType::Array => self.parse_array_node(offset, size),
+            NodeType::Map => self.parse_map_node(offset, size),
+            NodeType::HashMap => self.parse_hash_map_node(offset, size),
+            NodeType::ValueHashMap => self.parse_value_hash_map_node(offset, size),
+            _ => unreachable!("Container type is checked"),
+        };
+        self.guard.exit();
+        node
+    }
+}
+
+/// A document type which [`SliceParser`] can build, so that [`Byml`] and
+/// [`SharedByml`](super::SharedByml) documents share one parser.
+pub(super) trait ParseTarget: Sized {
+    /// The type of strings and map keys.
+    type Str: Clone + Eq + std::hash::Hash;
+    /// State used to create strings, such as a string pool.
+    type Pool;
+
+    /// Convert a string table entry. Each entry is converted once.
+    fn table_str(s: &str, pool: &mut Self::Pool) -> Self::Str;
+    fn string(s: Self::Str) -> Self;
+    /// Convert a value node which is not a string.
+    fn value(node: Byml) -> Self;
+    fn array(array: Vec<Self>) -> Self;
+    fn map(map: indexmap::IndexMap<Self::Str, Self, BuildHasherDefault<FxHasher>>) -> Self;
+    fn hash_map(map: FxHashMap<u32, Self>) -> Self;
+    fn value_hash_map(map: FxHashMap<u32, (Self, u32)>) -> Self;
+}
+
+impl ParseTarget for Byml {
+    type Pool = ();
+    type Str = String;
+
+    fn table_str(s: &str, _: &mut ()) -> String {
+        s.into()
+    }
+
+    fn string(s: String) -> Self {
+        Byml::String(s)
+    }
+
+    fn value(node: Byml) -> Self {
+        node
+    }
+
+    fn array(array: Vec<Self>) -> Self {
+        Byml::Array(array)
+    }
+
+    fn map(map: Map) -> Self {
+        Byml::Map(map)
+    }
+
+    fn hash_map(map: HashMap) -> Self {
+        Byml::HashMap(map)
+    }
+
+    fn value_hash_map(map: ValueHashMap) -> Self {
+        Byml::ValueHashMap(map)
+    }
+}
+
+/// Parser for documents already in memory. Reads directly from the slice
+/// instead of seeking a reader for every node, and decodes each string table
+/// only once.
+pub(super) struct SliceParser<'a, T: ParseTarget> {
+    doc: Document<'a>,
+    hash_keys: Vec<T::Str>,
+    strings: Vec<T::Str>,
+    guard: Guard,
+}
+
+impl<'a, T: ParseTarget> SliceParser<'a, T> {
+    pub(super) fn new(data: &'a [u8], limits: ParseLimits, pool: &mut T::Pool) -> Result<Self> {
+        let doc = Document::new(data)?;
+        let mut table = |offset| -> Result<Vec<T::Str>> {
+            Ok(doc
+                .table_strs(offset)?
+                .into_iter()
+                .map(|s| T::table_str(s, pool))
+                .collect())
+        };
+        Ok(Self {
+            hash_keys: table(doc.hash_key_table)?,
+            strings: table(doc.string_table)?,
+            doc,
+            guard: Guard::new(limits),
+        })
+    }
+
+    pub(super) fn parse(&mut self) -> Result<T> {
+        match self.doc.u32(0xC)? {
+            0 => Ok(T::value(Byml::Null)),
+            root => self.parse_container_node(root),
+        }
+    }
+
+    fn parse_value_node(&self, offset: u32, raw: u32, node_type: NodeType) -> Result<T> {
+        let read_data = |header_size: u32| -> Result<Vec<u8>> {
+            let size = self.doc.u32(raw)?;
+            check_data(self.doc.data.len() as u64, raw, header_size, size)?;
+            let start = raw as usize + header_size as usize;
+            Ok(self.doc.data[start..start + size as usize].to_vec())
+        };
+        Ok(T::value(match node_type {
+            NodeType::String => {
+                return Ok(T::string(
+                    self.strings
+                        .get(raw as usize)
+                        .ok_or_else(|| {
+                            Error::InvalidDataAt(
+                                offset as usize,
+                                format!("Invalid BYML string table entry index: {raw}"),
+                            )
+                        })?
+                        .clone(),
+                ));
+            }
+            NodeType::Binary => Byml::BinaryData(read_data(4)?),
+            NodeType::File => Byml::FileData(read_data(8)?),
+            NodeType::Bool => Byml::Bool(raw != 0),
+            NodeType::I32 => Byml::I32(raw as i32),
+            NodeType::U32 => Byml::U32(raw),
+            NodeType::Float => Byml::Float(f32::from_bits(raw)),
+            NodeType::I64 => Byml::I64(self.doc.u64(raw)? as i64),
+            NodeType::U64 => Byml::U64(self.doc.u64(raw)?),
+            NodeType::Double => Byml::Double(f64::from_bits(self.doc.u64(raw)?)),
+            NodeType::Null => Byml::Null,
+            _ => {
+                return Err(Error::InvalidDataAt(
+                    offset as usize,
+                    format!("Invalid value node type: {node_type:?}"),
+                ));
+            }
+        }))
+    }
+
+    /// Read a node type byte.
+    fn node_type(&self, offset: u32) -> Result<NodeType> {
+        let byte = self.doc.u8(offset)?;
+        view::parse_node_type(byte).map_err(|_| {
+            Error::InvalidDataAt(
+                offset as usize,
+                format!("Invalid BYML node type: {byte:#x}"),
+            )
+        })
+    }
+
+    #[inline]
+    fn parse_container_child_node(&mut self, offset: u32, type_offset: u32) -> Result<T> {
+        let node_type = self.node_type(type_offset)?;
+        let raw = self.doc.u32(offset)?;
+        if is_container_type(node_type) {
+            self.parse_container_node(raw)
+        } else {
+            self.guard.node(offset)?;
+            self.parse_value_node(offset, raw, node_type)
         }
     }
+
+    fn parse_array_node(&mut self, offset: u32, size: u32) -> Result<T> {
+        let values_offset = offset + 4 + align(size, 4);
+        (0..size)
+            .map(|i| self.parse_container_child_node(values_offset + 4 * i, offset + 4 + i))
+            .collect::<Result<_>>()
+            .map(T::array)
+    }
+
+    fn parse_map_node(&mut self, offset: u32, size: u32) -> Result<T> {
+        let mut map =
+            indexmap::IndexMap::with_capacity_and_hasher(size as usize, Default::default());
+        for i in 0..size {
+            let entry_offset = offset + 4 + 8 * i;
+            let key_index = self.doc.u24(entry_offset)?;
+            let key = self.hash_keys.get(key_index as usize).cloned().ok_or_else(|| {
+                Error::InvalidDataAt(
+                    entry_offset as usize,
+                    format!("Invalid BYML hash key table entry index: {key_index}"),
+                )
+            })?;
+            map.insert(
+                key,
+                self.parse_container_child_node(entry_offset + 4, entry_offset + 3)?,
+            );
+        }
+        Ok(T::map(map))
+    }
+
+    fn parse_hash_map_node(&mut self, offset: u32, size: u32) -> Result<T> {
+        let mut map = FxHashMap::with_capacity_and_hasher(size as usize, Default::default());
+        let types_offset = offset + 4 + 8 * size;
+        for i in 0..size {
+            let entry_offset = offset + 4 + 8 * i;
+            map.insert(
+                self.doc.u32(entry_offset)?,
+                self.parse_container_child_node(entry_offset + 4, types_offset + i)?,
+            );
+        }
+        Ok(T::hash_map(map))
+    }
+
+    fn parse_value_hash_map_node(&mut self, offset: u32, size: u32) -> Result<T> {
+        let mut map = FxHashMap::with_capacity_and_hasher(size as usize, Default::default());
+        let types_offset = offset + 4 + 12 * size;
+        for i in 0..size {
+            let entry_offset = offset + 4 + 12 * i;
+            map.insert(
+                self.doc.u32(entry_offset + 4)?,
+                (
+                    self.parse_container_child_node(entry_offset, types_offset + i)?,
+                    self.doc.u32(entry_offset + 8)?,
+                ),
+            );
+        }
+        Ok(T::value_hash_map(map))
+    }
+
+    fn parse_container_node(&mut self, offset: u32) -> Result<T> {
+        let node_type = self.node_type(offset)?;
+        let size = self.doc.u24(offset + 1)?;
+        check_container(self.doc.data.len() as u64, offset, node_type, size)?;
+        self.guard.enter(offset)?;
+        let node = match node_type {
+            NodeType::Array => self.parse_array_node(offset, size),
+            NodeType::Map => self.parse_map_node(offset, size),
+            NodeType::HashMap => self.parse_hash_map_node(offset, size),
+            NodeType::ValueHashMap => self.parse_value_hash_map_node(offset, size),
+            _ => unreachable!("Container type is checked"),
+        };
+        self.guard.exit();
+        node
+    }
 }
 
 #[cfg(test)]
@@ -339,4 +739,96 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn slice_parser() {
+        for file in jwalk::WalkDir::new("test/byml")
+            .into_iter()
+            .filter_map(|f| {
+                f.ok()
+                    .and_then(|f| f.file_name().to_str()?.ends_with(".byml").then(|| f.path()))
+            })
+        {
+            let data = std::fs::read(&file).unwrap();
+            if data.starts_with(b"Yaz0") {
+                continue;
+            }
+            println!("{}", file.display());
+            assert_eq!(
+                Byml::from_binary(&data).unwrap(),
+                Byml::read(std::io::Cursor::new(&data)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn malicious() {
+        fn doc(nodes: &[u8]) -> Vec<u8> {
+            let mut data = b"YB\x02\x00\x00\x00\x00\x00\x00\x00\x00\x00\x10\x00\x00\x00".to_vec();
+            data.extend_from_slice(nodes);
+            data
+        }
+        fn parse_all(data: &[u8]) -> Vec<Result<Byml>> {
+            vec![
+                Byml::from_binary(data),
+                Byml::read(std::io::Cursor::new(data)),
+                super::BymlRef::new(data).and_then(|r| r.to_owned()),
+            ]
+        }
+        // An array containing itself.
+        for result in parse_all(&doc(&[0xc0, 1, 0, 0, 0xc0, 0, 0, 0, 0x10, 0, 0, 0])) {
+            assert!(matches!(result, Err(Error::InvalidDataAt(0x10, _))));
+        }
+        // An array claiming far more entries than the data holds.
+        for result in parse_all(&doc(&[0xc0, 0xff, 0xff, 0xff])) {
+            assert!(matches!(result, Err(Error::InvalidDataAt(0x10, _))));
+        }
+        // Binary data larger than the document.
+        let data = doc(&[
+            0xc0, 1, 0, 0, 0xa1, 0, 0, 0, 0x18, 0, 0, 0, 0xff, 0xff, 0xff, 0x7f,
+        ]);
+        for result in parse_all(&data) {
+            assert!(result.is_err());
+        }
+
+        let mut nested = Byml::Array(vec![]);
+        for _ in 0..10 {
+            nested = Byml::Array(vec![nested]);
+        }
+        let data = nested.to_binary(Endian::Little);
+        assert!(Byml::from_binary(&data).is_ok());
+        let limits = ParseLimits {
+            max_depth: 5,
+            ..Default::default()
+        };
+        assert!(Byml::from_binary_with_limits(&data, limits).is_err());
+        assert!(Byml::read_with_limits(std::io::Cursor::new(&data), limits).is_err());
+        let data = std::fs::read("test/byml/ActorInfo.product.byml").unwrap();
+        let limits = ParseLimits {
+            max_nodes: 1000,
+            ..Default::default()
+        };
+        assert!(Byml::from_binary_with_limits(&data, limits).is_err());
+
+        // Truncated or corrupted files must fail without panicking.
+        let data =
+            std::fs::read("test/byml/Mrg_01e57204_MrgD100_B4-B3-B2-1A90E17A.bcett.byml").unwrap();
+        for len in 0..data.len() {
+            parse_all(&data[..len]);
+        }
+        for i in 0..data.len() {
+            for byte in [0x00, 0x10, 0xc0, 0xff] {
+                let mut corrupted = data.clone();
+                corrupted[i] = byte;
+                parse_all(&corrupted);
+            }
+        }
+        for i in (0..data.len() - 4).step_by(4) {
+            for word in [0xfffffff0u32, 0xffffffff, 0x7fffffff] {
+                let mut corrupted = data.clone();
+                corrupted[i..i + 4].copy_from_slice(&word.to_le_bytes());
+                parse_all(&corrupted);
+            }
+        }
+    }
 }
@@ -0,0 +1,523 @@
+use std::borrow::Cow;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::{
+    path::{array_position, escape, parse_hash, selector_value, unescape},
+    *,
+};
+
+/// Keys used by [`diff`] to match the items of arrays of maps, in order of
+/// preference.
+pub const DEFAULT_ARRAY_KEYS: &[&str] = &["name", "DataName", "HashId"];
+
+/// A single change in a [`BymlPatch`]. Paths are pointers as accepted by
+/// [`Byml::pointer`], so array items can be addressed by index or by a
+/// `key=value` selector.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    /// Set the node at the path, adding a map entry if the key does not
+    /// exist yet. For value hash map entries, `extra` sets the additional
+    /// value; if it is `None`, an existing entry keeps its value and a new
+    /// entry uses 0.
+    Set {
+        path:  String,
+        value: Byml,
+        extra: Option<u32>,
+    },
+    /// Remove the map entry or array item at the path.
+    Delete { path: String },
+    /// Insert an item into the array at the path. An index past the end of
+    /// the array appends the item.
+    Insert {
+        path:  String,
+        index: usize,
+        value: Byml,
+    },
+}
+
+impl PatchOp {
+    /// The path the operation applies to.
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOp::Set { path, .. } | PatchOp::Delete { path } | PatchOp::Insert { path, .. } => {
+                path
+            }
+        }
+    }
+}
+
+/// A structural patch between two BYML documents, created with [`diff`] and
+/// applied with [`Byml::apply_patch`].
+///
+/// Patches only describe what changed, so they are a compact way to ship
+/// edits to large documents such as `ActorInfo.product.byml`. Because array
+/// items of maps are addressed by identity keys where possible (see
+/// [`DEFAULT_ARRAY_KEYS`]), a patch can usually still be applied if the base
+/// document has been changed elsewhere.
+///
+/// ```
+/// # use roead::byml::{self, Byml};
+/// # fn docttest() -> Result<(), Box<dyn std::error::Error>> {
+/// let base = Byml::from_binary(std::fs::read("test/byml/ActorInfo.product.byml")?)?;
+/// let mut modified = base.clone();
+/// *modified
+///     .pointer_mut("/Actors/name=Enemy_Moriblin_Junior/life")
+///     .unwrap() = Byml::I32(1);
+/// let patch = byml::diff(&base, &modified);
+/// assert_eq!(patch.ops.len(), 1);
+/// let mut patched = base.clone();
+/// patched.apply_patch(&patch)?;
+/// assert_eq!(patched, modified);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BymlPatch {
+    /// The operations, applied in order.
+    pub ops: Vec<PatchOp>,
+}
+
+/// Compute the patch which turns `base` into `modified`, matching the items
+/// of arrays of maps by [`DEFAULT_ARRAY_KEYS`].
+pub fn diff(base: &Byml, modified: &Byml) -> BymlPatch {
+    diff_with_keys(base, modified, DEFAULT_ARRAY_KEYS)
+}
+
+/// Compute the patch which turns `base` into `modified`, matching the items
+/// of arrays of maps by the first of `keys` which has a unique string or
+/// integer value in every item of both arrays. Other arrays are compared by
+/// position: items are modified in place where possible and the remainder
+/// removed or inserted. Reordering the items of a keyed array is not
+/// recorded.
+pub fn diff_with_keys(base: &Byml, modified: &Byml, keys: &[&str]) -> BymlPatch {
+    let mut differ = Differ { keys, ops: vec![] };
+    differ.diff("", base, modified);
+    BymlPatch { ops: differ.ops }
+}
+
+struct Differ<'a> {
+    keys: &'a [&'a str],
+    ops:  Vec<PatchOp>,
+}
+
+/// The identity key values of all items in an array, if they are all unique.
+pub(super) fn item_keys<'a>(array: &'a [Byml], key: &str) -> Option<Vec<Cow<'a, str>>> {
+    let values = array
+        .iter()
+        .map(|item| item.get(key).and_then(selector_value))
+        .collect::<Option<Vec<_>>>()?;
+    let unique: FxHashSet<&str> = values.iter().map(|v| v.as_ref()).collect();
+    (unique.len() == values.len()).then_some(values)
+}
+
+impl Differ<'_> {
+    fn set(&mut self, path: &str, value: &Byml, extra: Option<u32>) {
+        self.ops.push(PatchOp::Set {
+            path: path.into(),
+            value: value.clone(),
+            extra,
+        });
+    }
+
+    fn delete(&mut self, path: &str) {
+        self.ops.push(PatchOp::Delete { path: path.into() });
+    }
+
+    fn insert(&mut self, path: &str, index: usize, value: &Byml) {
+        self.ops.push(PatchOp::Insert {
+            path: path.into(),
+            index,
+            value: value.clone(),
+        });
+    }
+
+    fn diff(&mut self, path: &str, base: &Byml, modified: &Byml) {
+        match (base, modified) {
+            (Byml::Map(base), Byml::Map(modified)) => {
+                let mut keys: Vec<&String> = base.keys().chain(modified.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                    let child = format!("{path}/{}", escape(key));
+                    match (base.get(key), modified.get(key)) {
+                        (Some(b), Some(m)) => self.diff(&child, b, m),
+                        (Some(_), None) => self.delete(&child),
+                        (None, Some(m)) => self.set(&child, m, None),
+                        (None, None) => unreachable!(),
+                    }
+                }
+            }
+            (Byml::HashMap(base), Byml::HashMap(modified)) => {
+                let mut keys: Vec<u32> = base.keys().chain(modified.keys()).copied().collect();
+                keys.sort_unstable();
+                keys.dedup();
+                for key in keys {
+                    let child = format!("{path}/{key}");
+                    match (base.get(&key), modified.get(&key)) {
+                        (Some(b), Some(m)) => self.diff(&child, b, m),
+                        (Some(_), None) => self.delete(&child),
+                        (None, Some(m)) => self.set(&child, m, None),
+                        (None, None) => unreachable!(),
+                    }
+                }
+            }
+            (Byml::ValueHashMap(base), Byml::ValueHashMap(modified)) => {
+                let mut keys: Vec<u32> = base.keys().chain(modified.keys()).copied().collect();
+                keys.sort_unstable();
+                keys.dedup();
+                for key in keys {
+                    let child = format!("{path}/{key}");
+                    match (base.get(&key), modified.get(&key)) {
+                        (Some((b, b_extra)), Some((m, m_extra))) => {
+                            if b_extra != m_extra {
+                                self.set(&child, m, Some(*m_extra));
+                            } else {
+                                self.diff(&child, b, m);
+                            }
+                        }
+                        (Some(_), None) => self.delete(&child),
+                        (None, Some((m, m_extra))) => self.set(&child, m, Some(*m_extra)),
+                        (None, None) => unreachable!(),
+                    }
+                }
+            }
+            (Byml::Array(base), Byml::Array(modified)) => {
+                let keyed = self
+                    .keys
+                    .iter()
+                    .find_map(|key| Some((*key, item_keys(base, key)?, item_keys(modified, key)?)));
+                match keyed {
+                    Some((key, base_keys, modified_keys)) => {
+                        self.diff_keyed_array(path, key, base, &base_keys, modified, &modified_keys)
+                    }
+                    None => self.diff_array(path, base, modified),
+                }
+            }
+            _ if base == modified => (),
+            _ => self.set(path, modified, None),
+        }
+    }
+
+    fn diff_keyed_array(
+        &mut self,
+        path: &str,
+        key: &str,
+        base: &[Byml],
+        base_keys: &[Cow<str>],
+        modified: &[Byml],
+        modified_keys: &[Cow<str>],
+    ) {
+        let modified_index: FxHashMap<&str, usize> = modified_keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k.as_ref(), i))
+            .collect();
+        let selector = |value: &str| format!("{path}/{}={}", escape(key), escape(value));
+        for value in base_keys {
+            if !modified_index.contains_key(value.as_ref()) {
+                self.delete(&selector(value));
+            }
+        }
+        for (item, value) in base.iter().zip(base_keys) {
+            if let Some(&i) = modified_index.get(value.as_ref()) {
+                self.diff(&selector(value), item, &modified[i]);
+            }
+        }
+        let base_set: FxHashSet<&str> = base_keys.iter().map(|k| k.as_ref()).collect();
+        for (i, (item, value)) in modified.iter().zip(modified_keys).enumerate() {
+            if !base_set.contains(value.as_ref()) {
+                self.insert(path, i, item);
+            }
+        }
+    }
+
+    fn diff_array(&mut self, path: &str, base: &[Byml], modified: &[Byml]) {
+        let prefix = base
+            .iter()
+            .zip(modified)
+            .take_while(|(b, m)| b == m)
+            .count();
+        let suffix = base[prefix..]
+            .iter()
+            .rev()
+            .zip(modified[prefix..].iter().rev())
+            .take_while(|(b, m)| b == m)
+            .count();
+        let base_middle = &base[prefix..base.len() - suffix];
+        let modified_middle = &modified[prefix..modified.len() - suffix];
+        let paired = base_middle.len().min(modified_middle.len());
+        for (i, (b, m)) in base_middle.iter().zip(modified_middle).enumerate() {
+            self.diff(&format!("{path}/{}", prefix + i), b, m);
+        }
+        for i in (paired..base_middle.len()).rev() {
+            self.delete(&format!("{path}/{}", prefix + i));
+        }
+        for (i, m) in modified_middle.iter().enumerate().skip(paired) {
+            self.insert(path, prefix + i, m);
+        }
+    }
+}
+
+fn not_found(path: &str) -> Error {
+    Error::InvalidDataD(format!("BYML patch path not found: {path}"))
+}
+
+/// Split a non-empty path into its parent path and last segment.
+fn split_path(path: &str) -> Result<(&str, &str)> {
+    path.rsplit_once('/')
+        .filter(|(parent, _)| parent.is_empty() || parent.starts_with('/'))
+        .ok_or_else(|| Error::InvalidDataD(format!("Invalid BYML patch path: {path}")))
+}
+
+impl Byml {
+    /// Apply a patch created with [`diff`]. Operations are applied in order.
+    /// Returns an error if a path cannot be resolved, in which case the
+    /// operations before it have already been applied.
+    pub fn apply_patch(&mut self, patch: &BymlPatch) -> Result<()> {
+        patch.ops.iter().try_for_each(|op| self.apply_patch_op(op))
+    }
+
+    fn apply_patch_op(&mut self, op: &PatchOp) -> Result<()> {
+        match op {
+            PatchOp::Set { path, value, .. } if path.is_empty() => *self = value.clone(),
+            PatchOp::Set { path, value, extra } => {
+                let (parent_path, segment) = split_path(path)?;
+                let parent = self
+                    .pointer_mut(parent_path)
+                    .ok_or_else(|| not_found(parent_path))?;
+                match parent {
+                    Byml::Map(map) => {
+                        map.insert(unescape(segment).as_ref().into(), value.clone());
+                    }
+                    Byml::HashMap(map) => {
+                        map.insert(
+                            parse_hash(segment).ok_or_else(|| not_found(path))?,
+                            value.clone(),
+                        );
+                    }
+                    Byml::ValueHashMap(map) => {
+                        let hash = parse_hash(segment).ok_or_else(|| not_found(path))?;
+                        let extra = extra
+                            .or_else(|| map.get(&hash).map(|(_, extra)| *extra))
+                            .unwrap_or_default();
+                        map.insert(hash, (value.clone(), extra));
+                    }
+                    Byml::Array(array) => {
+                        let index =
+                            array_position(array, segment).ok_or_else(|| not_found(path))?;
+                        array[index] = value.clone();
+                    }
+                    other => return Err(Error::TypeError(other.type_name(), "Array or Map")),
+                }
+            }
+            PatchOp::Delete { path } => {
+                let (parent_path, segment) = split_path(path)?;
+                let parent = self
+                    .pointer_mut(parent_path)
+                    .ok_or_else(|| not_found(parent_path))?;
+                let removed = match parent {
+                    Byml::Map(map) => {
+                        let key = unescape(segment);
+                        let len = map.len();
+                        map.retain(|k, _| k != key.as_ref());
+                        map.len() != len
+                    }
+                    Byml::HashMap(map) => {
+                        parse_hash(segment)
+                            .and_then(|hash| map.remove(&hash))
+                            .is_some()
+                    }
+                    Byml::ValueHashMap(map) => {
+                        parse_hash(segment)
+                            .and_then(|hash| map.remove(&hash))
+                            .is_some()
+                    }
+                    Byml::Array(array) => {
+                        array_position(array, segment)
+                            .map(|index| array.remove(index))
+                            .is_some()
+                    }
+                    other => return Err(Error::TypeError(other.type_name(), "Array or Map")),
+                };
+                if !removed {
+                    return Err(not_found(path));
+                }
+            }
+            PatchOp::Insert { path, index, value } => {
+                match self.pointer_mut(path).ok_or_else(|| not_found(path))? {
+                    Byml::Array(array) => array.insert((*index).min(array.len()), value.clone()),
+                    other => return Err(Error::TypeError(other.type_name(), "Array")),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BymlPatch {
+    /// Convert the patch to a BYML array with a map for each operation, with
+    /// `op`, `path`, and where applicable `value`, `extra` and `index` keys.
+    pub fn to_byml(&self) -> Byml {
+        self.ops
+            .iter()
+            .map(|op| {
+                let mut map = Map::default();
+                let name = match op {
+                    PatchOp::Set { value, extra, .. } => {
+                        map.insert("value".into(), value.clone());
+                        if let Some(extra) = extra {
+                            map.insert("extra".into(), Byml::U32(*extra));
+                        }
+                        "set"
+                    }
+                    PatchOp::Delete { .. } => "delete",
+                    PatchOp::Insert { index, value, .. } => {
+                        map.insert("index".into(), Byml::I32(*index as i32));
+                        map.insert("value".into(), value.clone());
+                        "insert"
+                    }
+                };
+                map.insert("op".into(), name.into());
+                map.insert("path".into(), op.path().into());
+                Byml::Map(map)
+            })
+            .collect()
+    }
+
+    /// Read a patch from the BYML representation created by
+    /// [`BymlPatch::to_byml`].
+    pub fn from_byml(byml: &Byml) -> Result<Self> {
+        let field = |op: &Byml, key: &'static str| -> Result<Byml> {
+            op.get(key)
+                .cloned()
+                .ok_or_else(|| Error::InvalidDataD(format!("BYML patch operation missing {key}")))
+        };
+        let ops = byml
+            .as_array()?
+            .iter()
+            .map(|op| {
+                let path = field(op, "path")?.as_string()?.clone();
+                Ok(match field(op, "op")?.as_string()?.as_str() {
+                    "set" => {
+                        PatchOp::Set {
+                            path,
+                            value: field(op, "value")?,
+                            extra: op.get("extra").map(|e| e.as_int()).transpose()?,
+                        }
+                    }
+                    "delete" => PatchOp::Delete { path },
+                    "insert" => {
+                        PatchOp::Insert {
+                            path,
+                            index: field(op, "index")?.as_int()?,
+                            value: field(op, "value")?,
+                        }
+                    }
+                    other => {
+                        return Err(Error::InvalidDataD(format!(
+                            "Invalid BYML patch operation: {other}"
+                        )));
+                    }
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { ops })
+    }
+
+    /// Serialize the patch to YAML.
+    #[cfg(feature = "yaml")]
+    pub fn to_text(&self) -> std::string::String {
+        self.to_byml().to_text()
+    }
+
+    /// Parse a patch from YAML.
+    #[cfg(feature = "yaml")]
+    pub fn from_text(text: impl AsRef<str>) -> Result<Self> {
+        Self::from_byml(&Byml::from_text(text)?)
+    }
+
+    /// Serialize the patch to JSON, writing values as [`Byml::to_json`]
+    /// does.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> std::string::String {
+        self.to_byml().to_json()
+    }
+
+    /// Parse a patch from JSON.
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str) -> Result<Self> {
+        Self::from_byml(&Byml::from_json(text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_and_patch() {
+        let base =
+            Byml::from_binary(std::fs::read("test/byml/ActorInfo.product.byml").unwrap()).unwrap();
+        let mut modified = base.clone();
+        let actors = modified["Actors"].as_mut_array().unwrap();
+        let mut new_actor = actors[0].clone();
+        new_actor["name"] = "Enemy_Test".into();
+        actors.insert(5, new_actor);
+        actors.remove(100);
+        actors[200]
+            .as_mut_map()
+            .unwrap()
+            .insert("instSize".into(), Byml::I32(1234));
+        let hashes = modified["Hashes"].as_mut_array().unwrap();
+        hashes.insert(3, Byml::I32(-1));
+        hashes.remove(50);
+
+        let patch = diff(&base, &modified);
+        assert!(patch
+            .ops
+            .iter()
+            .any(|op| op.path().starts_with("/Actors/name=")));
+        let mut patched = base.clone();
+        patched.apply_patch(&patch).unwrap();
+        assert_eq!(patched, modified);
+        assert!(diff(&base, &base).ops.is_empty());
+
+        let mut hashes = Byml::ValueHashMap([(1, (Byml::I32(1), 2))].into_iter().collect());
+        let modified = Byml::ValueHashMap(
+            [(1, (Byml::I32(1), 3)), (4, (Byml::Null, 5))]
+                .into_iter()
+                .collect(),
+        );
+        hashes.apply_patch(&diff(&hashes, &modified)).unwrap();
+        assert_eq!(hashes, modified);
+        assert!(Byml::Array(vec![])
+            .apply_patch(&BymlPatch {
+                ops: vec![PatchOp::Delete { path: "/0".into() }],
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn patch_text() {
+        let base: Byml = [("a", Byml::I32(1)), ("b", Byml::Array(vec![Byml::U32(1)]))]
+            .into_iter()
+            .collect();
+        let modified: Byml = [
+            ("b", Byml::Array(vec![Byml::U32(1), Byml::Float(2.0)])),
+            ("c/d", Byml::String("e".into())),
+        ]
+        .into_iter()
+        .collect();
+        let patch = diff(&base, &modified);
+        assert_eq!(BymlPatch::from_byml(&patch.to_byml()).unwrap(), patch);
+        #[cfg(feature = "yaml")]
+        assert_eq!(BymlPatch::from_text(patch.to_text()).unwrap(), patch);
+        #[cfg(feature = "json")]
+        {
+            let json = patch.to_json();
+            assert!(json.contains(r#""value": 2.0"#));
+            assert_eq!(BymlPatch::from_json(&json).unwrap(), patch);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use super::*;
+
+/// Decode a pointer segment, replacing the `~1` and `~0` escapes with `/` and
+/// `~`.
+pub(super) fn unescape(segment: &str) -> std::borrow::Cow<'_, str> {
+    if segment.contains('~') {
+        segment.replace("~1", "/").replace("~0", "~").into()
+    } else {
+        segment.into()
+    }
+}
+
+/// Encode a map key or selector value as a pointer segment, replacing `~` and
+/// `/` with the `~0` and `~1` escapes.
+pub(super) fn escape(key: &str) -> std::borrow::Cow<'_, str> {
+    if key.contains(['~', '/']) {
+        key.replace('~', "~0").replace('/', "~1").into()
+    } else {
+        key.into()
+    }
+}
+
+/// The value of a node as used by `key=value` selectors, if it is a string or
+/// integer.
+pub(super) fn selector_value(node: &Byml) -> Option<std::borrow::Cow<'_, str>> {
+    match node {
+        Byml::String(s) => Some(s.as_str().into()),
+        Byml::I32(v) => Some(v.to_string().into()),
+        Byml::U32(v) => Some(v.to_string().into()),
+        Byml::I64(v) => Some(v.to_string().into()),
+        Byml::U64(v) => Some(v.to_string().into()),
+        _ => None,
+    }
+}
+
+/// Find the position of the array item selected by a segment, either an index
+/// or a `key=value` selector matching the first map item whose `key` entry has
+/// the given string or integer value.
+pub(super) fn array_position(array: &[Byml], segment: &str) -> Option<usize> {
+    if let Ok(index) = segment.parse::<usize>() {
+        return (index < array.len()).then_some(index);
+    }
+    let segment = unescape(segment);
+    let (key, value) = segment.split_once('=')?;
+    array.iter().position(|item| {
+        item.get(key)
+            .and_then(selector_value)
+            .map(|v| v == value)
+            .unwrap_or(false)
+    })
+}
+
+/// Parse a hash key segment, either decimal or hexadecimal with a `0x` prefix.
+pub(super) fn parse_hash(segment: &str) -> Option<u32> {
+    match segment.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => segment.parse().ok(),
+    }
+}
+
+/// Split a pointer into its segments. Returns `None` if a non-empty pointer
+/// does not start with `/`.
+fn segments(pointer: &str) -> Option<impl Iterator<Item = &str>> {
+    if pointer.is_empty() {
+        Some("".split('/').skip(1))
+    } else if pointer.starts_with('/') {
+        Some(pointer.split('/').skip(1))
+    } else {
+        None
+    }
+}
+
+impl Byml {
+    /// Get a child of an array or map node. Unlike indexing, this returns
+    /// `None` if the node or index has the wrong type or the index is not
+    /// found.
+    pub fn get<'a>(&self, index: impl Into<BymlIndex<'a>>) -> Option<&Byml> {
+        match (self, index.into()) {
+            (Byml::Array(a), BymlIndex::ArrayIdx(i)) => a.get(i),
+            (Byml::Map(h), BymlIndex::StringIdx(k)) => h.get(k),
+            (Byml::HashMap(h), BymlIndex::HashIdx(i)) => h.get(&i),
+            (Byml::ValueHashMap(h), BymlIndex::HashIdx(i)) => h.get(&i).map(|(v, _)| v),
+            _ => None,
+        }
+    }
+
+    /// Get a mutable reference to a child of an array or map node. Unlike
+    /// indexing, this returns `None` if the node or index has the wrong type
+    /// or the index is not found.
+    pub fn get_mut<'a>(&mut self, index: impl Into<BymlIndex<'a>>) -> Option<&mut Byml> {
+        match (self, index.into()) {
+            (Byml::Array(a), BymlIndex::ArrayIdx(i)) => a.get_mut(i),
+            (Byml::Map(h), BymlIndex::StringIdx(k)) => h.get_mut(k),
+            (Byml::HashMap(h), BymlIndex::HashIdx(i)) => h.get_mut(&i),
+            (Byml::ValueHashMap(h), BymlIndex::HashIdx(i)) => h.get_mut(&i).map(|(v, _)| v),
+            _ => None,
+        }
+    }
+
+    /// Get a descendant node by following a path of indices, which may mix
+    /// string keys, array indices and hash keys:
+    /// ```
+    /// # use roead::byml::{Byml, BymlIndex};
+    /// # fn docttest() -> Result<(), Box<dyn std::error::Error>> {
+    /// let buf: Vec<u8> = std::fs::read("test/byml/ActorInfo.product.byml")?;
+    /// let actor_info = Byml::from_binary(&buf)?;
+    /// let name = actor_info.get_path(["Actors".into(), 12usize.into(), "name".into()]);
+    /// assert!(name.is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// Returns `None` if any step fails.
+    pub fn get_path<'a>(&self, path: impl IntoIterator<Item = BymlIndex<'a>>) -> Option<&Byml> {
+        path.into_iter()
+            .try_fold(self, |node, index| node.get(index))
+    }
+
+    /// Get a mutable reference to a descendant node by following a path of
+    /// indices. See [`Byml::get_path`].
+    pub fn get_path_mut<'a>(
+        &mut self,
+        path: impl IntoIterator<Item = BymlIndex<'a>>,
+    ) -> Option<&mut Byml> {
+        path.into_iter()
+            .try_fold(self, |node, index| node.get_mut(index))
+    }
+
+    fn child_by_segment(&self, segment: &str) -> Option<&Byml> {
+        match self {
+            Byml::Map(h) => h.get(unescape(segment).as_ref()),
+            Byml::Array(a) => a.get(array_position(a, segment)?),
+            Byml::HashMap(h) => h.get(&parse_hash(segment)?),
+            Byml::ValueHashMap(h) => h.get(&parse_hash(segment)?).map(|(v, _)| v),
+            _ => None,
+        }
+    }
+
+    fn child_by_segment_mut(&mut self, segment: &str) -> Option<&mut Byml> {
+        match self {
+            Byml::Map(h) => h.get_mut(unescape(segment).as_ref()),
+            Byml::Array(a) => {
+                let index = array_position(a, segment)?;
+                a.get_mut(index)
+            }
+            Byml::HashMap(h) => h.get_mut(&parse_hash(segment)?),
+            Byml::ValueHashMap(h) => h.get_mut(&parse_hash(segment)?).map(|(v, _)| v),
+            _ => None,
+        }
+    }
+
+    /// Look up a descendant node by a pointer such as `/Actors/12/name`, in
+    /// the style of JSON pointers. Each segment is interpreted according to
+    /// the node it is applied to: a key for maps, a decimal or `0x`-prefixed
+    /// hexadecimal hash for hash maps, and for arrays either an index or a
+    /// `key=value` selector, which picks the first map item whose `key` entry
+    /// is the string or integer `value` (e.g. `/Actors/name=Enemy_Moriblin`).
+    /// In keys and values, `~1` stands for `/` and `~0` for `~`. The empty
+    /// pointer refers to the node itself.
+    ///
+    /// Returns `None` if the pointer is malformed or any step fails.
+    pub fn pointer(&self, pointer: &str) -> Option<&Byml> {
+        segments(pointer)?.try_fold(self, |node, segment| node.child_by_segment(segment))
+    }
+
+    /// Look up a mutable reference to a descendant node by a pointer. See
+    /// [`Byml::pointer`].
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Byml> {
+        segments(pointer)?.try_fold(self, |node, segment| node.child_by_segment_mut(segment))
+    }
+
+    /// Find all nodes matching a pointer pattern. Patterns use the same syntax
+    /// as [`Byml::pointer`], except that a `*` segment matches every child of
+    /// an array or map node, so `/Actors/*/name` returns the name of every
+    /// actor. Map children are visited in map order. Nodes on which a segment
+    /// cannot be resolved are skipped.
+    ///
+    /// Returns an empty `Vec` if the pattern is malformed.
+    pub fn query(&self, pattern: &str) -> Vec<&Byml> {
+        fn visit<'a>(node: &'a Byml, segments: &[&str], out: &mut Vec<&'a Byml>) {
+            match segments.split_first() {
+                None => out.push(node),
+                Some((&"*", rest)) => {
+                    match node {
+                        Byml::Array(a) => a.iter().for_each(|v| visit(v, rest, out)),
+                        Byml::Map(h) => h.values().for_each(|v| visit(v, rest, out)),
+                        Byml::HashMap(h) => h.values().for_each(|v| visit(v, rest, out)),
+                        Byml::ValueHashMap(h) => h.values().for_each(|(v, _)| visit(v, rest, out)),
+                        _ => (),
+                    }
+                }
+                Some((segment, rest)) => {
+                    if let Some(child) = node.child_by_segment(segment) {
+                        visit(child, rest, out)
+                    }
+                }
+            }
+        }
+        let mut out = vec![];
+        if let Some(segments) = segments(pattern) {
+            visit(self, &segments.collect::<Vec<_>>(), &mut out);
+        }
+        out
+    }
+
+    /// Find mutable references to all nodes matching a pointer pattern. See
+    /// [`Byml::query`].
+    pub fn query_mut(&mut self, pattern: &str) -> Vec<&mut Byml> {
+        fn visit<'a>(node: &'a mut Byml, segments: &[&str], out: &mut Vec<&'a mut Byml>) {
+            match segments.split_first() {
+                None => out.push(node),
+                Some((&"*", rest)) => {
+                    match node {
+                        Byml::Array(a) => a.iter_mut().for_each(|v| visit(v, rest, out)),
+                        Byml::Map(h) => h.values_mut().for_each(|v| visit(v, rest, out)),
+                        Byml::HashMap(h) => h.values_mut().for_each(|v| visit(v, rest, out)),
+                        Byml::ValueHashMap(h) => {
+                            h.values_mut().for_each(|(v, _)| visit(v, rest, out))
+                        }
+                        _ => (),
+                    }
+                }
+                Some((segment, rest)) => {
+                    if let Some(child) = node.child_by_segment_mut(segment) {
+                        visit(child, rest, out)
+                    }
+                }
+            }
+        }
+        let mut out = vec![];
+        if let Some(segments) = segments(pattern) {
+            visit(self, &segments.collect::<Vec<_>>(), &mut out);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointers() {
+        let mut actor_info =
+            Byml::from_binary(std::fs::read("test/byml/ActorInfo.product.byml").unwrap()).unwrap();
+        let name = actor_info["Actors"][12]["name"].clone();
+        assert_eq!(actor_info.pointer("/Actors/12/name"), Some(&name));
+        assert_eq!(
+            actor_info
+                .pointer(&format!(
+                    "/Actors/name={}",
+                    escape(name.as_string().unwrap())
+                ))
+                .and_then(|actor| actor.get("name")),
+            Some(&name)
+        );
+        assert_eq!(
+            actor_info.get_path(["Actors".into(), 12usize.into(), "name".into()]),
+            Some(&name)
+        );
+        assert_eq!(actor_info.pointer(""), Some(&actor_info));
+        assert!(actor_info.pointer("/Actors/name").is_none());
+        assert!(actor_info.pointer("/Actors/99999").is_none());
+        assert!(actor_info.pointer("Actors").is_none());
+        *actor_info.pointer_mut("/Actors/12/name").unwrap() = "Test".into();
+        assert_eq!(actor_info["Actors"][12]["name"], Byml::from("Test"));
+
+        let names = actor_info.query("/Actors/*/name");
+        assert_eq!(names.len(), 7934);
+        assert_eq!(names[12], &Byml::from("Test"));
+        for name in actor_info.query_mut("/Actors/*/name") {
+            *name = "Renamed".into();
+        }
+        assert!(actor_info
+            .query("/Actors/*/name")
+            .into_iter()
+            .all(|n| *n == Byml::from("Renamed")));
+
+        let mut hashes = Byml::HashMap(
+            [(0xABCD, Byml::from(vec![Byml::I32(1)]))]
+                .into_iter()
+                .collect(),
+        );
+        hashes = Byml::ValueHashMap([(7, (hashes, 2))].into_iter().collect());
+        assert_eq!(hashes.pointer("/7/0xabcd/0"), Some(&Byml::I32(1)));
+        assert_eq!(
+            hashes.get_path([7u32.into(), 0xABCDu32.into(), 0usize.into()]),
+            Some(&Byml::I32(1))
+        );
+        assert_eq!(hashes.query("/*/*/*").len(), 1);
+        let map: Byml = [("a/b~", Byml::I32(3))].into_iter().collect();
+        assert_eq!(map.pointer("/a~1b~0"), Some(&Byml::I32(3)));
+    }
+}
@@ -0,0 +1,542 @@
+use serde::ser::{self, Serialize};
+
+use super::*;
+use crate::Endian;
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Any(msg.to_string())
+    }
+}
+
+/// Convert a value to a BYML node.
+///
+/// Structs and maps with string keys become [`Byml::Map`] nodes, maps with
+/// `u32` keys become [`Byml::HashMap`] nodes, and sequences and tuples
+/// become arrays. Integers keep their width where BYML has a matching node
+/// type: `i8` to `i32` become [`Byml::I32`], `u8` to `u32` become
+/// [`Byml::U32`], and 64-bit integers become [`Byml::I64`] or [`Byml::U64`].
+/// `None` and `()` become [`Byml::Null`]. Enums are externally tagged: unit
+/// variants become strings, and other variants a single-entry map.
+pub fn to_byml<T: Serialize + ?Sized>(value: &T) -> Result<Byml> {
+    value.serialize(Serializer)
+}
+
+/// Serialize a value to binary BYML with the specified endianness and
+/// version. The value must serialize to a map, array, or null.
+///
+/// Returns an error if the value serializes to node types which the version
+/// does not support (see [`Byml::validate_for_version`]), e.g. 64-bit
+/// integers or `f64` before version 3, or maps with `u32` keys before version
+/// 7.
+pub fn to_binary<T: Serialize + ?Sized>(
+    value: &T,
+    endian: Endian,
+    version: u16,
+) -> Result<Vec<u8>> {
+    to_byml(value)?.to_binary_checked(endian, version)
+}
+
+/// A serializer which converts Rust values to [`Byml`] nodes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Error = Error;
+    type Ok = Byml;
+    type SerializeMap = SerializeMap;
+    type SerializeSeq = SerializeVec;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStructVariant;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeTupleVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Byml> {
+        Ok(Byml::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Byml> {
+        Ok(Byml::I32(v as i32))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Byml> {
+        Ok(Byml::I32(v as i32))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Byml> {
+        Ok(Byml::I32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Byml> {
+        Ok(Byml::I64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Byml> {
+        Ok(Byml::U32(v as u32))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Byml> {
+        Ok(Byml::U32(v as u32))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Byml> {
+        Ok(Byml::U32(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Byml> {
+        Ok(Byml::U64(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Byml> {
+        Ok(Byml::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Byml> {
+        Ok(Byml::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Byml> {
+        Ok(Byml::String(v.encode_utf8(&mut [0; 4]).into()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Byml> {
+        Ok(Byml::String(v.into()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Byml> {
+        Ok(Byml::BinaryData(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Byml> {
+        Ok(Byml::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Byml> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Byml> {
+        Ok(Byml::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Byml> {
+        Ok(Byml::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Byml> {
+        Ok(Byml::String(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Byml> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Byml> {
+        let mut map = Map::default();
+        map.insert(variant.into(), value.serialize(self)?);
+        Ok(Byml::Map(map))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec> {
+        Ok(SerializeVec {
+            vec: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeTupleVariant> {
+        Ok(SerializeTupleVariant {
+            variant,
+            vec: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap {
+            entries:  Vec::with_capacity(len.unwrap_or(0)),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<SerializeStruct> {
+        Ok(SerializeStruct {
+            map: Map::default(),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeStructVariant> {
+        Ok(SerializeStructVariant {
+            variant,
+            map: Map::default(),
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeVec {
+    vec: Vec<Byml>,
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Error = Error;
+    type Ok = Byml;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Byml> {
+        Ok(Byml::Array(self.vec))
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Error = Error;
+    type Ok = Byml;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Byml> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Error = Error;
+    type Ok = Byml;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Byml> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    vec: Vec<Byml>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Error = Error;
+    type Ok = Byml;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Byml> {
+        let mut map = Map::default();
+        map.insert(self.variant.into(), Byml::Array(self.vec));
+        Ok(Byml::Map(map))
+    }
+}
+
+enum Key {
+    String(String),
+    Hash(u32),
+}
+
+#[doc(hidden)]
+pub struct SerializeMap {
+    entries:  Vec<(Key, Byml)>,
+    next_key: Option<Key>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Error = Error;
+    type Ok = Byml;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or(Error::InvalidData("Map value serialized before key"))?;
+        self.entries.push((key, value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Byml> {
+        if !self.entries.is_empty() && self.entries.iter().all(|(k, _)| matches!(k, Key::Hash(_))) {
+            Ok(Byml::HashMap(
+                self.entries
+                    .into_iter()
+                    .filter_map(|(k, v)| {
+                        match k {
+                            Key::Hash(hash) => Some((hash, v)),
+                            Key::String(_) => None,
+                        }
+                    })
+                    .collect(),
+            ))
+        } else {
+            let mut map = Map::default();
+            for (key, value) in self.entries {
+                match key {
+                    Key::String(key) => map.insert(key, value),
+                    Key::Hash(_) => {
+                        return Err(Error::InvalidData(
+                            "BYML map keys must be all strings or all u32 hashes",
+                        ));
+                    }
+                };
+            }
+            Ok(Byml::Map(map))
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeStruct {
+    map: Map,
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Error = Error;
+    type Ok = Byml;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.map.insert(key.into(), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Byml> {
+        Ok(Byml::Map(self.map))
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    map: Map,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Error = Error;
+    type Ok = Byml;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.map.insert(key.into(), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Byml> {
+        let mut map = Map::default();
+        map.insert(self.variant.into(), Byml::Map(self.map));
+        Ok(Byml::Map(map))
+    }
+}
+
+const KEY_ERROR: Error = Error::InvalidData("BYML map keys must be strings or u32 hashes");
+
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Error = Error;
+    type Ok = Key;
+    type SerializeMap = ser::Impossible<Key, Error>;
+    type SerializeSeq = ser::Impossible<Key, Error>;
+    type SerializeStruct = ser::Impossible<Key, Error>;
+    type SerializeStructVariant = ser::Impossible<Key, Error>;
+    type SerializeTuple = ser::Impossible<Key, Error>;
+    type SerializeTupleStruct = ser::Impossible<Key, Error>;
+    type SerializeTupleVariant = ser::Impossible<Key, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Key> {
+        Ok(Key::Hash(v as u32))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Key> {
+        Ok(Key::Hash(v as u32))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Key> {
+        Ok(Key::Hash(v))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Key> {
+        Ok(Key::String(v.encode_utf8(&mut [0; 4]).into()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Key> {
+        Ok(Key::String(v.into()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_none(self) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_unit(self) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Key> {
+        Ok(Key::String(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Key> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Key> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(KEY_ERROR)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(KEY_ERROR)
+    }
+}
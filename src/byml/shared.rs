@@ -0,0 +1,773 @@
+use std::{
+    borrow::Borrow,
+    fmt,
+    hash::BuildHasherDefault,
+    io::{Cursor, Seek, Write},
+    ops::Deref,
+    sync::Arc,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+
+use super::{
+    hash_entries, is_valid_version,
+    parser::{ParseTarget, SliceParser},
+    writer::{write_document, Node, WriteNode},
+    Byml, BymlIndex, ParseLimits,
+};
+use crate::{Endian, Error, Result};
+
+/// An immutable, reference-counted string, used for the strings of a
+/// [`SharedByml`] document.
+///
+/// Cloning only increments a reference count, so equal strings taken from the
+/// same [`StringPool`] share one allocation.
+#[derive(Clone, PartialOrd, Ord)]
+pub struct SharedString(Arc<str>);
+
+impl SharedString {
+    /// Get the string as a `&str`.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` if both strings share the same allocation.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Default for SharedString {
+    fn default() -> Self {
+        Self(Arc::from(""))
+    }
+}
+
+impl Deref for SharedString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SharedString {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for SharedString {
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for SharedString {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl Eq for SharedString {}
+
+impl PartialEq<str> for SharedString {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SharedString {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl std::hash::Hash for SharedString {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl fmt::Debug for SharedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for SharedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl From<&str> for SharedString {
+    fn from(s: &str) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+impl From<std::string::String> for SharedString {
+    fn from(s: std::string::String) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+impl From<&std::string::String> for SharedString {
+    fn from(s: &std::string::String) -> Self {
+        Self(Arc::from(s.as_str()))
+    }
+}
+
+impl From<smartstring::alias::String> for SharedString {
+    fn from(s: smartstring::alias::String) -> Self {
+        Self(Arc::from(s.as_str()))
+    }
+}
+
+impl From<&smartstring::alias::String> for SharedString {
+    fn from(s: &smartstring::alias::String) -> Self {
+        Self(Arc::from(s.as_str()))
+    }
+}
+
+impl From<SharedString> for smartstring::alias::String {
+    fn from(s: SharedString) -> Self {
+        s.as_str().into()
+    }
+}
+
+impl From<SharedString> for std::string::String {
+    fn from(s: SharedString) -> Self {
+        s.as_str().into()
+    }
+}
+
+#[cfg(feature = "with-serde")]
+impl serde::Serialize for SharedString {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "with-serde")]
+impl<'de> serde::Deserialize<'de> for SharedString {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        std::string::String::deserialize(deserializer).map(Into::into)
+    }
+}
+
+/// A set of [`SharedString`]s for sharing strings between the nodes of
+/// [`SharedByml`] documents, such as the map units of a whole game dump.
+#[derive(Debug, Default, Clone)]
+pub struct StringPool(FxHashSet<SharedString>);
+
+impl StringPool {
+    /// Create an empty string pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the pooled copy of a string, adding it if it is not in the pool.
+    pub fn intern(&mut self, s: &str) -> SharedString {
+        if let Some(shared) = self.0.get(s) {
+            shared.clone()
+        } else {
+            let shared = SharedString::from(s);
+            self.0.insert(shared.clone());
+            shared
+        }
+    }
+
+    /// Return the number of strings in the pool.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Map with interned string keys, used by [`SharedByml`].
+pub type SharedMap =
+    indexmap::IndexMap<SharedString, SharedByml, BuildHasherDefault<FxHasher>>;
+
+/// A read-only BYML document whose strings and map keys are
+/// [`SharedString`]s from a [`StringPool`], for keeping many large documents
+/// loaded at once. Equal strings in all documents using the same pool share
+/// one allocation.
+///
+/// Create one with [`Byml::from_binary_interned`] or [`Byml::into_shared`],
+/// and convert it back with [`SharedByml::to_byml`] to edit it. Like a
+/// [`Byml`], a node known to be an array or map can be indexed, which
+/// **panics if the node or index has the wrong type or the index is not
+/// found**:
+/// ```
+/// # use roead::byml::{Byml, StringPool};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data = std::fs::read("test/byml/ActorInfo.product.byml")?;
+/// let mut pool = StringPool::new();
+/// let actor_info = Byml::from_binary_interned(&data, &mut pool)?;
+/// assert_eq!(actor_info["Actors"].as_array()?.len(), 7934);
+/// assert_eq!(actor_info["Hashes"][0].as_i32()?, 31119);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub enum SharedByml {
+    /// String value.
+    String(SharedString),
+    /// Binary data (not used in BOTW).
+    BinaryData(Vec<u8>),
+    /// File data
+    FileData(Vec<u8>),
+    /// Array of BYML nodes.
+    Array(Vec<SharedByml>),
+    /// Hash map of BYML nodes with string keys.
+    Map(SharedMap),
+    /// Hash map of BYML nodes with u32 keys.
+    HashMap(FxHashMap<u32, SharedByml>),
+    /// Hash map of BYML nodes with u32 keys and additional u32 values.
+    ValueHashMap(FxHashMap<u32, (SharedByml, u32)>),
+    /// Boolean value.
+    Bool(bool),
+    /// 32-bit signed integer.
+    I32(i32),
+    /// 32-bit float.
+    Float(f32),
+    /// 32-bit unsigned integer.
+    U32(u32),
+    /// 64-bit signed integer.
+    I64(i64),
+    /// 64-bit unsigned integer.
+    U64(u64),
+    /// 64-bit float.
+    Double(f64),
+    /// Null value.
+    #[default]
+    Null,
+}
+
+impl SharedByml {
+    fn type_name(&self) -> super::String {
+        match self {
+            Self::String(_) => "String".into(),
+            Self::BinaryData(_) => "Binary".into(),
+            Self::FileData(_) => "File".into(),
+            Self::Array(_) => "Array".into(),
+            Self::Map(_) => "Map".into(),
+            Self::HashMap(_) => "HashMap".into(),
+            Self::ValueHashMap(_) => "ValueHashMap".into(),
+            Self::Bool(_) => "Bool".into(),
+            Self::I32(_) => "I32".into(),
+            Self::Float(_) => "Float".into(),
+            Self::U32(_) => "U32".into(),
+            Self::I64(_) => "I64".into(),
+            Self::U64(_) => "U64".into(),
+            Self::Double(_) => "Double".into(),
+            Self::Null => "Null".into(),
+        }
+    }
+
+    /// Checks if the BYML node is a null node
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Get the inner bool value.
+    pub fn as_bool(&self) -> Result<bool> {
+        if let Self::Bool(v) = self {
+            Ok(*v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "Bool"))
+        }
+    }
+
+    /// Get the inner i32 value.
+    pub fn as_i32(&self) -> Result<i32> {
+        if let Self::I32(v) = self {
+            Ok(*v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "I32"))
+        }
+    }
+
+    /// Get the inner u32 value.
+    pub fn as_u32(&self) -> Result<u32> {
+        if let Self::U32(v) = self {
+            Ok(*v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "U32"))
+        }
+    }
+
+    /// Get the inner i64 value.
+    pub fn as_i64(&self) -> Result<i64> {
+        if let Self::I64(v) = self {
+            Ok(*v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "I64"))
+        }
+    }
+
+    /// Get the inner u64 value.
+    pub fn as_u64(&self) -> Result<u64> {
+        if let Self::U64(v) = self {
+            Ok(*v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "U64"))
+        }
+    }
+
+    /// Get the inner f32 value.
+    pub fn as_float(&self) -> Result<f32> {
+        if let Self::Float(v) = self {
+            Ok(*v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "Float"))
+        }
+    }
+
+    /// Get the inner f64 value.
+    pub fn as_double(&self) -> Result<f64> {
+        if let Self::Double(v) = self {
+            Ok(*v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "Double"))
+        }
+    }
+
+    /// Get a reference to the inner string value.
+    pub fn as_string(&self) -> Result<&SharedString> {
+        if let Self::String(v) = self {
+            Ok(v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "String"))
+        }
+    }
+
+    /// Get a reference to the inner byte slice.
+    pub fn as_binary_data(&self) -> Result<&[u8]> {
+        if let Self::BinaryData(v) = self {
+            Ok(v.as_slice())
+        } else {
+            Err(Error::TypeError(self.type_name(), "BinaryData"))
+        }
+    }
+
+    /// Get a reference to the inner file data.
+    pub fn as_file_data(&self) -> Result<&[u8]> {
+        if let Self::FileData(v) = self {
+            Ok(v.as_slice())
+        } else {
+            Err(Error::TypeError(self.type_name(), "FileData"))
+        }
+    }
+
+    /// Get a reference to the inner array of BYML nodes.
+    pub fn as_array(&self) -> Result<&[SharedByml]> {
+        if let Self::Array(v) = self {
+            Ok(v.as_slice())
+        } else {
+            Err(Error::TypeError(self.type_name(), "Array"))
+        }
+    }
+
+    /// Get a reference to the inner string-keyed hash map of BYML nodes.
+    pub fn as_map(&self) -> Result<&SharedMap> {
+        if let Self::Map(v) = self {
+            Ok(v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "Map"))
+        }
+    }
+
+    /// Get a reference to the inner u32-keyed hash map of BYML nodes.
+    pub fn as_hash_map(&self) -> Result<&FxHashMap<u32, SharedByml>> {
+        if let Self::HashMap(v) = self {
+            Ok(v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "HashMap"))
+        }
+    }
+
+    /// Get a reference to the inner u32-keyed hash map of BYML nodes with
+    /// their additional values.
+    pub fn as_value_hash_map(&self) -> Result<&FxHashMap<u32, (SharedByml, u32)>> {
+        if let Self::ValueHashMap(v) = self {
+            Ok(v)
+        } else {
+            Err(Error::TypeError(self.type_name(), "ValueHashMap"))
+        }
+    }
+
+    /// Get a child node, or `None` if the node or index has the wrong type or
+    /// the index is not found.
+    pub fn get<'a>(&self, index: impl Into<BymlIndex<'a>>) -> Option<&SharedByml> {
+        match (self, index.into()) {
+            (Self::Array(a), BymlIndex::ArrayIdx(i)) => a.get(i),
+            (Self::Map(h), BymlIndex::StringIdx(k)) => h.get(k),
+            (Self::HashMap(h), BymlIndex::HashIdx(i)) => h.get(&i),
+            (Self::ValueHashMap(h), BymlIndex::HashIdx(i)) => h.get(&i).map(|(v, _)| v),
+            _ => None,
+        }
+    }
+
+    /// Convert the document to an ordinary [`Byml`] document.
+    pub fn to_byml(&self) -> Byml {
+        match self {
+            Self::String(s) => Byml::String(s.as_str().into()),
+            Self::BinaryData(data) => Byml::BinaryData(data.clone()),
+            Self::FileData(data) => Byml::FileData(data.clone()),
+            Self::Array(array) => Byml::Array(array.iter().map(Self::to_byml).collect()),
+            Self::Map(map) => {
+                Byml::Map(
+                    map.iter()
+                        .map(|(key, value)| (key.as_str().into(), value.to_byml()))
+                        .collect(),
+                )
+            }
+            Self::HashMap(map) => {
+                Byml::HashMap(map.iter().map(|(hash, value)| (*hash, value.to_byml())).collect())
+            }
+            Self::ValueHashMap(map) => {
+                Byml::ValueHashMap(
+                    map.iter()
+                        .map(|(hash, (value, extra))| (*hash, (value.to_byml(), *extra)))
+                        .collect(),
+                )
+            }
+            Self::Bool(v) => Byml::Bool(*v),
+            Self::I32(v) => Byml::I32(*v),
+            Self::Float(v) => Byml::Float(*v),
+            Self::U32(v) => Byml::U32(*v),
+            Self::I64(v) => Byml::I64(*v),
+            Self::U64(v) => Byml::U64(*v),
+            Self::Double(v) => Byml::Double(*v),
+            Self::Null => Byml::Null,
+        }
+    }
+
+    /// Serialize the document to binary into the given writer. This can only
+    /// be done for Null, Array, or Hash nodes. See [`Byml::write`].
+    pub fn write<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        version: u16,
+    ) -> Result<()> {
+        if !is_valid_version(version) {
+            return Err(Error::InvalidData("Unsupported BYML version (1-7 only)"));
+        }
+        if !matches!(
+            self,
+            Self::Map(_) | Self::HashMap(_) | Self::ValueHashMap(_) | Self::Array(_) | Self::Null
+        ) {
+            return Err(Error::TypeError(
+                self.type_name(),
+                "Map, HashMap, ValueHashMap, Array, or Null",
+            ));
+        }
+        write_document(self, writer, endian, version)
+    }
+
+    /// Serialize the document to binary data with the specified endianness
+    /// and default version (2). See [`Byml::to_binary`].
+    pub fn to_binary(&self, endian: Endian) -> Vec<u8> {
+        self.to_binary_with_version(endian, 2)
+    }
+
+    /// Serialize the document to binary data with the specified endianness
+    /// and version number. See [`Byml::to_binary_with_version`].
+    pub fn to_binary_with_version(&self, endian: Endian, version: u16) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write(&mut Cursor::new(&mut buf), endian, version)
+            .expect("BYML should serialize to binary without error");
+        buf
+    }
+
+    /// Parse a document from YAML text, taking its strings from the given
+    /// pool. See [`Byml::from_text`].
+    #[cfg(feature = "yaml")]
+    pub fn from_text(text: impl AsRef<str>, pool: &mut StringPool) -> Result<Self> {
+        Ok(Byml::from_text(text)?.into_shared(pool))
+    }
+
+    /// Serialize the document to YAML. See [`Byml::to_text`].
+    ///
+    /// The text is emitted from a temporary [`Byml`] copy of the document.
+    #[cfg(feature = "yaml")]
+    pub fn to_text(&self) -> std::string::String {
+        self.to_byml().to_text()
+    }
+
+    /// Parse a document from JSON, taking its strings from the given pool.
+    /// See [`Byml::from_json`].
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str, pool: &mut StringPool) -> Result<Self> {
+        Ok(Byml::from_json(text)?.into_shared(pool))
+    }
+
+    /// Serialize the document to JSON. See [`Byml::to_json`].
+    ///
+    /// The text is emitted from a temporary [`Byml`] copy of the document.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> std::string::String {
+        self.to_byml().to_json()
+    }
+}
+
+impl PartialEq for SharedByml {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(s1), Self::String(s2)) => s1 == s2,
+            (Self::BinaryData(d1), Self::BinaryData(d2)) => d1 == d2,
+            (Self::FileData(d1), Self::FileData(d2)) => d1 == d2,
+            (Self::Array(a1), Self::Array(a2)) => a1 == a2,
+            (Self::Map(h1), Self::Map(h2)) => h1 == h2,
+            (Self::HashMap(h1), Self::HashMap(h2)) => h1 == h2,
+            (Self::ValueHashMap(h1), Self::ValueHashMap(h2)) => h1 == h2,
+            (Self::Bool(b1), Self::Bool(b2)) => b1 == b2,
+            (Self::I32(i1), Self::I32(i2)) => i1 == i2,
+            (Self::Float(f1), Self::Float(f2)) => almost::equal(*f1, *f2),
+            (Self::U32(u1), Self::U32(u2)) => u1 == u2,
+            (Self::I64(i1), Self::I64(i2)) => i1 == i2,
+            (Self::U64(u1), Self::U64(u2)) => u1 == u2,
+            (Self::Double(d1), Self::Double(d2)) => almost::equal(*d1, *d2),
+            (Self::Null, Self::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::hash::Hash for SharedByml {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self {
+            Self::String(s) => s.hash(state),
+            Self::BinaryData(b) => b.hash(state),
+            Self::FileData(b) => b.hash(state),
+            Self::Array(a) => a.hash(state),
+            Self::Map(h) => hash_entries(h.iter(), state),
+            Self::HashMap(h) => hash_entries(h.iter(), state),
+            Self::ValueHashMap(h) => hash_entries(h.iter(), state),
+            Self::Bool(b) => b.hash(state),
+            Self::I32(i) => i.hash(state),
+            Self::Float(f) => {
+                b"f".hash(state);
+                f.to_bits().hash(state)
+            }
+            Self::U32(u) => u.hash(state),
+            Self::I64(i) => i.hash(state),
+            Self::U64(u) => u.hash(state),
+            Self::Double(d) => {
+                b"d".hash(state);
+                d.to_bits().hash(state)
+            }
+            Self::Null => std::hash::Hash::hash(&0, state),
+        }
+    }
+}
+
+impl<'a, I: Into<BymlIndex<'a>>> std::ops::Index<I> for SharedByml {
+    type Output = SharedByml;
+
+    fn index(&self, index: I) -> &Self::Output {
+        match (self, index.into()) {
+            (Self::Array(a), BymlIndex::ArrayIdx(i)) => &a[i],
+            (Self::Map(h), BymlIndex::StringIdx(k)) => &h[k],
+            (Self::HashMap(h), BymlIndex::HashIdx(i)) => &h[&i],
+            (Self::ValueHashMap(h), BymlIndex::HashIdx(i)) => &h[&i].0,
+            _ => panic!("Wrong index type or node type."),
+        }
+    }
+}
+
+impl From<&SharedByml> for Byml {
+    fn from(value: &SharedByml) -> Self {
+        value.to_byml()
+    }
+}
+
+impl ParseTarget for SharedByml {
+    type Pool = StringPool;
+    type Str = SharedString;
+
+    fn table_str(s: &str, pool: &mut StringPool) -> SharedString {
+        pool.intern(s)
+    }
+
+    fn string(s: SharedString) -> Self {
+        Self::String(s)
+    }
+
+    fn value(node: Byml) -> Self {
+        match node {
+            Byml::BinaryData(data) => Self::BinaryData(data),
+            Byml::FileData(data) => Self::FileData(data),
+            Byml::Bool(v) => Self::Bool(v),
+            Byml::I32(v) => Self::I32(v),
+            Byml::Float(v) => Self::Float(v),
+            Byml::U32(v) => Self::U32(v),
+            Byml::I64(v) => Self::I64(v),
+            Byml::U64(v) => Self::U64(v),
+            Byml::Double(v) => Self::Double(v),
+            Byml::Null => Self::Null,
+            _ => unreachable!("Strings and containers are built separately"),
+        }
+    }
+
+    fn array(array: Vec<Self>) -> Self {
+        Self::Array(array)
+    }
+
+    fn map(map: SharedMap) -> Self {
+        Self::Map(map)
+    }
+
+    fn hash_map(map: FxHashMap<u32, Self>) -> Self {
+        Self::HashMap(map)
+    }
+
+    fn value_hash_map(map: FxHashMap<u32, (Self, u32)>) -> Self {
+        Self::ValueHashMap(map)
+    }
+}
+
+impl WriteNode for SharedByml {
+    type Str = SharedString;
+
+    fn node(&self) -> Node<'_, Self> {
+        match self {
+            Self::String(s) => Node::String(s),
+            Self::BinaryData(data) => Node::BinaryData(data),
+            Self::FileData(data) => Node::FileData(data),
+            Self::Array(array) => Node::Array(array),
+            Self::Map(map) => Node::Map(map),
+            Self::HashMap(map) => Node::HashMap(map),
+            Self::ValueHashMap(map) => Node::ValueHashMap(map),
+            Self::Bool(v) => Node::Bool(*v),
+            Self::I32(v) => Node::I32(*v),
+            Self::Float(v) => Node::Float(*v),
+            Self::U32(v) => Node::U32(*v),
+            Self::I64(v) => Node::I64(*v),
+            Self::U64(v) => Node::U64(*v),
+            Self::Double(v) => Node::Double(*v),
+            Self::Null => Node::Null,
+        }
+    }
+}
+
+impl Byml {
+    /// Read a document from binary data into a [`SharedByml`], taking its
+    /// strings from the given pool. Each string table entry is interned once
+    /// while parsing, without building a [`Byml`] first.
+    pub fn from_binary_interned(
+        data: impl AsRef<[u8]>,
+        pool: &mut StringPool,
+    ) -> Result<SharedByml> {
+        let limits = ParseLimits::default();
+        #[cfg(feature = "yaz0")]
+        {
+            if data.as_ref().starts_with(b"Yaz0") {
+                let data = crate::yaz0::decompress(data.as_ref())?;
+                return SliceParser::new(&data, limits, pool)?.parse();
+            }
+        }
+        SliceParser::new(data.as_ref(), limits, pool)?.parse()
+    }
+
+    /// Convert the document into a [`SharedByml`], taking its strings from
+    /// the given pool.
+    pub fn into_shared(self, pool: &mut StringPool) -> SharedByml {
+        match self {
+            Byml::String(s) => SharedByml::String(pool.intern(&s)),
+            Byml::BinaryData(data) => SharedByml::BinaryData(data),
+            Byml::FileData(data) => SharedByml::FileData(data),
+            Byml::Array(array) => {
+                SharedByml::Array(array.into_iter().map(|item| item.into_shared(pool)).collect())
+            }
+            Byml::Map(map) => {
+                SharedByml::Map(
+                    map.into_iter()
+                        .map(|(key, value)| (pool.intern(&key), value.into_shared(pool)))
+                        .collect(),
+                )
+            }
+            Byml::HashMap(map) => {
+                SharedByml::HashMap(
+                    map.into_iter()
+                        .map(|(hash, value)| (hash, value.into_shared(pool)))
+                        .collect(),
+                )
+            }
+            Byml::ValueHashMap(map) => {
+                SharedByml::ValueHashMap(
+                    map.into_iter()
+                        .map(|(hash, (value, extra))| (hash, (value.into_shared(pool), extra)))
+                        .collect(),
+                )
+            }
+            Byml::Bool(v) => SharedByml::Bool(v),
+            Byml::I32(v) => SharedByml::I32(v),
+            Byml::Float(v) => SharedByml::Float(v),
+            Byml::U32(v) => SharedByml::U32(v),
+            Byml::I64(v) => SharedByml::I64(v),
+            Byml::U64(v) => SharedByml::U64(v),
+            Byml::Double(v) => SharedByml::Double(v),
+            Byml::Null => SharedByml::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_strings() {
+        let data = std::fs::read("test/byml/ActorInfo.product.byml").unwrap();
+        let mut pool = StringPool::new();
+        let first = Byml::from_binary_interned(&data, &mut pool).unwrap();
+        let len = pool.len();
+        let second = Byml::from_binary_interned(&data, &mut pool).unwrap();
+        assert_eq!(pool.len(), len);
+        assert_eq!(first, second);
+
+        let profile = |doc: &SharedByml| doc["Actors"][0]["profile"].as_string().unwrap().clone();
+        assert!(profile(&first).ptr_eq(&profile(&second)));
+        assert_eq!(first["Actors"].as_array().unwrap().len(), 7934);
+        assert_eq!(first.get("Hashes").and_then(|h| h.get(0)), Some(&SharedByml::I32(31119)));
+        assert!(first.get("Missing").is_none());
+        assert!(first["Actors"].as_map().is_err());
+        let byml = Byml::from_binary(&data).unwrap();
+        assert_eq!(first.to_byml(), byml);
+        assert_eq!(first.to_binary(Endian::Little), byml.to_binary(Endian::Little));
+        assert_eq!(first.to_binary(Endian::Big), byml.to_binary(Endian::Big));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn shared_json() {
+        let data = std::fs::read("test/byml/ActorInfo.product.byml").unwrap();
+        let mut pool = StringPool::new();
+        let doc = Byml::from_binary_interned(&data, &mut pool).unwrap();
+        let len = pool.len();
+        let json = doc.to_json();
+        assert_eq!(json, Byml::from_binary(&data).unwrap().to_json());
+        assert_eq!(SharedByml::from_json(&json, &mut pool).unwrap(), doc);
+        assert_eq!(pool.len(), len);
+    }
+}
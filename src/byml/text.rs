@@ -6,14 +6,55 @@ use crate::{yaml::*, Error, Result};
 
 impl Byml {
     /// Parse BYML document from YAML text.
+    ///
+    /// Keys of hash maps (`!h` and `!vh`) may be integers or names, which are
+    /// hashed with the [default hash name table](get_default_hash_name_table).
+    /// The names are not added to the table.
     pub fn from_text(text: impl AsRef<str>) -> Result<Byml> {
-        Parser::new(text.as_ref())?.parse()
+        Parser::new(text.as_ref(), KeyNames {
+            table: get_default_hash_name_table(),
+            add:   false,
+        })?
+        .parse()
+    }
+
+    /// Parse BYML document from YAML text, hashing the string keys of hash
+    /// maps with the given name table and adding them to it.
+    pub fn from_text_with_names(text: impl AsRef<str>, names: &HashNameTable) -> Result<Byml> {
+        Parser::new(text.as_ref(), KeyNames {
+            table: names,
+            add:   true,
+        })?
+        .parse()
     }
 
     /// Serialize the document to YAML. This can only be done for Null, Array,
     /// or Hash nodes.
+    ///
+    /// Map keys are emitted in insertion order, so a document parsed from
+    /// YAML is emitted in its source order. Use [`Byml::to_text_sorted`] to
+    /// sort them.
+    ///
+    /// Hash map keys found in the [default hash name
+    /// table](get_default_hash_name_table) are written as strings.
     pub fn to_text(&self) -> std::string::String {
-        Emitter::new(self)
+        self.to_text_with_names(get_default_hash_name_table())
+    }
+
+    /// Serialize the document to YAML, writing hash map keys found in the
+    /// given name table as strings. This can only be done for Null, Array, or
+    /// Hash nodes.
+    pub fn to_text_with_names(&self, names: &HashNameTable) -> std::string::String {
+        Emitter::new(self, false, names)
+            .emit()
+            .expect("BYML must be container or null to serialize")
+    }
+
+    /// Serialize the document to YAML with all map keys sorted, regardless of
+    /// how the document was built. This can only be done for Null, Array, or
+    /// Hash nodes.
+    pub fn to_text_sorted(&self) -> std::string::String {
+        Emitter::new(self, true, get_default_hash_name_table())
             .emit()
             .expect("BYML must be container or null to serialize")
     }
@@ -27,31 +68,51 @@ fn is_binary_tag(tag: &str) -> bool {
 #[inline]
 fn recognize_tag(tag: &str) -> Option<TagBasedType> {
     match tag {
-        "!f64" => Some(TagBasedType::Float),
+        "!f32" | "!f64" => Some(TagBasedType::Float),
         "!u" | "!l" | "!ul" => Some(TagBasedType::Int),
         "tag:yaml.org,2002:binary" | "!!binary" | "!!file" => Some(TagBasedType::Str),
         _ => None,
     }
 }
 
-struct Parser<'a>(Tree<'a>);
 
-impl<'a> Parser<'a> {
-    fn new(text: &str) -> Result<Self> {
-        Ok(Self(Tree::parse(text)?))
+/// The name table used for hash map keys while parsing, and whether unknown
+/// names may be added to it.
+#[derive(Clone, Copy)]
+struct KeyNames<'n> {
+    table: &'n HashNameTable,
+    add:   bool,
+}
+
+impl KeyNames<'_> {
+    /// Read a hash map key, which is either a hash or a name to hash.
+    fn parse_key(self, key: &str) -> u32 {
+        if let Ok(hash) = key.parse() {
+            hash
+        } else if self.add {
+            self.table.add_name(key)
+        } else {
+            self.table.hash_name(key)
+        }
+    }
+}
+
+struct Parser<'a, 'n>(Tree<'a>, KeyNames<'n>);
+
+impl<'a, 'n> Parser<'a, 'n> {
+    fn new(text: &str, names: KeyNames<'n>) -> Result<Self> {
+        Ok(Self(Tree::parse(text)?, names))
     }
 
-    fn parse_node(node: NodeRef<'a, '_, '_, &Tree<'a>>) -> Result<Byml> {
+    fn parse_node(node: NodeRef<'a, '_, '_, &Tree<'a>>, names: KeyNames) -> Result<Byml> {
         if node.is_map()? {
             match node.val_tag().unwrap_or("") {
                 "!h" => {
                     Ok(Byml::HashMap(
                         node.iter()?
                             .map(|child| {
-                                let key = child.key()?.parse().map_err(|_| {
-                                    Error::Any(format!("Expected integer hash key"))
-                                })?;
-                                let value = Self::parse_node(child.clone())?;
+                                let key = names.parse_key(child.key()?);
+                                let value = Self::parse_node(child.clone(), names)?;
                                 Ok((key, value))
                             })
                             .collect::<Result<_>>()?,
@@ -61,10 +122,8 @@ impl<'a> Parser<'a> {
                     Ok(Byml::ValueHashMap(
                         node.iter()?
                             .map(|child| {
-                                let key = child.key()?.parse().map_err(|_| {
-                                    Error::Any(format!("Expected integer hash key"))
-                                })?;
-                                let value = Self::parse_node(child.clone())?;
+                                let key = names.parse_key(child.key()?);
+                                let value = Self::parse_node(child.clone(), names)?;
                                 Ok((key, (value, 0)))
                             })
                             .collect::<Result<_>>()?,
@@ -75,7 +134,7 @@ impl<'a> Parser<'a> {
                         node.iter()?
                             .map(|child| {
                                 let key = child.key()?;
-                                let value = Self::parse_node(child.clone())?;
+                                let value = Self::parse_node(child.clone(), names)?;
                                 Ok((key.into(), value))
                             })
                             .collect::<Result<_>>()?,
@@ -85,7 +144,7 @@ impl<'a> Parser<'a> {
         } else if node.is_seq()? {
             Ok(Byml::Array(
                 node.iter()?
-                    .map(|child| Self::parse_node(child.clone()))
+                    .map(|child| Self::parse_node(child.clone(), names))
                     .collect::<Result<_>>()?,
             ))
         } else {
@@ -128,7 +187,7 @@ impl<'a> Parser<'a> {
 
     fn parse(self) -> Result<Byml> {
         let root = self.0.root_ref()?;
-        Self::parse_node(root)
+        Self::parse_node(root, self.1)
     }
 }
 
@@ -142,18 +201,32 @@ fn should_use_inline(byml: &Byml) -> bool {
     }
 }
 
-struct Emitter<'a, 'b>(&'a Byml, Tree<'b>);
+/// Write a hash map key as its name if it is known, unless the name could be
+/// mistaken for a hash.
+fn hash_key_text(
+    key: u32,
+    names: &rustc_hash::FxHashMap<u32, std::string::String>,
+) -> std::string::String {
+    match names.get(&key) {
+        Some(name) if !name.is_empty() && name.parse::<u32>().is_err() => name.clone(),
+        _ => key.to_string(),
+    }
+}
+
+struct Emitter<'a, 'b, 'n>(&'a Byml, Tree<'b>, bool, &'n HashNameTable);
 
-impl<'a, 'b> Emitter<'a, 'b> {
-    fn new(byml: &'a Byml) -> Self {
+impl<'a, 'b, 'n> Emitter<'a, 'b, 'n> {
+    fn new(byml: &'a Byml, sorted: bool, names: &'n HashNameTable) -> Self {
         let mut tree = Tree::default();
         tree.reserve(20000);
-        Self(byml, tree)
+        Self(byml, tree, sorted, names)
     }
 
     fn build_node<'e>(
         byml: &Byml,
         mut dest_node: NodeRef<'b, 'e, '_, &'e mut Tree<'b>>,
+        sorted: bool,
+        names: &rustc_hash::FxHashMap<u32, std::string::String>,
     ) -> Result<()> {
         match byml {
             Byml::Array(array) => {
@@ -164,7 +237,7 @@ impl<'a, 'b> Emitter<'a, 'b> {
                 }
                 for item in array {
                     let node = dest_node.append_child()?;
-                    Self::build_node(item, node)?;
+                    Self::build_node(item, node, sorted, names)?;
                 }
             }
             Byml::Map(hash) => {
@@ -174,7 +247,9 @@ impl<'a, 'b> Emitter<'a, 'b> {
                     dest_node.change_type(ryml::NodeType::Map)?;
                 }
                 let mut map_items = hash.iter().collect::<Vec<_>>();
-                map_items.sort_by(|a, b| a.0.cmp(b.0));
+                if sorted {
+                    map_items.sort_by(|a, b| a.0.cmp(b.0));
+                }
                 for (key, value) in map_items {
                     let mut node = dest_node.append_child()?;
                     node.set_key(key)?;
@@ -182,7 +257,7 @@ impl<'a, 'b> Emitter<'a, 'b> {
                         let flags = node.node_type()?;
                         node.set_type_flags(flags | ryml::NodeType::WipKeySquo)?;
                     }
-                    Self::build_node(value, node)?;
+                    Self::build_node(value, node, sorted, names)?;
                 }
             }
             Byml::HashMap(hash) => {
@@ -195,8 +270,13 @@ impl<'a, 'b> Emitter<'a, 'b> {
                 map_items.sort_by(|a, b| a.0.cmp(b.0));
                 for (key, value) in map_items {
                     let mut node = dest_node.append_child()?;
-                    node.set_key(&key.to_string())?;
-                    Self::build_node(value, node)?;
+                    let key = hash_key_text(*key, names);
+                    node.set_key(&key)?;
+                    if key.parse::<u32>().is_err() && string_needs_quotes(&key) {
+                        let flags = node.node_type()?;
+                        node.set_type_flags(flags | ryml::NodeType::WipKeySquo)?;
+                    }
+                    Self::build_node(value, node, sorted, names)?;
                 }
                 dest_node.set_val_tag("!h")?;
             }
@@ -210,8 +290,13 @@ impl<'a, 'b> Emitter<'a, 'b> {
                 map_items.sort_by(|a, b| a.0.cmp(b.0));
                 for (key, (value, _)) in map_items {
                     let mut node = dest_node.append_child()?;
-                    node.set_key(&key.to_string())?;
-                    Self::build_node(value, node)?;
+                    let key = hash_key_text(*key, names);
+                    node.set_key(&key)?;
+                    if key.parse::<u32>().is_err() && string_needs_quotes(&key) {
+                        let flags = node.node_type()?;
+                        node.set_type_flags(flags | ryml::NodeType::WipKeySquo)?;
+                    }
+                    Self::build_node(value, node, sorted, names)?;
                 }
                 dest_node.set_val_tag("!vh")?;
             }
@@ -269,7 +354,7 @@ impl<'a, 'b> Emitter<'a, 'b> {
     }
 
     fn emit(self) -> Result<std::string::String> {
-        let Self(byml, mut tree) = self;
+        let Self(byml, mut tree, sorted, names) = self;
         match byml {
             Byml::Map(_) | Byml::HashMap(_) | Byml::ValueHashMap(_) => tree.to_map(0)?,
             Byml::Array(_) => tree.to_seq(0)?,
@@ -280,7 +365,7 @@ impl<'a, 'b> Emitter<'a, 'b> {
                 ));
             }
         };
-        Self::build_node(byml, tree.root_ref_mut()?)?;
+        names.with_names(|names| Self::build_node(byml, tree.root_ref_mut()?, sorted, names))?;
         Ok(tree.emit()?)
     }
 }
@@ -321,4 +406,74 @@ mod test {
             assert_eq!(byml, byml);
         }
     }
+
+    #[test]
+    fn key_order() {
+        let byml = Byml::from_text("{zeta: 1, alpha: 2, mid: 3}").unwrap();
+        let sorted = byml.to_text_sorted();
+        let positions = |text: &str| ["zeta", "alpha", "mid"].map(|key| text.find(key).unwrap());
+        let [zeta, alpha, mid] = positions(&sorted);
+        assert!(alpha < mid && mid < zeta);
+        let [zeta, alpha, mid] = positions(&byml.to_text());
+        assert!(zeta < alpha && alpha < mid);
+    }
+
+    #[test]
+    fn float_tags() {
+        let byml = Byml::from_text("[!f32 1, !f64 2, 3, 4.5]").unwrap();
+        assert_eq!(
+            byml,
+            Byml::Array(vec![
+                Byml::Float(1.0),
+                Byml::Double(2.0),
+                Byml::I32(3),
+                Byml::Float(4.5)
+            ])
+        );
+        // Whole floats are emitted without a decimal point.
+        let byml = Byml::Array(vec![Byml::Float(1.0), Byml::Double(-2.0)]);
+        assert_eq!(Byml::from_text(byml.to_text()).unwrap(), byml);
+    }
+
+    #[test]
+    fn hash_key_names() {
+        let names = HashNameTable::new(KeyHash::Murmur3);
+        let byml = Byml::from_text_with_names(
+            "!h {Known: 1, 12345: 2, Nested: !vh {Inner: 3}}",
+            &names,
+        )
+        .unwrap();
+        let known = KeyHash::Murmur3.hash("Known");
+        assert_eq!(byml.as_hash_map().unwrap()[&known], Byml::I32(1));
+        assert_eq!(byml.as_hash_map().unwrap()[&12345], Byml::I32(2));
+        assert_eq!(names.get_name(known).as_deref(), Some("Known"));
+
+        let text = byml.to_text_with_names(&names);
+        assert!(text.contains("Known"));
+        assert!(text.contains("Inner"));
+        assert!(text.contains("12345"));
+        assert_eq!(Byml::from_text_with_names(&text, &names).unwrap(), byml);
+
+        let unnamed = byml.to_text_with_names(&HashNameTable::default());
+        assert!(!unnamed.contains("Known"));
+        assert!(unnamed.contains(&known.to_string()));
+        assert_eq!(
+            Byml::from_text_with_names(unnamed, &HashNameTable::default()).unwrap(),
+            byml
+        );
+    }
+
+    #[test]
+    fn default_hash_key_names() {
+        let table = get_default_hash_name_table();
+        let unknown = table.hash_name("TextUnknownKeyName");
+        let byml = Byml::from_text("!h {TextUnknownKeyName: 1}").unwrap();
+        assert_eq!(byml.as_hash_map().unwrap()[&unknown], Byml::I32(1));
+        assert_eq!(table.get_name(unknown), None);
+
+        let known = table.add_name("TextKnownKeyName");
+        let byml = Byml::from_text("!vh {TextKnownKeyName: 1}").unwrap();
+        assert_eq!(byml.as_value_hash_map().unwrap()[&known].0, Byml::I32(1));
+        assert_eq!(Byml::from_text(byml.to_text()).unwrap(), byml);
+    }
 }
@@ -0,0 +1,243 @@
+use super::{path::escape, *};
+
+/// A node which cannot be written in a BYML version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionIssue {
+    /// Pointer to the node, as accepted by [`Byml::pointer`].
+    pub path: String,
+    /// The type of the node.
+    pub node_type: String,
+    /// The first BYML version which supports the node type.
+    pub min_version: u16,
+}
+
+impl std::fmt::Display for VersionIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at `{}` requires BYML version {} or later",
+            self.node_type, self.path, self.min_version
+        )
+    }
+}
+
+/// A node converted by [`Byml::migrate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// Pointer to the node, as accepted by [`Byml::pointer`].
+    pub path: String,
+    /// The original type of the node.
+    pub from: String,
+    /// The new type of the node.
+    pub to:   String,
+}
+
+/// The result of [`Byml::migrate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Nodes converted to types supported by the target version.
+    pub converted: Vec<Conversion>,
+    /// Nodes which could not be converted without losing data.
+    pub remaining: Vec<VersionIssue>,
+}
+
+impl MigrationReport {
+    /// Checks if the document is now valid for the target version.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.remaining.is_empty()
+    }
+}
+
+/// The first BYML version which supports a node type.
+pub(super) fn min_type_version(node_type: NodeType) -> u16 {
+    match node_type {
+        NodeType::HashMap | NodeType::ValueHashMap => 7,
+        NodeType::Binary | NodeType::File => 4,
+        NodeType::I64 | NodeType::U64 | NodeType::Double => 3,
+        _ => 1,
+    }
+}
+
+fn min_version(node: &Byml) -> u16 {
+    min_type_version(node.get_node_type())
+}
+
+/// The error for nodes which cannot be written in a BYML version.
+pub(super) fn version_error(version: u16, issues: &[VersionIssue]) -> Error {
+    Error::InvalidDataD(format!(
+        "BYML document is not valid for version {version}: {}",
+        issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+fn check_version(version: u16) -> Result<()> {
+    if is_valid_version(version) {
+        Ok(())
+    } else {
+        Err(Error::InvalidData("Unsupported BYML version (1-7 only)"))
+    }
+}
+
+/// Build a pointer, as accepted by [`Byml::pointer`], from a node path.
+pub(super) fn pointer<'a>(path: impl IntoIterator<Item = BymlIndex<'a>>) -> String {
+    let mut pointer = String::new();
+    for index in path {
+        pointer.push('/');
+        match index {
+            BymlIndex::StringIdx(key) => pointer.push_str(&escape(key)),
+            BymlIndex::HashIdx(hash) => pointer.push_str(&hash.to_string()),
+            BymlIndex::ArrayIdx(i) => pointer.push_str(&i.to_string()),
+        }
+    }
+    pointer
+}
+
+/// Convert a node to an older type with the same value, if there is one.
+fn lower(node: &Byml) -> Option<Byml> {
+    match node {
+        Byml::I64(v) => i32::try_from(*v).ok().map(Byml::I32),
+        Byml::U64(v) => u32::try_from(*v).ok().map(Byml::U32),
+        Byml::Double(v) => {
+            let lowered = *v as f32;
+            (lowered as f64 == *v).then_some(Byml::Float(lowered))
+        }
+        _ => None,
+    }
+}
+
+impl Byml {
+    /// Find every node which cannot be written in the given BYML version:
+    /// 64-bit nodes require version 3, binary and file data nodes version 4
+    /// and hash map nodes version 7. Returns an empty `Vec` if the document is
+    /// valid, or an error if the version is not supported at all.
+    ///
+    /// [`Byml::write`] refuses to write documents with any of these issues,
+    /// so use this function to find them, or [`Byml::migrate`] to fix them
+    /// where possible.
+    pub fn validate_for_version(&self, version: u16) -> Result<Vec<VersionIssue>> {
+        check_version(version)?;
+        let mut issues = vec![];
+        self.visit(&mut |path: &[BymlIndex], node: &Byml| {
+            let min_version = min_version(node);
+            if min_version > version {
+                issues.push(VersionIssue {
+                    path: pointer(path.iter().copied()),
+                    node_type: node.type_name(),
+                    min_version,
+                });
+            }
+        });
+        issues.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(issues)
+    }
+
+    /// Like [`Byml::validate_for_version`], but returns an error listing the
+    /// issues if there are any.
+    pub(crate) fn ensure_version(&self, version: u16) -> Result<()> {
+        let issues = self.validate_for_version(version)?;
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(version_error(version, &issues))
+        }
+    }
+
+    /// Convert nodes which the given BYML version does not support to older
+    /// types where this loses no data: I64 and U64 nodes whose values fit in
+    /// 32 bits become I32 and U32 nodes, and Double nodes which are exactly
+    /// representable as f32 become Float nodes. All node types of older
+    /// versions are supported by newer ones, so raising the version never
+    /// requires changes.
+    ///
+    /// The report lists the converted nodes and any which could not be
+    /// converted, such as hash maps in versions before 7.
+    pub fn migrate(&mut self, version: u16) -> Result<MigrationReport> {
+        check_version(version)?;
+        let mut report = MigrationReport::default();
+        self.visit_mut(&mut |path: &[OwnedBymlIndex], node: &mut Byml| {
+            let min_version = min_version(node);
+            if min_version <= version {
+                return;
+            }
+            match lower(node) {
+                Some(lowered) => {
+                    report.converted.push(Conversion {
+                        path: pointer(path.iter().map(OwnedBymlIndex::as_index)),
+                        from: node.type_name(),
+                        to:   lowered.type_name(),
+                    });
+                    *node = lowered;
+                }
+                None => {
+                    report.remaining.push(VersionIssue {
+                        path: pointer(path.iter().map(OwnedBymlIndex::as_index)),
+                        node_type: node.type_name(),
+                        min_version,
+                    })
+                }
+            }
+        });
+        report.converted.sort_by(|a, b| a.path.cmp(&b.path));
+        report.remaining.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_and_migrate() {
+        let mut byml: Byml = [
+            ("small", Byml::I64(5)),
+            ("big", Byml::U64(u64::MAX)),
+            ("half", Byml::Double(0.5)),
+            ("tenth", Byml::Double(0.1)),
+            (
+                "hash",
+                Byml::HashMap([(1, Byml::I64(1))].into_iter().collect()),
+            ),
+            ("items", Byml::Array(vec![Byml::I32(1)])),
+        ]
+        .into_iter()
+        .collect();
+        assert!(byml.validate_for_version(7).unwrap().is_empty());
+        assert_eq!(byml.validate_for_version(3).unwrap().len(), 1);
+        let issues = byml.validate_for_version(2).unwrap();
+        assert_eq!(
+            issues.iter().map(|i| i.path.as_str()).collect::<Vec<_>>(),
+            ["/big", "/half", "/hash", "/hash/1", "/small", "/tenth"]
+        );
+        let data = Byml::Array(vec![
+            Byml::BinaryData(vec![1]),
+            Byml::FileData(vec![2]),
+        ]);
+        assert_eq!(data.validate_for_version(3).unwrap().len(), 2);
+        assert!(data.validate_for_version(4).unwrap().is_empty());
+        assert!(data.to_binary_with_version(crate::Endian::Big, 4).len() > 0x10);
+        assert!(data.to_binary_checked(crate::Endian::Big, 2).is_err());
+        assert!(data.to_binary_checked(crate::Endian::Big, 4).is_ok());
+        assert!(byml.validate_for_version(8).is_err());
+
+        let report = byml.migrate(2).unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.converted.len(), 3);
+        assert_eq!(
+            report
+                .remaining
+                .iter()
+                .map(|i| i.path.as_str())
+                .collect::<Vec<_>>(),
+            ["/big", "/hash", "/tenth"]
+        );
+        assert_eq!(byml["small"], Byml::I32(5));
+        assert_eq!(byml["half"], Byml::Float(0.5));
+        assert_eq!(byml["hash"][1u32], Byml::I32(1));
+    }
+}
@@ -0,0 +1,656 @@
+use super::{
+    parser::{check_container, Guard},
+    *,
+};
+use crate::{util::align, Endian, Error, Result};
+
+pub(super) fn parse_node_type(byte: u8) -> Result<NodeType> {
+    Ok(match byte {
+        0x20 => NodeType::HashMap,
+        0x21 => NodeType::ValueHashMap,
+        0xa0 => NodeType::String,
+        0xa1 => NodeType::Binary,
+        0xa2 => NodeType::File,
+        0xc0 => NodeType::Array,
+        0xc1 => NodeType::Map,
+        0xc2 => NodeType::StringTable,
+        0xd0 => NodeType::Bool,
+        0xd1 => NodeType::I32,
+        0xd2 => NodeType::Float,
+        0xd3 => NodeType::U32,
+        0xd4 => NodeType::I64,
+        0xd5 => NodeType::U64,
+        0xd6 => NodeType::Double,
+        0xff => NodeType::Null,
+        _ => {
+            return Err(Error::InvalidDataD(format!(
+                "Invalid BYML node type: {byte:#x}"
+            )));
+        }
+    })
+}
+
+/// The binary document shared by every [`BymlRef`] into it.
+#[derive(Clone, Copy)]
+pub(super) struct Document<'a> {
+    pub(super) data: &'a [u8],
+    pub(super) endian: Endian,
+    pub(super) hash_key_table: u32,
+    pub(super) string_table: u32,
+}
+
+impl<'a> Document<'a> {
+    /// Validate the document header and locate the string tables.
+    pub(super) fn new(data: &'a [u8]) -> Result<Self> {
+        if data.len() < 0x10 {
+            return Err(Error::InsufficientData(data.len(), 0x10));
+        }
+        let endian = match &data[..2] {
+            b"BY" => Endian::Big,
+            b"YB" => Endian::Little,
+            magic => {
+                return Err(Error::BadMagic(
+                    std::string::String::from_utf8_lossy(magic).into(),
+                    "BY or YB",
+                ));
+            }
+        };
+        let mut doc = Document {
+            data,
+            endian,
+            hash_key_table: 0,
+            string_table: 0,
+        };
+        let version = match endian {
+            Endian::Big => u16::from_be_bytes([data[2], data[3]]),
+            Endian::Little => u16::from_le_bytes([data[2], data[3]]),
+        };
+        if !is_valid_version(version) {
+            return Err(Error::InvalidData("Unsupported BYML version (1-7 only)"));
+        }
+        doc.hash_key_table = doc.u32(0x4)?;
+        doc.string_table = doc.u32(0x8)?;
+        for table in [doc.hash_key_table, doc.string_table] {
+            if table != 0 && doc.u8(table)? != NodeType::StringTable as u8 {
+                return Err(Error::InvalidData("Invalid BYML string table"));
+            }
+        }
+        Ok(doc)
+    }
+
+    fn bytes<const N: usize>(&self, offset: u32) -> Result<[u8; N]> {
+        let offset = offset as usize;
+        self.data
+            .get(offset..offset + N)
+            .and_then(|b| b.try_into().ok())
+            .ok_or(Error::InsufficientData(self.data.len(), offset + N))
+    }
+
+    pub(super) fn u8(&self, offset: u32) -> Result<u8> {
+        Ok(self.bytes::<1>(offset)?[0])
+    }
+
+    pub(super) fn u24(&self, offset: u32) -> Result<u32> {
+        let [a, b, c] = self.bytes(offset)?;
+        Ok(match self.endian {
+            Endian::Big => u32::from_be_bytes([0, a, b, c]),
+            Endian::Little => u32::from_le_bytes([a, b, c, 0]),
+        })
+    }
+
+    pub(super) fn u32(&self, offset: u32) -> Result<u32> {
+        let bytes = self.bytes(offset)?;
+        Ok(match self.endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        })
+    }
+
+    pub(super) fn u64(&self, offset: u32) -> Result<u64> {
+        let bytes = self.bytes(offset)?;
+        Ok(match self.endian {
+            Endian::Big => u64::from_be_bytes(bytes),
+            Endian::Little => u64::from_le_bytes(bytes),
+        })
+    }
+
+    pub(super) fn table_len(&self, table: u32) -> Result<u32> {
+        if table == 0 {
+            Ok(0)
+        } else {
+            self.u24(table + 1)
+        }
+    }
+
+    pub(super) fn table_str(&self, table: u32, index: u32) -> Result<&'a str> {
+        if index >= self.table_len(table)? {
+            return Err(Error::InvalidData("Invalid string table entry index"));
+        }
+        let start = table as usize + self.u32(table + 4 + 4 * index)? as usize;
+        let bytes = self
+            .data
+            .get(start..)
+            .ok_or(Error::InsufficientData(self.data.len(), start))?;
+        let len = bytes
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(Error::InvalidData("Unterminated BYML string"))?;
+        Ok(std::str::from_utf8(&bytes[..len])?)
+    }
+
+    /// Read every string in a string table.
+    pub(super) fn table_strs(&self, table: u32) -> Result<Vec<&'a str>> {
+        (0..self.table_len(table)?)
+            .map(|i| self.table_str(table, i))
+            .collect()
+    }
+
+    /// Binary search a (sorted) string table.
+    fn table_find(&self, table: u32, key: &str) -> Result<Option<u32>> {
+        let (mut lo, mut hi) = (0, self.table_len(table)?);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.table_str(table, mid)?.cmp(key) {
+                std::cmp::Ordering::Equal => return Ok(Some(mid)),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A borrowed, lazily resolved view of a node in a binary BYML document.
+///
+/// Unlike [`Byml::from_binary`], creating a view does not parse the document.
+/// Nodes are only read when accessed: arrays are indexed directly, map keys
+/// are found by binary search in the sorted hash key table, and strings are
+/// borrowed from the string table. This makes it cheap to read a few values
+/// from a large document. Use [`BymlRef::to_owned`] to parse a subtree into a
+/// [`Byml`].
+///
+/// The data must be uncompressed.
+///
+/// ```
+/// # use roead::byml::BymlRef;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data = std::fs::read("test/byml/ActorInfo.product.byml")?;
+/// let actor_info = BymlRef::new(&data)?;
+/// let actors = actor_info.get("Actors").unwrap();
+/// assert_eq!(actors.len(), 7934);
+/// let name: &str = actors.at(0).unwrap().get("name").unwrap().as_str()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct BymlRef<'a> {
+    doc: Document<'a>,
+    node_type: NodeType,
+    /// Offset for container and 64-bit nodes, inline value otherwise.
+    value: u32,
+}
+
+impl std::fmt::Debug for BymlRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BymlRef")
+            .field("node_type", &self.node_type)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<'a> BymlRef<'a> {
+    /// Create a view of the root node of a binary BYML document.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let doc = Document::new(data)?;
+        let root = doc.u32(0xC)?;
+        if root == 0 {
+            Ok(Self {
+                doc,
+                node_type: NodeType::Null,
+                value: 0,
+            })
+        } else {
+            let node_type = parse_node_type(doc.u8(root)?)?;
+            if !is_container_type(node_type) {
+                return Err(Error::InvalidData("BYML root node must be a container"));
+            }
+            Ok(Self {
+                doc,
+                node_type,
+                value: root,
+            })
+        }
+    }
+
+    fn child(&self, node_type: u8, value_offset: u32) -> Result<Self> {
+        let child = Self {
+            doc: self.doc,
+            node_type: parse_node_type(node_type)?,
+            value: self.doc.u32(value_offset)?,
+        };
+        if child.data_offset().is_some() && child.value as usize >= self.doc.data.len() {
+            return Err(Error::InvalidDataAt(
+                value_offset as usize,
+                "BYML node offset is past the end of the data".into(),
+            ));
+        }
+        Ok(child)
+    }
+
+    /// Offset of the node's data if it is stored outside its parent, which is
+    /// the case for container, binary, file and 64-bit nodes.
+    pub(super) fn data_offset(&self) -> Option<u32> {
+        match self.node_type {
+            NodeType::Array
+            | NodeType::Map
+            | NodeType::HashMap
+            | NodeType::ValueHashMap
+            | NodeType::Binary
+            | NodeType::File
+            | NodeType::I64
+            | NodeType::U64
+            | NodeType::Double => Some(self.value),
+            _ => None,
+        }
+    }
+
+    /// The value stored after the size of file data.
+    pub(super) fn file_data_extra(&self) -> Option<u32> {
+        match self.node_type {
+            NodeType::File => self.doc.u32(self.value + 4).ok(),
+            _ => None,
+        }
+    }
+
+    fn type_error(&self, expected: &'static str) -> Error {
+        Error::TypeError(format!("{:?}", self.node_type).into(), expected)
+    }
+
+    /// Endianness of the document.
+    #[inline]
+    pub fn endian(&self) -> Endian {
+        self.doc.endian
+    }
+
+    /// Checks if the node is null.
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.node_type == NodeType::Null
+    }
+
+    /// Checks if the node is an array.
+    #[inline]
+    pub fn is_array(&self) -> bool {
+        self.node_type == NodeType::Array
+    }
+
+    /// Checks if the node is a map with string keys.
+    #[inline]
+    pub fn is_map(&self) -> bool {
+        self.node_type == NodeType::Map
+    }
+
+    /// Checks if the node is a map with u32 hash keys (with or without
+    /// additional values).
+    #[inline]
+    pub fn is_hash_map(&self) -> bool {
+        matches!(self.node_type, NodeType::HashMap | NodeType::ValueHashMap)
+    }
+
+    /// Number of children of a container node, or 0 for other nodes.
+    pub fn len(&self) -> usize {
+        if is_container_type(self.node_type) {
+            self.doc.u24(self.value + 1).unwrap_or(0) as usize
+        } else {
+            0
+        }
+    }
+
+    /// Checks if the node has no children.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get an item of an array node. Returns `None` if the node is not an
+    /// array or the index is out of bounds.
+    pub fn at(&self, index: usize) -> Option<Self> {
+        if !self.is_array() || index >= self.len() {
+            return None;
+        }
+        let len = self.len() as u32;
+        let index = index as u32;
+        let node_type = self.doc.u8(self.value + 4 + index).ok()?;
+        self.child(node_type, self.value + 4 + align(len, 4) + 4 * index)
+            .ok()
+    }
+
+    /// Iterate over the items of an array node (empty for other nodes).
+    pub fn iter(&self) -> impl Iterator<Item = Self> + '_ {
+        (0..if self.is_array() { self.len() } else { 0 }).filter_map(|i| self.at(i))
+    }
+
+    fn map_entry(&self, index: u32) -> Result<(&'a str, Self)> {
+        let entry = self.value + 4 + 8 * index;
+        let key = self
+            .doc
+            .table_str(self.doc.hash_key_table, self.doc.u24(entry)?)?;
+        Ok((key, self.child(self.doc.u8(entry + 3)?, entry + 4)?))
+    }
+
+    /// Look up a key in a map node. Returns `None` if the node is not a map or
+    /// the key is not found.
+    pub fn get(&self, key: &str) -> Option<Self> {
+        if !self.is_map() {
+            return None;
+        }
+        let key_index = self.doc.table_find(self.doc.hash_key_table, key).ok()??;
+        let (mut lo, mut hi) = (0, self.len() as u32);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let entry = self.value + 4 + 8 * mid;
+            match self.doc.u24(entry).ok()?.cmp(&key_index) {
+                std::cmp::Ordering::Equal => {
+                    return self.child(self.doc.u8(entry + 3).ok()?, entry + 4).ok();
+                }
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+            }
+        }
+        None
+    }
+
+    /// Iterate over the entries of a map node (empty for other nodes).
+    pub fn map_iter(&self) -> impl Iterator<Item = (&'a str, Self)> + '_ {
+        (0..if self.is_map() { self.len() as u32 } else { 0 })
+            .filter_map(|i| self.map_entry(i).ok())
+    }
+
+    fn hash_entry(&self, index: u32) -> Result<(u32, Self, Option<u32>)> {
+        let len = self.len() as u32;
+        match self.node_type {
+            NodeType::HashMap => {
+                let entry = self.value + 4 + 8 * index;
+                let node_type = self.doc.u8(self.value + 4 + 8 * len + index)?;
+                Ok((
+                    self.doc.u32(entry)?,
+                    self.child(node_type, entry + 4)?,
+                    None,
+                ))
+            }
+            NodeType::ValueHashMap => {
+                let entry = self.value + 4 + 12 * index;
+                let node_type = self.doc.u8(self.value + 4 + 12 * len + index)?;
+                Ok((
+                    self.doc.u32(entry + 4)?,
+                    self.child(node_type, entry)?,
+                    Some(self.doc.u32(entry + 8)?),
+                ))
+            }
+            _ => Err(self.type_error("HashMap or ValueHashMap")),
+        }
+    }
+
+    /// Look up a hash in a hash map node. Returns `None` if the node is not a
+    /// hash map or the hash is not found.
+    pub fn get_hash(&self, hash: u32) -> Option<Self> {
+        if !self.is_hash_map() {
+            return None;
+        }
+        let (mut lo, mut hi) = (0, self.len() as u32);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (entry_hash, node, _) = self.hash_entry(mid).ok()?;
+            match entry_hash.cmp(&hash) {
+                std::cmp::Ordering::Equal => return Some(node),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+            }
+        }
+        None
+    }
+
+    /// Iterate over the entries of a hash map node (empty for other nodes).
+    /// The third item is the additional value of value hash map entries.
+    pub fn hash_iter(&self) -> impl Iterator<Item = (u32, Self, Option<u32>)> + '_ {
+        (0..if self.is_hash_map() {
+            self.len() as u32
+        } else {
+            0
+        })
+            .filter_map(|i| self.hash_entry(i).ok())
+    }
+
+    /// Get a reference to the inner string value, borrowed from the
+    /// document's string table.
+    pub fn as_str(&self) -> Result<&'a str> {
+        match self.node_type {
+            NodeType::String => self.doc.table_str(self.doc.string_table, self.value),
+            _ => Err(self.type_error("String")),
+        }
+    }
+
+    /// Get a reference to the inner binary or file data, borrowed from the
+    /// document.
+    pub fn as_binary_data(&self) -> Result<&'a [u8]> {
+        match self.node_type {
+            NodeType::Binary | NodeType::File => {
+                let size = self.doc.u32(self.value)? as usize;
+                let start = self.value as usize
+                    + if self.node_type == NodeType::File {
+                        8
+                    } else {
+                        4
+                    };
+                self.doc
+                    .data
+                    .get(start..start + size)
+                    .ok_or(Error::InsufficientData(self.doc.data.len(), start + size))
+            }
+            _ => Err(self.type_error("Binary or File")),
+        }
+    }
+
+    /// Get the inner bool value.
+    pub fn as_bool(&self) -> Result<bool> {
+        match self.node_type {
+            NodeType::Bool => Ok(self.value != 0),
+            _ => Err(self.type_error("Bool")),
+        }
+    }
+
+    /// Get the inner i32 value.
+    pub fn as_i32(&self) -> Result<i32> {
+        match self.node_type {
+            NodeType::I32 => Ok(self.value as i32),
+            _ => Err(self.type_error("I32")),
+        }
+    }
+
+    /// Get the inner u32 value.
+    pub fn as_u32(&self) -> Result<u32> {
+        match self.node_type {
+            NodeType::U32 => Ok(self.value),
+            _ => Err(self.type_error("U32")),
+        }
+    }
+
+    /// Get the inner i64 value.
+    pub fn as_i64(&self) -> Result<i64> {
+        match self.node_type {
+            NodeType::I64 => Ok(self.doc.u64(self.value)? as i64),
+            _ => Err(self.type_error("I64")),
+        }
+    }
+
+    /// Get the inner u64 value.
+    pub fn as_u64(&self) -> Result<u64> {
+        match self.node_type {
+            NodeType::U64 => self.doc.u64(self.value),
+            _ => Err(self.type_error("U64")),
+        }
+    }
+
+    /// Get the inner f32 value.
+    pub fn as_float(&self) -> Result<f32> {
+        match self.node_type {
+            NodeType::Float => Ok(f32::from_bits(self.value)),
+            _ => Err(self.type_error("Float")),
+        }
+    }
+
+    /// Get the inner f64 value.
+    pub fn as_double(&self) -> Result<f64> {
+        match self.node_type {
+            NodeType::Double => Ok(f64::from_bits(self.doc.u64(self.value)?)),
+            _ => Err(self.type_error("Double")),
+        }
+    }
+
+    /// Parse the node and all of its children into an owned [`Byml`], with
+    /// the default [`ParseLimits`](super::ParseLimits).
+    pub fn to_owned(&self) -> Result<Byml> {
+        self.to_owned_guarded(&mut Guard::new(Default::default()), self.value)
+    }
+
+    /// Convert the node, counting value nodes at the offset of their parent.
+    fn to_owned_guarded(self, guard: &mut Guard, parent: u32) -> Result<Byml> {
+        if is_container_type(self.node_type) {
+            check_container(
+                self.doc.data.len() as u64,
+                self.value,
+                self.node_type,
+                self.len() as u32,
+            )?;
+            guard.enter(self.value)?;
+            let node = self.container_to_owned(guard);
+            guard.exit();
+            return node;
+        }
+        guard.node(parent)?;
+        Ok(match self.node_type {
+            NodeType::String => Byml::String(self.as_str()?.into()),
+            NodeType::Binary => Byml::BinaryData(self.as_binary_data()?.to_vec()),
+            NodeType::File => Byml::FileData(self.as_binary_data()?.to_vec()),
+            NodeType::Bool => Byml::Bool(self.as_bool()?),
+            NodeType::I32 => Byml::I32(self.as_i32()?),
+            NodeType::U32 => Byml::U32(self.as_u32()?),
+            NodeType::Float => Byml::Float(self.as_float()?),
+            NodeType::I64 => Byml::I64(self.as_i64()?),
+            NodeType::U64 => Byml::U64(self.as_u64()?),
+            NodeType::Double => Byml::Double(self.as_double()?),
+            NodeType::Null => Byml::Null,
+            _ => return Err(self.type_error("value or container")),
+        })
+    }
+
+    fn container_to_owned(&self, guard: &mut Guard) -> Result<Byml> {
+        Ok(match self.node_type {
+            NodeType::Array => {
+                Byml::Array(
+                    (0..self.len())
+                        .map(|i| {
+                            self.at(i)
+                                .ok_or(Error::InvalidData("Invalid BYML array item"))?
+                                .to_owned_guarded(guard, self.value)
+                        })
+                        .collect::<Result<_>>()?,
+                )
+            }
+            NodeType::Map => {
+                Byml::Map(
+                    (0..self.len() as u32)
+                        .map(|i| {
+                            let (key, node) = self.map_entry(i)?;
+                            Ok((key.into(), node.to_owned_guarded(guard, self.value)?))
+                        })
+                        .collect::<Result<_>>()?,
+                )
+            }
+            NodeType::HashMap => {
+                Byml::HashMap(
+                    (0..self.len() as u32)
+                        .map(|i| {
+                            let (hash, node, _) = self.hash_entry(i)?;
+                            Ok((hash, node.to_owned_guarded(guard, self.value)?))
+                        })
+                        .collect::<Result<_>>()?,
+                )
+            }
+            NodeType::ValueHashMap => {
+                Byml::ValueHashMap(
+                    (0..self.len() as u32)
+                        .map(|i| {
+                            let (hash, node, extra) = self.hash_entry(i)?;
+                            Ok((
+                                hash,
+                                (
+                                    node.to_owned_guarded(guard, self.value)?,
+                                    extra.unwrap_or_default(),
+                                ),
+                            ))
+                        })
+                        .collect::<Result<_>>()?,
+                )
+            }
+            _ => return Err(self.type_error("container")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actor_info_view() {
+        let data = std::fs::read("test/byml/ActorInfo.product.byml").unwrap();
+        let view = BymlRef::new(&data).unwrap();
+        let byml = Byml::from_binary(&data).unwrap();
+        assert_eq!(
+            view.get("Hashes").unwrap().at(0).unwrap().as_i32().unwrap(),
+            31119
+        );
+        let actors = view.get("Actors").unwrap();
+        assert_eq!(actors.len(), 7934);
+        assert!(view.get("Nonexistent").is_none());
+        let actor = actors.at(100).unwrap();
+        assert_eq!(
+            actor.get("name").unwrap().as_str().unwrap(),
+            byml["Actors"][100]["name"].as_string().unwrap().as_str()
+        );
+        assert_eq!(actor.to_owned().unwrap(), byml["Actors"][100]);
+        for (key, value) in actor.map_iter() {
+            assert_eq!(value.to_owned().unwrap(), byml["Actors"][100][key]);
+        }
+    }
+
+    #[test]
+    fn hash_map_view() {
+        let byml = Byml::Array(vec![
+            Byml::HashMap(
+                [(3, Byml::I32(1)), (1, Byml::String("a".into()))]
+                    .into_iter()
+                    .collect(),
+            ),
+            Byml::ValueHashMap([(5, (Byml::Double(0.5), 9))].into_iter().collect()),
+            Byml::BinaryData(vec![1, 2, 3]),
+            Byml::U64(u64::MAX),
+        ]);
+        for endian in [Endian::Big, Endian::Little] {
+            let data = byml.to_binary_with_version(endian, 7);
+            let view = BymlRef::new(&data).unwrap();
+            assert_eq!(view.to_owned().unwrap(), byml);
+            let hash_map = view.at(0).unwrap();
+            assert_eq!(hash_map.get_hash(1).unwrap().as_str().unwrap(), "a");
+            assert_eq!(hash_map.get_hash(3).unwrap().as_i32().unwrap(), 1);
+            assert!(hash_map.get_hash(2).is_none());
+            let (hash, node, extra) = view.at(1).unwrap().hash_iter().next().unwrap();
+            assert_eq!((hash, node.as_double().unwrap(), extra), (5, 0.5, Some(9)));
+            assert_eq!(view.at(2).unwrap().as_binary_data().unwrap(), &[1, 2, 3]);
+            assert_eq!(view.at(3).unwrap().as_u64().unwrap(), u64::MAX);
+            assert!(view.at(4).is_none());
+        }
+    }
+}
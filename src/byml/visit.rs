@@ -0,0 +1,294 @@
+use super::*;
+
+/// A visitor for walking a [`Byml`] document with [`Byml::visit`].
+///
+/// Each node is passed with its path from the root, as indices which can be
+/// given to [`Byml::get_path`]. The root node has an empty path. The children
+/// of hash nodes are visited in the iteration order of their map.
+///
+/// Closures taking `(&[BymlIndex], &Byml)` implement this trait:
+/// ```
+/// # use roead::byml::{Byml, BymlIndex};
+/// # fn doctest() -> Result<(), Box<dyn std::error::Error>> {
+/// let actor_info = Byml::from_binary(std::fs::read("test/byml/ActorInfo.product.byml")?)?;
+/// let mut floats = 0;
+/// actor_info.visit(&mut |_: &[BymlIndex], node: &Byml| {
+///     if let Byml::Float(_) = node {
+///         floats += 1;
+///     }
+/// });
+/// assert!(floats > 0);
+/// # Ok(())
+/// # }
+/// ```
+pub trait Visit {
+    /// Called for each node before its children. Return `false` to skip the
+    /// children of a container node.
+    fn enter(&mut self, path: &[BymlIndex<'_>], node: &Byml) -> bool;
+
+    /// Called for each container node after its children, if they were not
+    /// skipped.
+    fn leave(&mut self, _path: &[BymlIndex<'_>], _node: &Byml) {}
+}
+
+/// An owned [`BymlIndex`], used for the paths passed to a [`VisitMut`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OwnedBymlIndex {
+    /// Index into a hash node. The key is a string.
+    StringIdx(String),
+    /// Index into a hash node. The key is a u32 hash.
+    HashIdx(u32),
+    /// Index into an array node. The index is an integer.
+    ArrayIdx(usize),
+}
+
+impl OwnedBymlIndex {
+    /// Borrow this index as a [`BymlIndex`].
+    pub fn as_index(&self) -> BymlIndex<'_> {
+        match self {
+            Self::StringIdx(key) => BymlIndex::StringIdx(key),
+            Self::HashIdx(hash) => BymlIndex::HashIdx(*hash),
+            Self::ArrayIdx(i) => BymlIndex::ArrayIdx(*i),
+        }
+    }
+}
+
+impl From<BymlIndex<'_>> for OwnedBymlIndex {
+    fn from(index: BymlIndex<'_>) -> Self {
+        match index {
+            BymlIndex::StringIdx(key) => Self::StringIdx(key.into()),
+            BymlIndex::HashIdx(hash) => Self::HashIdx(hash),
+            BymlIndex::ArrayIdx(i) => Self::ArrayIdx(i),
+        }
+    }
+}
+
+impl PartialEq<BymlIndex<'_>> for OwnedBymlIndex {
+    fn eq(&self, other: &BymlIndex<'_>) -> bool {
+        self.as_index() == *other
+    }
+}
+
+/// A visitor for modifying a [`Byml`] document in place with
+/// [`Byml::visit_mut`].
+///
+/// This works like [`Visit`], but `enter` may change or replace the node.
+/// If it does, the children of the new node are visited. Paths are given as
+/// [`OwnedBymlIndex`]es, since the keys of a node cannot be borrowed while it
+/// is being modified. Closures taking `(&[OwnedBymlIndex], &mut Byml)`
+/// implement this trait:
+/// ```
+/// # use roead::byml::{Byml, OwnedBymlIndex};
+/// let mut doc = Byml::Array(vec![Byml::Float(1.25), Byml::Array(vec![Byml::Float(2.75)])]);
+/// doc.visit_mut(&mut |_: &[OwnedBymlIndex], node: &mut Byml| {
+///     if let Byml::Float(f) = node {
+///         *f = f.round();
+///     }
+/// });
+/// assert_eq!(doc[1][0], Byml::Float(3.0));
+/// ```
+pub trait VisitMut {
+    /// Called for each node before its children. Return `false` to skip the
+    /// children of a container node.
+    fn enter(&mut self, path: &[OwnedBymlIndex], node: &mut Byml) -> bool;
+
+    /// Called for each container node after its children, if they were not
+    /// skipped.
+    fn leave(&mut self, _path: &[OwnedBymlIndex], _node: &mut Byml) {}
+}
+
+impl<F: FnMut(&[BymlIndex<'_>], &Byml)> Visit for F {
+    fn enter(&mut self, path: &[BymlIndex<'_>], node: &Byml) -> bool {
+        self(path, node);
+        true
+    }
+}
+
+impl<F: FnMut(&[OwnedBymlIndex], &mut Byml)> VisitMut for F {
+    fn enter(&mut self, path: &[OwnedBymlIndex], node: &mut Byml) -> bool {
+        self(path, node);
+        true
+    }
+}
+
+fn is_container(node: &Byml) -> bool {
+    matches!(
+        node,
+        Byml::Array(_) | Byml::Map(_) | Byml::HashMap(_) | Byml::ValueHashMap(_)
+    )
+}
+
+fn walk<'a, V: Visit + ?Sized>(node: &'a Byml, path: &mut Vec<BymlIndex<'a>>, visitor: &mut V) {
+    if !visitor.enter(path, node) || !is_container(node) {
+        return;
+    }
+    let mut child = |index, node, path: &mut Vec<BymlIndex<'a>>| {
+        path.push(index);
+        walk(node, path, visitor);
+        path.pop();
+    };
+    match node {
+        Byml::Array(array) => {
+            for (i, item) in array.iter().enumerate() {
+                child(BymlIndex::ArrayIdx(i), item, path);
+            }
+        }
+        Byml::Map(map) => {
+            for (key, value) in map.iter() {
+                child(BymlIndex::StringIdx(key), value, path);
+            }
+        }
+        Byml::HashMap(map) => {
+            for (hash, value) in map.iter() {
+                child(BymlIndex::HashIdx(*hash), value, path);
+            }
+        }
+        Byml::ValueHashMap(map) => {
+            for (hash, (value, _)) in map.iter() {
+                child(BymlIndex::HashIdx(*hash), value, path);
+            }
+        }
+        _ => unreachable!(),
+    }
+    visitor.leave(path, node);
+}
+
+fn walk_mut<V: VisitMut + ?Sized>(
+    node: &mut Byml,
+    path: &mut Vec<OwnedBymlIndex>,
+    visitor: &mut V,
+) {
+    if !visitor.enter(path, node) || !is_container(node) {
+        return;
+    }
+    let mut child = |index, node, path: &mut Vec<OwnedBymlIndex>| {
+        path.push(index);
+        walk_mut(node, path, visitor);
+        path.pop();
+    };
+    match node {
+        Byml::Array(array) => {
+            for (i, item) in array.iter_mut().enumerate() {
+                child(OwnedBymlIndex::ArrayIdx(i), item, path);
+            }
+        }
+        Byml::Map(map) => {
+            for (key, value) in map.iter_mut() {
+                child(OwnedBymlIndex::StringIdx(key.clone()), value, path);
+            }
+        }
+        Byml::HashMap(map) => {
+            for (hash, value) in map.iter_mut() {
+                child(OwnedBymlIndex::HashIdx(*hash), value, path);
+            }
+        }
+        Byml::ValueHashMap(map) => {
+            for (hash, (value, _)) in map.iter_mut() {
+                child(OwnedBymlIndex::HashIdx(*hash), value, path);
+            }
+        }
+        _ => unreachable!(),
+    }
+    visitor.leave(path, node);
+}
+
+impl Byml {
+    /// Walk this node and all its descendants, parents before children. See
+    /// [`Visit`].
+    pub fn visit<V: Visit + ?Sized>(&self, visitor: &mut V) {
+        walk(self, &mut Vec::new(), visitor);
+    }
+
+    /// Walk this node and all its descendants mutably, parents before
+    /// children. See [`VisitMut`].
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        walk_mut(self, &mut Vec::new(), visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Stats {
+        nodes:      usize,
+        max_depth:  usize,
+        containers: usize,
+    }
+
+    impl Visit for Stats {
+        fn enter(&mut self, path: &[BymlIndex<'_>], _node: &Byml) -> bool {
+            self.nodes += 1;
+            self.max_depth = self.max_depth.max(path.len());
+            true
+        }
+
+        fn leave(&mut self, _path: &[BymlIndex<'_>], _node: &Byml) {
+            self.containers += 1;
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let doc = crate::byml!({
+            "Actors": [{"name": "A"}, {"name": "B"}],
+            "Hashes": !h {7: 1},
+            "Values": !vh {(8, 9): 2},
+        });
+        let mut seen = Vec::new();
+        doc.visit(&mut |path: &[BymlIndex], node: &Byml| {
+            assert_eq!(doc.get_path(path.iter().copied()), Some(node));
+            seen.push(path.len());
+        });
+        assert_eq!(seen.len(), 10);
+
+        let mut stats = Stats::default();
+        doc.visit(&mut stats);
+        assert_eq!(stats.nodes, 10);
+        assert_eq!(stats.max_depth, 3);
+        // The root, the array, its two maps and both hash maps.
+        assert_eq!(stats.containers, 6);
+
+        let mut copy = doc.clone();
+        let mut count = 0;
+        copy.visit_mut(&mut |path: &[OwnedBymlIndex], node: &mut Byml| {
+            assert_eq!(doc.get_path(path.iter().map(OwnedBymlIndex::as_index)), Some(&*node));
+            count += 1;
+        });
+        assert_eq!(count, 10);
+    }
+
+    #[test]
+    fn rename() {
+        struct Rename;
+        impl VisitMut for Rename {
+            fn enter(&mut self, path: &[OwnedBymlIndex], node: &mut Byml) -> bool {
+                if path.last().is_some_and(|index| *index == BymlIndex::StringIdx("name"))
+                    && node.as_string().map(|s| s == "Enemy_Moriblin_Junior").unwrap_or(false)
+                {
+                    *node = "Enemy_Bokoblin".into();
+                }
+                // Skip anything nested in the actor entries.
+                path.len() < 3
+            }
+        }
+
+        let mut doc =
+            Byml::from_binary(std::fs::read("test/byml/ActorInfo.product.byml").unwrap()).unwrap();
+        let count = |doc: &Byml, name: &str| {
+            let mut count = 0;
+            doc.visit(&mut |_: &[BymlIndex], node: &Byml| {
+                if node.as_string().map(|s| s == name).unwrap_or(false) {
+                    count += 1;
+                }
+            });
+            count
+        };
+        let before = count(&doc, "Enemy_Moriblin_Junior");
+        assert!(before > 0);
+        doc.visit_mut(&mut Rename);
+        assert_eq!(count(&doc, "Enemy_Moriblin_Junior"), 0);
+        assert!(count(&doc, "Enemy_Bokoblin") >= before);
+    }
+}
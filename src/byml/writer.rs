@@ -1,11 +1,13 @@
 use std::{
     collections::BTreeMap,
+    hash::BuildHasherDefault,
     io::{Cursor, Seek, SeekFrom, Write},
+    ops::Deref,
     rc::Rc,
 };
 
 use binrw::prelude::*;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHasher};
 
 use super::*;
 use crate::{
@@ -23,7 +25,7 @@ impl Byml {
         version: u16,
     ) -> Result<()> {
         if !is_valid_version(version) {
-            return Err(Error::InvalidData("Unsupported BYML version (2-4 only)"));
+            return Err(Error::InvalidData("Unsupported BYML version (1-7 only)"));
         }
 
         if !matches!(
@@ -36,39 +38,7 @@ impl Byml {
             ));
         }
 
-        let mut ctx = WriteContext::new(self, writer, endian);
-        ctx.write(match endian {
-            Endian::Little => b"YB",
-            Endian::Big => b"BY",
-        })?;
-        ctx.write(version)?;
-        ctx.write(0u32)?; // Hash key table offset
-        ctx.write(0u32)?; // String table offset
-        ctx.write(0u32)?; // Root node offset
-
-        if let &Byml::Null = self {
-            Ok(())
-        } else {
-            if !ctx.hash_key_table.is_empty() {
-                let pos = ctx.writer.stream_position()? as u32;
-                ctx.write_at(pos, 0x4)?;
-                ctx.write_string_table(ctx.hash_key_table.clone())?;
-            }
-
-            if !ctx.string_table.is_empty() {
-                let pos = ctx.writer.stream_position()? as u32;
-                ctx.write_at(pos, 0x8)?;
-                ctx.write_string_table(ctx.string_table.clone())?;
-            }
-
-            let pos = ctx.writer.stream_position()? as u32;
-            ctx.write_at(pos, 0xC)?;
-            ctx.align()?;
-            ctx.write_container_node(self)?;
-            ctx.align()?;
-            ctx.writer.flush()?;
-            Ok(())
-        }
+        write_document(self, writer, endian, version)
     }
 
     /// Serialize the document to bytes with the specified endianness and
@@ -89,27 +59,196 @@ impl Byml {
             .expect("BYML should serialize to binary without error");
         buf
     }
+
+    /// Serialize the document to BYML with the specified endianness and
+    /// version number, returning an error instead of writing nodes which the
+    /// version does not support (see [`Byml::validate_for_version`]).
+    pub fn to_binary_checked(&self, endian: Endian, version: u16) -> Result<Vec<u8>> {
+        self.ensure_version(version)?;
+        let mut buf = Vec::new();
+        self.write(&mut Cursor::new(&mut buf), endian, version)?;
+        Ok(buf)
+    }
 }
 
-struct NonInlineNode<'a> {
-    data:   &'a Byml,
+/// Write a document whose version and root node type have already been
+/// checked.
+pub(super) fn write_document<N: WriteNode, W: Write + Seek>(
+    root: &N,
+    writer: &mut W,
+    endian: Endian,
+    version: u16,
+) -> Result<()> {
+    let mut ctx = WriteContext::new(root, writer, endian);
+    ctx.write(match endian {
+        Endian::Little => b"YB",
+        Endian::Big => b"BY",
+    })?;
+    ctx.write(version)?;
+    ctx.write(0u32)?; // Hash key table offset
+    ctx.write(0u32)?; // String table offset
+    ctx.write(0u32)?; // Root node offset
+
+    if let Node::Null = root.node() {
+        Ok(())
+    } else {
+        if !ctx.hash_key_table.is_empty() {
+            let pos = ctx.writer.stream_position()? as u32;
+            ctx.write_at(pos, 0x4)?;
+            ctx.write_string_table(ctx.hash_key_table.clone())?;
+        }
+
+        if !ctx.string_table.is_empty() {
+            let pos = ctx.writer.stream_position()? as u32;
+            ctx.write_at(pos, 0x8)?;
+            ctx.write_string_table(ctx.string_table.clone())?;
+        }
+
+        let pos = ctx.writer.stream_position()? as u32;
+        ctx.write_at(pos, 0xC)?;
+        ctx.align()?;
+        ctx.write_container_node(root)?;
+        ctx.align()?;
+        ctx.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A borrowed view of a node, so that [`Byml`] and
+/// [`SharedByml`](super::SharedByml) documents share one writer.
+pub(super) enum Node<'a, N: WriteNode> {
+    String(&'a N::Str),
+    BinaryData(&'a [u8]),
+    FileData(&'a [u8]),
+    Array(&'a [N]),
+    Map(&'a indexmap::IndexMap<N::Str, N, BuildHasherDefault<FxHasher>>),
+    HashMap(&'a FxHashMap<u32, N>),
+    ValueHashMap(&'a FxHashMap<u32, (N, u32)>),
+    Bool(bool),
+    I32(i32),
+    Float(f32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    Double(f64),
+    Null,
+}
+
+impl<N: WriteNode> Node<'_, N> {
+    fn node_type(&self) -> NodeType {
+        match self {
+            Node::String(_) => NodeType::String,
+            Node::BinaryData(_) => NodeType::Binary,
+            Node::FileData(_) => NodeType::File,
+            Node::Array(_) => NodeType::Array,
+            Node::Map(_) => NodeType::Map,
+            Node::HashMap(_) => NodeType::HashMap,
+            Node::ValueHashMap(_) => NodeType::ValueHashMap,
+            Node::Bool(_) => NodeType::Bool,
+            Node::I32(_) => NodeType::I32,
+            Node::Float(_) => NodeType::Float,
+            Node::U32(_) => NodeType::U32,
+            Node::I64(_) => NodeType::I64,
+            Node::U64(_) => NodeType::U64,
+            Node::Double(_) => NodeType::Double,
+            Node::Null => NodeType::Null,
+        }
+    }
+
+    #[inline(always)]
+    fn is_non_inline_type(&self) -> bool {
+        matches!(
+            self,
+            Node::Array(_)
+                | Node::Map(_)
+                | Node::HashMap(_)
+                | Node::ValueHashMap(_)
+                | Node::BinaryData(_)
+                | Node::FileData(_)
+                | Node::I64(_)
+                | Node::U64(_)
+                | Node::Double(_)
+        )
+    }
+}
+
+/// A document node which can be written to binary.
+pub(super) trait WriteNode: PartialEq + std::hash::Hash + Sized {
+    /// The type of strings and map keys.
+    type Str: Ord + std::hash::Hash + Deref<Target = str>;
+
+    fn node(&self) -> Node<'_, Self>;
+}
+
+impl WriteNode for Byml {
+    type Str = String;
+
+    fn node(&self) -> Node<'_, Self> {
+        match self {
+            Byml::String(s) => Node::String(s),
+            Byml::BinaryData(data) => Node::BinaryData(data),
+            Byml::FileData(data) => Node::FileData(data),
+            Byml::Array(array) => Node::Array(array),
+            Byml::Map(map) => Node::Map(map),
+            Byml::HashMap(map) => Node::HashMap(map),
+            Byml::ValueHashMap(map) => Node::ValueHashMap(map),
+            Byml::Bool(v) => Node::Bool(*v),
+            Byml::I32(v) => Node::I32(*v),
+            Byml::Float(v) => Node::Float(*v),
+            Byml::U32(v) => Node::U32(*v),
+            Byml::I64(v) => Node::I64(*v),
+            Byml::U64(v) => Node::U64(*v),
+            Byml::Double(v) => Node::Double(*v),
+            Byml::Null => Node::Null,
+        }
+    }
+}
+
+/// Key for deduplicating written nodes by equality.
+struct NodeKey<'a, N>(&'a N);
+
+impl<N: PartialEq> PartialEq for NodeKey<'_, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<N: PartialEq> Eq for NodeKey<'_, N> {}
+
+impl<N: std::hash::Hash> std::hash::Hash for NodeKey<'_, N> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+struct NonInlineNode<'a, N> {
+    data:   &'a N,
     offset: u32,
 }
 
-#[derive(Debug, Default)]
-struct StringTable<'a> {
-    table: FxHashMap<&'a String, u32>,
-    sorted_strings: Vec<&'a String>,
+#[derive(Debug)]
+struct StringTable<'a, S> {
+    table: FxHashMap<&'a S, u32>,
+    sorted_strings: Vec<&'a S>,
+}
+
+impl<S> Default for StringTable<'_, S> {
+    fn default() -> Self {
+        Self {
+            table: Default::default(),
+            sorted_strings: Default::default(),
+        }
+    }
 }
 
-impl<'a> StringTable<'a> {
+impl<'a, S: Ord + std::hash::Hash> StringTable<'a, S> {
     #[inline]
-    fn add<'b>(&'b mut self, s: &'a String) {
+    fn add<'b>(&'b mut self, s: &'a S) {
         self.table.insert(s, 0);
     }
 
     #[inline]
-    fn get_index(&self, s: &String) -> u32 {
+    fn get_index(&self, s: &S) -> u32 {
         unsafe { self.table.get(s).copied().unwrap_unchecked() }
     }
 
@@ -135,55 +274,55 @@ impl<'a> StringTable<'a> {
     }
 }
 
-struct WriteContext<'a, W: Write + Seek> {
+struct WriteContext<'a, N: WriteNode, W: Write + Seek> {
     writer: W,
     endian: binrw::Endian,
-    hash_key_table: Rc<StringTable<'a>>,
-    string_table: Rc<StringTable<'a>>,
-    non_inline_node_data: FxHashMap<&'a Byml, u32>,
+    hash_key_table: Rc<StringTable<'a, N::Str>>,
+    string_table: Rc<StringTable<'a, N::Str>>,
+    non_inline_node_data: FxHashMap<NodeKey<'a, N>, u32>,
 }
 
-impl<'a, W: Write + Seek> WriteContext<'a, W> {
-    fn new(byml: &'a Byml, writer: W, endian: Endian) -> Self {
+impl<'a, N: WriteNode, W: Write + Seek> WriteContext<'a, N, W> {
+    fn new(byml: &'a N, writer: W, endian: Endian) -> Self {
         let mut non_inline_node_count = 0;
         let mut string_table = StringTable::default();
         let mut hash_key_table = StringTable::default();
-        fn traverse<'a>(
-            byml: &'a Byml,
+        fn traverse<'a, N: WriteNode>(
+            byml: &'a N,
             count: &mut usize,
-            string_table: &mut StringTable<'a>,
-            hash_key_table: &mut StringTable<'a>,
+            string_table: &mut StringTable<'a, N::Str>,
+            hash_key_table: &mut StringTable<'a, N::Str>,
         ) {
-            match byml {
-                Byml::String(s) => {
+            match byml.node() {
+                Node::String(s) => {
                     string_table.add(s);
                 }
-                Byml::Array(arr) => {
+                Node::Array(arr) => {
                     for node in arr.iter() {
                         traverse(node, count, string_table, hash_key_table);
                     }
                 }
-                Byml::Map(hash) => {
+                Node::Map(hash) => {
                     for (key, node) in hash.iter() {
                         hash_key_table.add(key);
                         traverse(node, count, string_table, hash_key_table);
                     }
                 }
-                Byml::HashMap(hash) => {
+                Node::HashMap(hash) => {
                     for node in hash.values() {
                         traverse(node, count, string_table, hash_key_table);
                     }
                 }
-                Byml::ValueHashMap(hash) => {
+                Node::ValueHashMap(hash) => {
                     for (node, _) in hash.values() {
                         traverse(node, count, string_table, hash_key_table);
                     }
                 }
-                Byml::BinaryData(_)
-                | Byml::FileData(_)
-                | Byml::I64(_)
-                | Byml::U64(_)
-                | Byml::Double(_) => {}
+                Node::BinaryData(_)
+                | Node::FileData(_)
+                | Node::I64(_)
+                | Node::U64(_)
+                | Node::Double(_) => {}
                 _ => return,
             }
             *count += 1;
@@ -237,26 +376,26 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
         Ok(())
     }
 
-    fn write_value_node(&mut self, node: &Byml) -> binrw::BinResult<()> {
-        match node {
-            Byml::Null => self.write(0u32),
-            Byml::String(s) => self.write(self.string_table.get_index(s)),
-            Byml::BinaryData(data) => {
+    fn write_value_node(&mut self, node: &N) -> binrw::BinResult<()> {
+        match node.node() {
+            Node::Null => self.write(0u32),
+            Node::String(s) => self.write(self.string_table.get_index(s)),
+            Node::BinaryData(data) => {
                 self.write(data.len() as u32)?;
                 self.write(data)
             }
-            Byml::FileData(data) => {
+            Node::FileData(data) => {
                 self.write(data.len() as u32)?;
                 self.write(0x1000u32)?; // unknown
                 self.write(data)
             }
-            Byml::Bool(b) => self.write(*b as u32),
-            Byml::I32(i) => self.write(*i),
-            Byml::U32(u) => self.write(*u),
-            Byml::Float(f) => self.write(f.to_bits()),
-            Byml::I64(i) => self.write(*i),
-            Byml::U64(u) => self.write(*u),
-            Byml::Double(d) => self.write(d.to_bits()),
+            Node::Bool(b) => self.write(b as u32),
+            Node::I32(i) => self.write(i),
+            Node::U32(u) => self.write(u),
+            Node::Float(f) => self.write(f.to_bits()),
+            Node::I64(i) => self.write(i),
+            Node::U64(u) => self.write(u),
+            Node::Double(d) => self.write(d.to_bits()),
             _ => {
                 BinResult::Err(binrw::Error::Custom {
                     pos: self.writer.stream_position()?,
@@ -266,16 +405,16 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
         }
     }
 
-    fn write_container_node<'b>(&'b mut self, node: &'a Byml) -> binrw::BinResult<()> {
+    fn write_container_node<'b>(&'b mut self, node: &'a N) -> binrw::BinResult<()> {
         let mut non_inline_nodes = Vec::new();
 
         #[inline]
-        fn write_container_item<'parent, W: Write + Seek>(
-            ctx: &mut WriteContext<'parent, W>,
-            item: &'parent Byml,
-            non_inline_nodes: &mut Vec<NonInlineNode<'parent>>,
+        fn write_container_item<'parent, N: WriteNode, W: Write + Seek>(
+            ctx: &mut WriteContext<'parent, N, W>,
+            item: &'parent N,
+            non_inline_nodes: &mut Vec<NonInlineNode<'parent, N>>,
         ) -> binrw::BinResult<()> {
-            if item.is_non_inline_type() {
+            if item.node().is_non_inline_type() {
                 non_inline_nodes.push(NonInlineNode {
                     data:   item,
                     offset: ctx.writer.stream_position()? as u32,
@@ -287,8 +426,8 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
             Ok(())
         }
 
-        match node {
-            Byml::Array(arr) => {
+        match node.node() {
+            Node::Array(arr) => {
                 non_inline_nodes.reserve(arr.len());
                 self.write(NodeType::Array)?;
                 self.write(u24(arr.len() as u32))?;
@@ -296,22 +435,22 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
                 self.writer.seek(SeekFrom::Current(arr.len() as i64))?;
                 self.align()?;
                 for (i, item) in arr.iter().enumerate() {
-                    self.write_at(item.get_node_type(), types_pos + i as u32)?;
+                    self.write_at(item.node().node_type(), types_pos + i as u32)?;
                     write_container_item(self, item, &mut non_inline_nodes)?;
                 }
             }
-            Byml::Map(map) => {
+            Node::Map(map) => {
                 non_inline_nodes.reserve(map.len());
                 self.write(NodeType::Map)?;
                 self.write(u24(map.len() as u32))?;
                 let sorted = map.iter().collect::<BTreeMap<_, _>>();
                 for (key, item) in sorted {
                     self.write(u24(self.hash_key_table.get_index(key)))?;
-                    self.write(item.get_node_type())?;
+                    self.write(item.node().node_type())?;
                     write_container_item(self, item, &mut non_inline_nodes)?;
                 }
             }
-            Byml::HashMap(hash) => {
+            Node::HashMap(hash) => {
                 non_inline_nodes.reserve(hash.len());
                 self.write(NodeType::HashMap)?;
                 self.write(u24(hash.len() as u32))?;
@@ -321,11 +460,11 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
                     write_container_item(self, item, &mut non_inline_nodes)?;
                 }
                 for item in sorted.values() {
-                    self.write(item.get_node_type())?;
+                    self.write(item.node().node_type())?;
                 }
                 self.align()?;
             }
-            Byml::ValueHashMap(hash) => {
+            Node::ValueHashMap(hash) => {
                 non_inline_nodes.reserve(hash.len());
                 self.write(NodeType::ValueHashMap)?;
                 self.write(u24(hash.len() as u32))?;
@@ -336,7 +475,7 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
                     self.write(unknown)?;
                 }
                 for (item, _) in sorted.values() {
-                    self.write(item.get_node_type())?;
+                    self.write(item.node().node_type())?;
                 }
                 self.align()?;
             }
@@ -350,17 +489,16 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
 
         for node in non_inline_nodes {
             self.align()?;
-            if let Some(pos) = self.non_inline_node_data.get(&node.data).copied() {
+            if let Some(pos) = self.non_inline_node_data.get(&NodeKey(node.data)).copied() {
                 self.write_at(pos, node.offset)?;
             } else {
                 let offset = self.writer.stream_position()? as u32;
                 self.write_at(offset, node.offset)?;
-                self.non_inline_node_data.insert(node.data, offset);
-                match node.data {
-                    Byml::Array(_) | Byml::Map(_) | Byml::HashMap(_) | Byml::ValueHashMap(_) => {
-                        self.write_container_node(node.data)?
-                    }
-                    _ => self.write_value_node(node.data)?,
+                self.non_inline_node_data.insert(NodeKey(node.data), offset);
+                if is_container_type(node.data.node().node_type()) {
+                    self.write_container_node(node.data)?
+                } else {
+                    self.write_value_node(node.data)?
                 }
             }
         }
@@ -368,7 +506,7 @@ impl<'a, W: Write + Seek> WriteContext<'a, W> {
         Ok(())
     }
 
-    fn write_string_table(&mut self, table: Rc<StringTable<'_>>) -> binrw::BinResult<()> {
+    fn write_string_table(&mut self, table: Rc<StringTable<'_, N::Str>>) -> binrw::BinResult<()> {
         let start = self.writer.stream_position()? as u32;
         self.write(NodeType::StringTable)?;
         self.write(u24(table.len() as u32))?;
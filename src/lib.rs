@@ -15,6 +15,8 @@
 //! * [AAMP](https://zeldamods.org/wiki/AAMP) (binary parameter archive): Only version 2 is
 //!   supported.
 //! * [BYML](https://zeldamods.org/wiki/BYML) (binary YAML): Versions 2, 3, and 4 are supported.
+//! * [RSTB](https://zeldamods.org/wiki/ResourceSizeTable.product.rsizetable) (resource size table),
+//!   including the TOTK RESTBL variant
 //! * [SARC](https://zeldamods.org/wiki/SARC) (archive)
 //! * [Yaz0](https://zeldamods.org/wiki/Yaz0) (compression algorithm)
 //!
@@ -24,11 +26,20 @@
 //! itself, visit [its GitHub repo](https://github.com/zeldamods/oead/).
 //!
 //! Each of roead's major modules is configurable as a feature. The default
-//! feature set includes `byml`, `aamp`, `sarc,` and `yaz0`. For compatibility
+//! feature set includes `byml`, `aamp`, `rstb`, `sarc,` and `yaz0`. For compatibility
 //! with many existing tools for these formats, there is also a `yaml` feature
 //! which enables serializing/deserializing AAMP and BYML files as YAML
-//! documents. Finally, serde support is available using the `with-serde`
-//! feature.
+//! documents, and a `json` feature for converting BYML documents to and from
+//! JSON. Serde support is available using the `with-serde` feature.
+//! Finally, the `rayon` feature enables parallel extraction and compression
+//! for SARC archives.
+//!
+//! Writer defaults for specific games and platforms (endianness, BYML
+//! version, SARC alignment and so on) can be set up once with a
+//! [`profile::Profile`].
+//!
+//! The `botw` module (enabled with `byml`) provides typed wrappers for
+//! *Breath of the Wild* game data files, such as the actor info table.
 //!
 //! For API documentation, see the docs for each module.
 //!
@@ -56,7 +67,12 @@
 #[cfg(feature = "aamp")]
 pub mod aamp;
 #[cfg(feature = "byml")]
+pub mod botw;
+#[cfg(feature = "byml")]
 pub mod byml;
+pub mod profile;
+#[cfg(feature = "rstb")]
+pub mod rstb;
 #[cfg(feature = "sarc")]
 pub mod sarc;
 pub mod types;
@@ -78,6 +94,8 @@ pub enum Error {
     InvalidData(&'static str),
     #[error("{0}")]
     InvalidDataD(String),
+    #[error("{1} (at offset {0:#x})")]
+    InvalidDataAt(usize, String),
     #[error("Found {0}, expected {1}")]
     TypeError(smartstring::alias::String, &'static str),
     #[error(transparent)]
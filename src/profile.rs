@@ -0,0 +1,237 @@
+//! Game and platform profiles for writer defaults.
+//!
+//! Several writers in roead default to what *Breath of the Wild* expects: BYML
+//! version 2, BOTW's SARC alignment tables, and AAMP name tables with BOTW
+//! strings. A [`Profile`] bundles these settings for one game and platform so
+//! that a tool can target several games without repeating the configuration
+//! at every call site.
+//!
+//! Sample usage:
+//! ```
+//! # use roead::{profile::Profile, Endian};
+//! let profile = Profile::TOTK;
+//! assert_eq!(profile.endian, Endian::Little);
+//! assert_eq!(profile.byml_version, 7);
+//!
+//! let custom = Profile::custom(Endian::Big).with_byml_version(3);
+//! assert_eq!(custom.byml_version, 3);
+//! ```
+use crate::Endian;
+
+/// The compression codec a game uses for its resource files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Files are not compressed.
+    None,
+    /// Yaz0, used by Wii U and early Switch titles. Compressed files use an
+    /// `s`-prefixed extension (e.g. `.sbfres`).
+    Yaz0,
+    /// Zstandard, used by later Switch titles. Compressed files have an
+    /// additional `.zs` extension. roead does not implement this codec.
+    Zstd,
+}
+
+/// Writer defaults for a game and platform.
+///
+/// The predefined profiles are available as associated constants. For other
+/// games, start from [`Profile::custom`] and adjust the fields as needed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// Endianness of the platform (big endian for Wii U, little endian for
+    /// Switch).
+    pub endian: Endian,
+    /// BYML version to write.
+    pub byml_version: u16,
+    /// Minimum data alignment for files in SARC archives.
+    pub sarc_min_alignment: usize,
+    /// Whether SARC writers add BOTW's alignment requirements (from the
+    /// resource factory and AGL environment tables).
+    pub sarc_botw_alignments: bool,
+    /// Whether SARC writers use legacy mode (for games without a BOTW-style
+    /// resource system).
+    pub sarc_legacy: bool,
+    /// Additional SARC alignment requirements, as pairs of file extension
+    /// (without the dot) and alignment.
+    pub sarc_alignments: Vec<(String, usize)>,
+    /// Compression codec for resource files, used by SARC writers to
+    /// compress files.
+    pub compression: Compression,
+    /// Whether AAMP name tables should include BOTW strings.
+    pub aamp_botw_names: bool,
+}
+
+impl Profile {
+    /// *Breath of the Wild* for Switch.
+    pub const BOTW_SWITCH: Profile = Profile {
+        endian: Endian::Little,
+        byml_version: 2,
+        sarc_min_alignment: 4,
+        sarc_botw_alignments: true,
+        sarc_legacy: false,
+        sarc_alignments: Vec::new(),
+        compression: Compression::Yaz0,
+        aamp_botw_names: true,
+    };
+    /// *Breath of the Wild* for Wii U.
+    pub const BOTW_WIIU: Profile = Profile {
+        endian: Endian::Big,
+        byml_version: 2,
+        sarc_min_alignment: 4,
+        sarc_botw_alignments: true,
+        sarc_legacy: false,
+        sarc_alignments: Vec::new(),
+        compression: Compression::Yaz0,
+        aamp_botw_names: true,
+    };
+    /// *Splatoon 3*.
+    ///
+    /// The game compresses its files with Zstandard, which roead does not
+    /// implement, so this profile leaves files uncompressed.
+    pub const SPLATOON3: Profile = Profile {
+        endian: Endian::Little,
+        byml_version: 7,
+        sarc_min_alignment: 8,
+        sarc_botw_alignments: false,
+        sarc_legacy: false,
+        sarc_alignments: Vec::new(),
+        compression: Compression::None,
+        aamp_botw_names: false,
+    };
+    /// *Tears of the Kingdom*.
+    ///
+    /// The game compresses its files with Zstandard, which roead does not
+    /// implement, so this profile leaves files uncompressed.
+    pub const TOTK: Profile = Profile {
+        endian: Endian::Little,
+        byml_version: 7,
+        sarc_min_alignment: 8,
+        sarc_botw_alignments: false,
+        sarc_legacy: false,
+        sarc_alignments: Vec::new(),
+        compression: Compression::None,
+        aamp_botw_names: false,
+    };
+
+    /// A profile for any other game, with no game-specific tables. It writes
+    /// BYML version 2, uses legacy SARC alignment and no compression.
+    pub const fn custom(endian: Endian) -> Profile {
+        Profile {
+            endian,
+            byml_version: 2,
+            sarc_min_alignment: 4,
+            sarc_botw_alignments: false,
+            sarc_legacy: true,
+            sarc_alignments: Vec::new(),
+            compression: Compression::None,
+            aamp_botw_names: false,
+        }
+    }
+
+    /// Builder-style method to set the endianness
+    #[inline]
+    pub fn with_endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    /// Builder-style method to set the BYML version
+    #[inline]
+    pub fn with_byml_version(mut self, version: u16) -> Self {
+        self.byml_version = version;
+        self
+    }
+
+    /// Builder-style method to set the minimum SARC data alignment
+    #[inline]
+    pub fn with_sarc_min_alignment(mut self, alignment: usize) -> Self {
+        self.sarc_min_alignment = alignment;
+        self
+    }
+
+    /// Builder-style method to add a SARC alignment requirement for a file
+    /// extension (without the dot)
+    #[inline]
+    pub fn with_sarc_alignment(mut self, ext: impl Into<String>, alignment: usize) -> Self {
+        self.sarc_alignments.push((ext.into(), alignment));
+        self
+    }
+
+    /// Builder-style method to set the compression codec
+    #[inline]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Create a SARC writer configured for this profile. Returns an error if
+    /// the profile has an invalid SARC alignment.
+    #[cfg(feature = "sarc")]
+    pub fn sarc_writer(&self) -> crate::Result<crate::sarc::SarcWriter> {
+        crate::sarc::SarcWriter::new(self.endian).with_profile(self)
+    }
+
+    /// Serialize a BYML document with this profile's endianness and version.
+    /// Returns an error if the document contains nodes which the version does
+    /// not support (see [`Byml::validate_for_version`](crate::byml::Byml::validate_for_version)).
+    #[cfg(feature = "byml")]
+    pub fn byml_to_binary(&self, byml: &crate::byml::Byml) -> crate::Result<Vec<u8>> {
+        byml.to_binary_checked(self.endian, self.byml_version)
+    }
+
+    /// Create an AAMP name table for this profile.
+    #[cfg(feature = "aamp")]
+    pub fn name_table(&self) -> crate::aamp::NameTable<'static> {
+        crate::aamp::NameTable::new(self.aamp_botw_names)
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::BOTW_WIIU
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "sarc")]
+    #[test]
+    fn sarc_profile() {
+        let data = Profile::TOTK
+            .sarc_writer()
+            .unwrap()
+            .with_file("Test.bfres", b"abcd".to_vec())
+            .to_binary();
+        let sarc = crate::sarc::Sarc::new(&data).unwrap();
+        assert_eq!(sarc.endian(), Endian::Little);
+        assert_eq!(sarc.get_data("Test.bfres"), Some(b"abcd".as_slice()));
+
+        assert!(Profile::custom(Endian::Big)
+            .with_sarc_min_alignment(3)
+            .sarc_writer()
+            .is_err());
+        assert!(Profile::custom(Endian::Big)
+            .with_sarc_alignment("bfres", 0)
+            .sarc_writer()
+            .is_err());
+    }
+
+    #[cfg(feature = "byml")]
+    #[test]
+    fn byml_profile() {
+        let byml = crate::byml::Byml::Array(vec![crate::byml::Byml::I32(1)]);
+        let data = Profile::BOTW_SWITCH.byml_to_binary(&byml).unwrap();
+        assert_eq!(&data[..4], b"YB\x02\x00");
+        let data = Profile::TOTK.byml_to_binary(&byml).unwrap();
+        assert_eq!(&data[..4], b"YB\x07\x00");
+        let data = Profile::custom(Endian::Big)
+            .with_byml_version(4)
+            .byml_to_binary(&byml)
+            .unwrap();
+        assert_eq!(&data[..4], b"BY\x00\x04");
+        let byml = crate::byml::Byml::Array(vec![crate::byml::Byml::U64(1)]);
+        assert!(Profile::BOTW_WIIU.byml_to_binary(&byml).is_err());
+        assert!(Profile::TOTK.byml_to_binary(&byml).is_ok());
+    }
+}
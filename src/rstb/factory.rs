@@ -0,0 +1,554 @@
+use std::ops::Deref;
+
+use join_str::jstr;
+use once_cell::sync::Lazy;
+use rustc_hash::FxHashMap;
+
+use crate::{Endian, Error, Result};
+
+static FACTORY_INFO: &str = include_str!("../../data/botw_resource_factory_info.tsv");
+
+/// Extra allocation made by the resource manager for every resource.
+const RESOURCE_OVERHEAD_WIIU: u32 = 0xE4;
+const RESOURCE_OVERHEAD_NX: u32 = 0x168;
+
+/// Approximate in-memory sizes of a parsed AAMP list, object and parameter
+/// on Wii U and Switch. Pointers are twice as large on Switch.
+const AAMP_NODE_SIZES_WIIU: [u32; 3] = [0x24, 0x18, 0x14];
+const AAMP_NODE_SIZES_NX: [u32; 3] = [0x48, 0x30, 0x28];
+/// Approximate in-memory size of a parsed bfres sub-file (model, texture,
+/// animation or embedded file) on Wii U and Switch.
+const BFRES_SUBFILE_SIZE_WIIU: u32 = 0x80;
+const BFRES_SUBFILE_SIZE_NX: u32 = 0x100;
+
+/// The size of the parsed data for a resource type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseSize {
+    /// A fixed number of bytes.
+    Fixed(u32),
+    /// Depends on the contents of the file.
+    Complex,
+}
+
+/// Information about one resource factory (resource type) in the game's
+/// resource system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactoryInfo {
+    /// Primary extension (without the dot) of the resource type.
+    pub name: String,
+    /// Additional extensions handled by the same factory.
+    pub other_extensions: Vec<String>,
+    /// Size of the resource class on Switch.
+    pub size_nx: u32,
+    /// Size of the resource class on Wii U.
+    pub size_wiiu: u32,
+    /// Data alignment required by the factory.
+    pub alignment: u32,
+    /// Parse size on Switch.
+    pub parse_size_nx: ParseSize,
+    /// Parse size on Wii U.
+    pub parse_size_wiiu: ParseSize,
+    /// Multiplier applied to the file size when the resource has no size
+    /// table entry.
+    pub multiplier: f32,
+    /// Constant added to the file size when the resource has no size table
+    /// entry.
+    pub constant: u32,
+    /// Game subsystem which owns the factory.
+    pub subsystem: String,
+    /// Free-form notes about the factory.
+    pub details: String,
+}
+
+impl FactoryInfo {
+    /// Size of the resource class for the platform with the given
+    /// endianness (big endian for Wii U, little endian for Switch).
+    #[inline]
+    pub fn size(&self, endian: Endian) -> u32 {
+        match endian {
+            Endian::Big => self.size_wiiu,
+            Endian::Little => self.size_nx,
+        }
+    }
+
+    /// Parse size for the platform with the given endianness (big endian for
+    /// Wii U, little endian for Switch).
+    #[inline]
+    pub fn parse_size(&self, endian: Endian) -> ParseSize {
+        match endian {
+            Endian::Big => self.parse_size_wiiu,
+            Endian::Little => self.parse_size_nx,
+        }
+    }
+
+    /// Check if the parse size depends on the file contents on either
+    /// platform.
+    #[inline]
+    pub fn is_complex(&self) -> bool {
+        self.parse_size_nx == ParseSize::Complex || self.parse_size_wiiu == ParseSize::Complex
+    }
+}
+
+fn parse_u32(value: &str) -> Result<u32> {
+    let value = value.trim();
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| Error::InvalidDataD(jstr!("Invalid factory info number: {value}")))
+}
+
+fn parse_parse_size(value: &str) -> Result<ParseSize> {
+    if value.trim() == "complex" {
+        Ok(ParseSize::Complex)
+    } else {
+        parse_u32(value).map(ParseSize::Fixed)
+    }
+}
+
+/// A table of resource factories, used to estimate resource sizes for the
+/// resource size table.
+///
+/// The default table ([`ResourceFactory::botw`]) contains the factories in
+/// Breath of the Wild.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceFactory {
+    factories: Vec<FactoryInfo>,
+    by_ext: FxHashMap<String, usize>,
+}
+
+impl ResourceFactory {
+    /// Returns the factory table for Breath of the Wild. It is parsed on
+    /// first use.
+    pub fn botw() -> &'static ResourceFactory {
+        static BOTW_FACTORY: Lazy<ResourceFactory> = Lazy::new(|| {
+            ResourceFactory::from_tsv(FACTORY_INFO).expect("BOTW factory info should be valid")
+        });
+        BOTW_FACTORY.deref()
+    }
+
+    /// Parse a factory table from tab-separated values. The columns must be
+    /// the same as in `data/botw_resource_factory_info.tsv`, including the
+    /// header row. The `name` column may contain several comma-separated
+    /// extensions.
+    pub fn from_tsv(text: &str) -> Result<Self> {
+        let mut factories = Vec::new();
+        for line in text.lines().skip(1) {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+            let cols: Vec<&str> = line.split('\t').collect();
+            if cols.len() < 10 {
+                return Err(Error::InvalidDataD(jstr!(
+                    "Invalid factory info line: {line}"
+                )));
+            }
+            let mut names = cols[0].split(',').map(|n| n.trim().to_owned());
+            let name = names
+                .next()
+                .ok_or_else(|| Error::InvalidDataD(jstr!("Missing factory name: {line}")))?;
+            factories.push(FactoryInfo {
+                name,
+                other_extensions: names
+                    .chain(
+                        cols[6]
+                            .split(',')
+                            .map(|n| n.trim().to_owned())
+                            .filter(|n| !n.is_empty()),
+                    )
+                    .collect(),
+                size_nx: parse_u32(cols[1])?,
+                size_wiiu: parse_u32(cols[2])?,
+                alignment: parse_u32(cols[3])?,
+                parse_size_nx: parse_parse_size(cols[4])?,
+                parse_size_wiiu: parse_parse_size(cols[5])?,
+                multiplier: cols[7].trim().parse().map_err(|_| {
+                    Error::InvalidDataD(format!("Invalid factory multiplier: {}", cols[7]))
+                })?,
+                constant: parse_u32(cols[8])?,
+                subsystem: cols[9].to_owned(),
+                details: cols.get(10).copied().unwrap_or_default().to_owned(),
+            });
+        }
+        Ok(Self::new(factories))
+    }
+
+    /// Create a factory table from a list of factories. A factory named `*`
+    /// is used for any extension not otherwise found.
+    pub fn new(factories: Vec<FactoryInfo>) -> Self {
+        let by_ext = factories
+            .iter()
+            .enumerate()
+            .flat_map(|(i, info)| {
+                std::iter::once(info.name.clone())
+                    .chain(info.other_extensions.iter().cloned())
+                    .map(move |ext| (ext, i))
+            })
+            .collect();
+        Self { factories, by_ext }
+    }
+
+    /// Iterate over all factories.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &FactoryInfo> {
+        self.factories.iter()
+    }
+
+    /// Get the factory for an extension (without the dot), if there is one.
+    #[inline]
+    pub fn get(&self, ext: &str) -> Option<&FactoryInfo> {
+        self.by_ext.get(ext).map(|i| &self.factories[*i])
+    }
+
+    /// Get the factory which handles a resource path. Compound extensions
+    /// (e.g. `Tex1.bfres`) are checked first, and Yaz0 extensions (e.g.
+    /// `sbfres`) are matched to their uncompressed counterpart. Falls back to
+    /// the `*` factory, if present.
+    pub fn get_for_path(&self, path: &str) -> Option<&FactoryInfo> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        file_name
+            .match_indices('.')
+            .map(|(i, _)| &file_name[i + 1..])
+            .find_map(|ext| {
+                self.get(ext).or_else(|| {
+                    let (prefix, last) = ext.rsplit_once('.').unwrap_or(("", ext));
+                    let last = last.strip_prefix('s')?;
+                    if prefix.is_empty() {
+                        self.get(last)
+                    } else {
+                        self.get(&jstr!("{prefix}.{last}"))
+                    }
+                })
+            })
+            .or_else(|| self.get("*"))
+    }
+
+    /// Estimate the resource size table value for a file. The data must be
+    /// decompressed. The endianness selects the platform: big endian for Wii
+    /// U, little endian for Switch.
+    ///
+    /// For resource types with a fixed parse size the result matches what
+    /// the game requires. For "complex" types, the parse size is the file
+    /// size multiplier and constant from the factory table plus an allowance
+    /// for the structures listed in the file's header: the lists, objects
+    /// and parameters of AAMP files, the string table entries of BYML files
+    /// and the sub-files (models, textures, animations and embedded files)
+    /// of bfres files. This errs on the side of overallocation but is not
+    /// exact.
+    ///
+    /// Returns `None` if no factory handles the path or the size does not
+    /// fit in a `u32`.
+    pub fn estimate_size(&self, path: &str, data: &[u8], endian: Endian) -> Option<u32> {
+        let info = self.get_for_path(path)?;
+        let file_size = u32::try_from(data.len()).ok()?.checked_add(31)? & !31;
+        let parse_size = match info.parse_size(endian) {
+            ParseSize::Fixed(size) => size,
+            ParseSize::Complex => estimate_complex_parse_size(info, data, endian)?,
+        };
+        let overhead = match endian {
+            Endian::Big => RESOURCE_OVERHEAD_WIIU,
+            Endian::Little => RESOURCE_OVERHEAD_NX,
+        };
+        file_size
+            .checked_add(overhead)?
+            .checked_add(info.size(endian))?
+            .checked_add(parse_size)
+    }
+}
+
+fn estimate_complex_parse_size(info: &FactoryInfo, data: &[u8], endian: Endian) -> Option<u32> {
+    let scaled = (data.len() as f64 * info.multiplier.max(1.0) as f64).ceil();
+    if scaled > u32::MAX as f64 {
+        return None;
+    }
+    (scaled as u32)
+        .checked_add(info.constant)?
+        .checked_add(header_structures_size(data, endian)?)
+}
+
+fn read_u16(data: &[u8], offset: usize, endian: Endian) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?.try_into().ok()?;
+    Some(match endian {
+        Endian::Big => u16::from_be_bytes(bytes),
+        Endian::Little => u16::from_le_bytes(bytes),
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize, endian: Endian) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?.try_into().ok()?;
+    Some(match endian {
+        Endian::Big => u32::from_be_bytes(bytes),
+        Endian::Little => u32::from_le_bytes(bytes),
+    })
+}
+
+/// Number of entries in a BYML string table, or 0 if there is no table.
+fn byml_table_len(data: &[u8], offset: u32, endian: Endian) -> u32 {
+    let offset = offset as usize;
+    if offset == 0 || data.get(offset) != Some(&0xc2) {
+        return 0;
+    }
+    match (endian, data.get(offset + 1..offset + 4)) {
+        (Endian::Big, Some(&[a, b, c])) => u32::from_be_bytes([0, a, b, c]),
+        (Endian::Little, Some(&[a, b, c])) => u32::from_le_bytes([a, b, c, 0]),
+        _ => 0,
+    }
+}
+
+/// Estimate the memory needed for the structures listed in the header of an
+/// AAMP, BYML or bfres file, on the platform with the given endianness.
+/// Other formats and truncated headers need nothing. Returns `None` if the
+/// size does not fit in a `u32`.
+fn header_structures_size(data: &[u8], endian: Endian) -> Option<u32> {
+    let pointer_size = match endian {
+        Endian::Big => 4,
+        Endian::Little => 8,
+    };
+    match data.get(..4) {
+        Some(b"AAMP") => {
+            let sizes = match endian {
+                Endian::Big => AAMP_NODE_SIZES_WIIU,
+                Endian::Little => AAMP_NODE_SIZES_NX,
+            };
+            // AAMP files are always little endian. The list, object and
+            // parameter counts are at 0x18, 0x1C and 0x20.
+            let Some(counts) = (0..3)
+                .map(|i| read_u32(data, 0x18 + 4 * i, Endian::Little))
+                .collect::<Option<Vec<_>>>()
+            else {
+                return Some(0);
+            };
+            counts
+                .iter()
+                .zip(sizes)
+                .try_fold(0u32, |total, (count, size)| {
+                    total.checked_add(count.checked_mul(size)?)
+                })
+        }
+        Some([b'B', b'Y', ..]) | Some([b'Y', b'B', ..]) => {
+            let file_endian = if data[0] == b'B' {
+                Endian::Big
+            } else {
+                Endian::Little
+            };
+            let (Some(hash_keys), Some(strings)) = (
+                read_u32(data, 0x4, file_endian),
+                read_u32(data, 0x8, file_endian),
+            ) else {
+                return Some(0);
+            };
+            (byml_table_len(data, hash_keys, file_endian) as u64
+                + byml_table_len(data, strings, file_endian) as u64)
+                .checked_mul(pointer_size)?
+                .try_into()
+                .ok()
+        }
+        Some(b"FRES") => {
+            // Switch files have four spaces after the magic and the counts
+            // of 7 sub-file groups at 0xBC. Wii U files are big endian with
+            // the counts of 12 groups at 0x50.
+            let (file_endian, counts, groups) = if data.get(4..8) == Some(b"    ") {
+                (Endian::Little, 0xBC, 7)
+            } else {
+                (Endian::Big, 0x50, 12)
+            };
+            let size = match endian {
+                Endian::Big => BFRES_SUBFILE_SIZE_WIIU,
+                Endian::Little => BFRES_SUBFILE_SIZE_NX,
+            };
+            let subfiles = (0..groups)
+                .map(|i| read_u16(data, counts + 2 * i, file_endian).map(u32::from))
+                .sum::<Option<u32>>()
+                .unwrap_or(0);
+            subfiles.checked_mul(size)
+        }
+        _ => Some(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn botw_factory_info() {
+        let factory = ResourceFactory::botw();
+        let bfres = factory.get("bfres").unwrap();
+        assert_eq!(bfres.alignment, 0x1000);
+        assert_eq!(bfres.parse_size_wiiu, ParseSize::Complex);
+        assert_eq!(bfres.multiplier, 2.5);
+        assert_eq!(factory.get("bnfprl").unwrap().name, "batpl");
+        assert_eq!(
+            factory.get("bdmgparam").unwrap().parse_size_nx,
+            ParseSize::Fixed(0x790)
+        );
+        assert_eq!(factory.get("bfevtm").unwrap().subsystem, "Event");
+        assert_eq!(
+            factory
+                .get_for_path("Actor/Pack/Test.sbactorpack")
+                .unwrap()
+                .name,
+            "sarc"
+        );
+        assert_eq!(
+            factory.get_for_path("Model/Test.Tex1.sbfres").unwrap().name,
+            "*"
+        );
+        assert_eq!(
+            factory.get_for_path("Model/Test.sbfres").unwrap().name,
+            "bfres"
+        );
+        assert_eq!(factory.get_for_path("Unknown/File.xyz").unwrap().name, "*");
+    }
+
+    #[test]
+    fn estimate_sizes() {
+        let factory = ResourceFactory::botw();
+        let data = vec![0u8; 100];
+        assert_eq!(
+            factory.estimate_size("Actor/AIDefine/Test.baischedule", &data, Endian::Big),
+            Some(128 + 0xE4 + 0x244)
+        );
+        assert_eq!(
+            factory.estimate_size("Event/Test.sbeventpack", &data, Endian::Little),
+            Some(128 + 0x168 + 0x68)
+        );
+        // bphysics has a complex parse size, 6 times the file size plus the
+        // structures in the AAMP header, of which there is none here.
+        assert_eq!(
+            factory.estimate_size("Actor/Physics/Test.bphysics", &data, Endian::Big),
+            Some(128 + 0xE4 + 0x324 + 600)
+        );
+        // bfres: 2.5 times the file size plus 0x400000.
+        assert_eq!(
+            factory.estimate_size("Model/Test.sbfres", &data, Endian::Little),
+            Some(128 + 0x168 + 0x1a8 + 250 + 0x400000)
+        );
+    }
+
+    #[cfg(feature = "aamp")]
+    #[test]
+    fn estimate_aamp() {
+        use crate::aamp::*;
+
+        #[derive(Default)]
+        struct Counts([u32; 3]);
+        impl Visit for Counts {
+            fn visit_list(&mut self, _path: &[Name], _list: &ParameterList) -> bool {
+                self.0[0] += 1;
+                true
+            }
+
+            fn visit_object(&mut self, _path: &[Name], _object: &ParameterObject) -> bool {
+                self.0[1] += 1;
+                true
+            }
+
+            fn visit_parameter(&mut self, _path: &[Name], _param: &Parameter) {
+                self.0[2] += 1;
+            }
+        }
+
+        let factory = ResourceFactory::botw();
+        for file in ["Lizalfos.bphysics", "GameRomHorse.bxml", "Horse.blifecondition"] {
+            let data = std::fs::read(jstr!("test/aamp/{file}")).unwrap();
+            let mut counts = Counts::default();
+            ParameterIO::from_binary(&data).unwrap().visit(&mut counts);
+            let [lists, objects, params] = counts.0;
+            assert!(params > 0);
+            let info = factory.get_for_path(file).unwrap();
+            let len = data.len() as u32;
+            let base = ((len + 31) & !31)
+                + (len as f32 * info.multiplier.max(1.0)).ceil() as u32
+                + info.constant;
+            assert_eq!(
+                factory.estimate_size(file, &data, Endian::Big),
+                Some(base + 0xE4 + info.size_wiiu + lists * 0x24 + objects * 0x18 + params * 0x14)
+            );
+            assert_eq!(
+                factory.estimate_size(file, &data, Endian::Little),
+                Some(base + 0x168 + info.size_nx + lists * 0x48 + objects * 0x30 + params * 0x28)
+            );
+        }
+    }
+
+    #[cfg(feature = "byml")]
+    #[test]
+    fn estimate_byml() {
+        use crate::byml::{Byml, BymlIndex};
+
+        let mut info = ResourceFactory::botw().get("bphysics").unwrap().clone();
+        info.name = "byml".into();
+        info.multiplier = 1.0;
+        let factory = ResourceFactory::new(vec![info.clone()]);
+        for file in ["A-1_Dynamic.byml", "EventInfo.product.byml"] {
+            let data = std::fs::read(jstr!("test/byml/{file}")).unwrap();
+            let mut keys = rustc_hash::FxHashSet::default();
+            let mut strings = rustc_hash::FxHashSet::default();
+            Byml::from_binary(&data).unwrap().visit(&mut |_: &[BymlIndex], node: &Byml| {
+                match node {
+                    Byml::Map(map) => keys.extend(map.keys().cloned()),
+                    Byml::String(s) => {
+                        strings.insert(s.clone());
+                    }
+                    _ => (),
+                }
+            });
+            let len = data.len() as u32;
+            let base = ((len + 31) & !31) + 0x168 + info.size_nx + len + info.constant;
+            assert_eq!(
+                factory.estimate_size(file, &data, Endian::Little),
+                Some(base + (keys.len() + strings.len()) as u32 * 8)
+            );
+        }
+    }
+
+    #[test]
+    fn estimate_bfres() {
+        let factory = ResourceFactory::botw();
+        let info = factory.get("bfres").unwrap();
+        let base = |data: &[u8], size: u32, overhead: u32| {
+            let len = data.len() as u32;
+            ((len + 31) & !31) + overhead + size + (len as f32 * 2.5).ceil() as u32 + 0x400000
+        };
+        // Wii U: 2 models and 3 textures.
+        let mut wiiu = vec![0u8; 0x6C];
+        wiiu[..4].copy_from_slice(b"FRES");
+        wiiu[0x51] = 2;
+        wiiu[0x53] = 3;
+        assert_eq!(
+            factory.estimate_size("Model/Test.sbfres", &wiiu, Endian::Big),
+            Some(base(&wiiu, info.size_wiiu, 0xE4) + 5 * 0x80)
+        );
+        // Switch: 1 model and 4 skeletal animations.
+        let mut nx = vec![0u8; 0xD0];
+        nx[..8].copy_from_slice(b"FRES    ");
+        nx[0xBC] = 1;
+        nx[0xBE] = 4;
+        assert_eq!(
+            factory.estimate_size("Model/Test.sbfres", &nx, Endian::Little),
+            Some(base(&nx, info.size_nx, 0x168) + 5 * 0x100)
+        );
+    }
+
+    #[test]
+    fn estimate_overflow() {
+        let mut info = ResourceFactory::botw().get("bfres").unwrap().clone();
+        info.constant = u32::MAX - 0x10;
+        let factory = ResourceFactory::new(vec![info.clone()]);
+        assert_eq!(factory.estimate_size("Test.bfres", &[0; 4], Endian::Big), None);
+        info.constant = 0;
+        info.multiplier = 1.0e10;
+        let factory = ResourceFactory::new(vec![info]);
+        assert_eq!(factory.estimate_size("Test.bfres", &[0; 4], Endian::Big), None);
+        // Structure counts from a crafted header.
+        let mut aamp = vec![0u8; 0x30];
+        aamp[..4].copy_from_slice(b"AAMP");
+        aamp[0x20..0x24].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            ResourceFactory::botw().estimate_size("Test.bphysics", &aamp, Endian::Big),
+            None
+        );
+    }
+}
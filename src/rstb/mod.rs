@@ -0,0 +1,465 @@
+//! Resource size table (RSTB/RESTBL) support.
+//!
+//! The resource size table (`ResourceSizeTable.product.srsizetable` in BOTW
+//! and `ResourceSizeTable.Product.*.rsizetable` in TOTK) tells the game's
+//! resource system how much memory to allocate for each resource. It maps the
+//! CRC32 hash of a resource path to a size. A separate name table holds
+//! entries for paths whose hash collides with another path.
+//!
+//! Both BOTW layouts are supported (big endian for Wii U, little endian for
+//! Switch), as well as the TOTK `RESTBL` variant.
+//!
+//! Sizes for new or modified resources can be estimated with
+//! [`ResourceFactory`], which holds the game's resource factory info table.
+//!
+//! Sample usage:
+//! ```
+//! # use roead::{rstb::*, Endian};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut table = ResourceSizeTable::new(Format::Rstb, Endian::Big);
+//! table.set("Actor/Pack/Enemy_Lizalfos_Electric.bactorpack", 0x5280)?;
+//! assert_eq!(
+//!     table.get("Actor/Pack/Enemy_Lizalfos_Electric.bactorpack"),
+//!     Some(0x5280)
+//! );
+//! let data = table.to_binary()?;
+//! let table2 = ResourceSizeTable::from_binary(data)?;
+//! assert_eq!(table, table2);
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Seek, Write},
+};
+
+use binrw::{binrw, BinRead, BinReaderExt, BinWrite};
+use join_str::jstr;
+
+use crate::{Endian, Error, Result};
+
+mod factory;
+pub use factory::{FactoryInfo, ParseSize, ResourceFactory};
+
+/// Hash a resource path for the resource size table (CRC32).
+#[inline]
+pub const fn hash_path(path: &str) -> u32 {
+    crate::util::crc32(path.as_bytes())
+}
+
+/// The layout of a resource size table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// BOTW `RSTB` table, big endian on Wii U and little endian on Switch.
+    Rstb,
+    /// TOTK `RESTBL` table (always little endian).
+    Restbl,
+}
+
+impl Format {
+    /// Maximum length of a name table entry, including the null terminator.
+    #[inline]
+    pub const fn name_size(self) -> usize {
+        match self {
+            Format::Rstb => 128,
+            Format::Restbl => 160,
+        }
+    }
+}
+
+/// Size = 0x0C
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[binrw]
+#[brw(magic = b"RSTB")]
+struct ResRstbHeader {
+    crc_table_size:  u32,
+    name_table_size: u32,
+}
+
+/// Size = 0x16
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[binrw]
+#[brw(little, magic = b"RESTBL")]
+struct ResRestblHeader {
+    version: u32,
+    string_size: u32,
+    crc_table_size: u32,
+    name_table_size: u32,
+}
+
+/// Size = 0x08
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[binrw]
+struct ResCrcEntry {
+    crc:  u32,
+    size: u32,
+}
+
+/// A resource size table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceSizeTable {
+    format: Format,
+    endian: Endian,
+    /// Sizes keyed by the CRC32 hash of the resource path.
+    pub crc_table: BTreeMap<u32, u32>,
+    /// Sizes keyed by resource path, for paths with colliding hashes.
+    pub name_table: BTreeMap<String, u32>,
+}
+
+impl ResourceSizeTable {
+    /// Create a new, empty resource size table. RESTBL tables are always
+    /// little endian, so the endianness is ignored for [`Format::Restbl`].
+    pub fn new(format: Format, endian: Endian) -> Self {
+        Self {
+            format,
+            endian: match format {
+                Format::Rstb => endian,
+                Format::Restbl => Endian::Little,
+            },
+            crc_table: BTreeMap::new(),
+            name_table: BTreeMap::new(),
+        }
+    }
+
+    /// Parse a resource size table from binary data. The layout and
+    /// endianness are detected automatically.
+    ///
+    /// **Note**: If and only if the `yaz0` feature is enabled, this function
+    /// automatically decompresses the data when necessary.
+    pub fn from_binary(data: impl AsRef<[u8]>) -> Result<Self> {
+        let data = data.as_ref();
+        #[cfg(feature = "yaz0")]
+        {
+            if data.starts_with(b"Yaz0") {
+                return Self::parse(&crate::yaz0::decompress(data)?);
+            }
+        }
+        Self::parse(data)
+    }
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(data);
+        if data.starts_with(b"RESTBL") {
+            let header: ResRestblHeader = reader.read_le()?;
+            if header.string_size as usize != Format::Restbl.name_size() {
+                return Err(Error::InvalidDataD(jstr!(
+                    "Unsupported RESTBL name size: {&header.string_size.to_string()}"
+                )));
+            }
+            Self::parse_tables(
+                &mut reader,
+                Format::Restbl,
+                Endian::Little,
+                header.crc_table_size,
+                header.name_table_size,
+            )
+        } else if data.starts_with(b"RSTB") {
+            let endian = [Endian::Big, Endian::Little]
+                .into_iter()
+                .find(|endian| {
+                    reader.set_position(0);
+                    read::<ResRstbHeader>(&mut reader, *endian)
+                        .map(|h| {
+                            0xC + h.crc_table_size as usize * 0x8
+                                + h.name_table_size as usize * (Format::Rstb.name_size() + 4)
+                                == data.len()
+                        })
+                        .unwrap_or(false)
+                })
+                .ok_or(Error::InvalidData(
+                    "RSTB table sizes do not match the data size",
+                ))?;
+            reader.set_position(0);
+            let header: ResRstbHeader = read(&mut reader, endian)?;
+            Self::parse_tables(
+                &mut reader,
+                Format::Rstb,
+                endian,
+                header.crc_table_size,
+                header.name_table_size,
+            )
+        } else {
+            Err(Error::BadMagic(
+                String::from_utf8_lossy(&data[..data.len().min(4)]).into(),
+                "RSTB or RESTBL",
+            ))
+        }
+    }
+
+    fn parse_tables(
+        reader: &mut Cursor<&[u8]>,
+        format: Format,
+        endian: Endian,
+        crc_table_size: u32,
+        name_table_size: u32,
+    ) -> Result<Self> {
+        let name_size = format.name_size();
+        let data = *reader.get_ref();
+        let needed = reader.position() as usize
+            + crc_table_size as usize * 0x8
+            + name_table_size as usize * (name_size + 4);
+        if data.len() < needed {
+            return Err(Error::InsufficientData(data.len(), needed));
+        }
+        let mut table = Self::new(format, endian);
+        for _ in 0..crc_table_size {
+            let entry: ResCrcEntry = read(reader, endian)?;
+            table.crc_table.insert(entry.crc, entry.size);
+        }
+        for _ in 0..name_table_size {
+            let start = reader.position() as usize;
+            let raw = &data[start..start + name_size];
+            let len = raw.iter().position(|b| *b == 0).unwrap_or(name_size);
+            let name = std::str::from_utf8(&raw[..len])?.to_owned();
+            reader.set_position((start + name_size) as u64);
+            let size: u32 = read(reader, endian)?;
+            table.name_table.insert(name, size);
+        }
+        Ok(table)
+    }
+
+    /// Serialize the table to a Write + Seek writer.
+    pub fn write<W: Write + Seek>(&self, writer: &mut W) -> Result<()> {
+        let brw_endian = match self.endian {
+            Endian::Big => binrw::Endian::Big,
+            Endian::Little => binrw::Endian::Little,
+        };
+        match self.format {
+            Format::Rstb => {
+                ResRstbHeader {
+                    crc_table_size:  self.crc_table.len() as u32,
+                    name_table_size: self.name_table.len() as u32,
+                }
+                .write_options(writer, brw_endian, ())?
+            }
+            Format::Restbl => {
+                ResRestblHeader {
+                    version: 1,
+                    string_size: Format::Restbl.name_size() as u32,
+                    crc_table_size: self.crc_table.len() as u32,
+                    name_table_size: self.name_table.len() as u32,
+                }
+                .write_options(writer, brw_endian, ())?
+            }
+        }
+        for (&crc, &size) in &self.crc_table {
+            ResCrcEntry { crc, size }.write_options(writer, brw_endian, ())?;
+        }
+        let name_size = self.format.name_size();
+        for (name, &size) in &self.name_table {
+            if name.len() >= name_size {
+                return Err(Error::InvalidDataD(jstr!(
+                    "Resource name too long for name table: {name}"
+                )));
+            }
+            let mut buf = vec![0u8; name_size];
+            buf[..name.len()].copy_from_slice(name.as_bytes());
+            writer.write_all(&buf)?;
+            size.write_options(writer, brw_endian, ())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Serialize the table to in-memory bytes. Fails if a name table entry is
+    /// too long for the table format.
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(
+            0x16 + self.crc_table.len() * 0x8
+                + self.name_table.len() * (self.format.name_size() + 4),
+        );
+        self.write(&mut Cursor::new(&mut buf))?;
+        Ok(buf)
+    }
+
+    /// Get the table layout.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Get the table endianness.
+    #[inline]
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// Set the endianness. Has no effect on RESTBL tables, which are always
+    /// little endian.
+    #[inline]
+    pub fn set_endian(&mut self, endian: Endian) {
+        if self.format == Format::Rstb {
+            self.endian = endian;
+        }
+    }
+
+    /// Builder-style method to set the endianness.
+    #[inline]
+    pub fn with_endian(mut self, endian: Endian) -> Self {
+        self.set_endian(endian);
+        self
+    }
+
+    /// Get the total number of entries in both tables.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.crc_table.len() + self.name_table.len()
+    }
+
+    /// Check if the table has no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.crc_table.is_empty() && self.name_table.is_empty()
+    }
+
+    /// Get the resource size for a path. The name table takes priority over
+    /// the hash table.
+    pub fn get(&self, path: &str) -> Option<u32> {
+        self.name_table
+            .get(path)
+            .or_else(|| self.crc_table.get(&hash_path(path)))
+            .copied()
+    }
+
+    /// Get the resource size for a path hash. Only the hash table is
+    /// searched.
+    #[inline]
+    pub fn get_by_hash(&self, hash: u32) -> Option<u32> {
+        self.crc_table.get(&hash).copied()
+    }
+
+    /// Check if the table has an entry for a path.
+    #[inline]
+    pub fn contains(&self, path: &str) -> bool {
+        self.get(path).is_some()
+    }
+
+    /// Check if the hash table has an entry for a path hash.
+    #[inline]
+    pub fn contains_hash(&self, hash: u32) -> bool {
+        self.crc_table.contains_key(&hash)
+    }
+
+    /// Set the resource size for a path. The entry goes into the name table
+    /// if the path is already there or its hash collides with a path in the
+    /// name table, and into the hash table otherwise.
+    ///
+    /// Fails if the entry needs to go into the name table but the path is too
+    /// long for the table format.
+    pub fn set(&mut self, path: &str, size: u32) -> Result<()> {
+        if self.needs_name_entry(path) {
+            if path.len() >= self.format.name_size() {
+                return Err(Error::InvalidDataD(jstr!(
+                    "Resource name too long for name table: {path}"
+                )));
+            }
+            self.name_table.insert(path.to_owned(), size);
+        } else {
+            self.crc_table.insert(hash_path(path), size);
+        }
+        Ok(())
+    }
+
+    /// Set the resource size for a path hash in the hash table.
+    #[inline]
+    pub fn set_by_hash(&mut self, hash: u32, size: u32) {
+        self.crc_table.insert(hash, size);
+    }
+
+    /// Remove the entry for a path, returning the old size if there was one.
+    /// The name table takes priority over the hash table.
+    pub fn remove(&mut self, path: &str) -> Option<u32> {
+        self.name_table
+            .remove(path)
+            .or_else(|| self.crc_table.remove(&hash_path(path)))
+    }
+
+    /// Remove the entry for a path hash from the hash table, returning the old
+    /// size if there was one.
+    #[inline]
+    pub fn remove_by_hash(&mut self, hash: u32) -> Option<u32> {
+        self.crc_table.remove(&hash)
+    }
+
+    fn needs_name_entry(&self, path: &str) -> bool {
+        let hash = hash_path(path);
+        self.name_table.contains_key(path) || self.name_table.keys().any(|n| hash_path(n) == hash)
+    }
+}
+
+#[inline(always)]
+fn read<'a, T: BinRead>(reader: &mut Cursor<&[u8]>, endian: Endian) -> Result<T>
+where
+    <T as BinRead>::Args<'a>: Default + Clone,
+{
+    Ok(match endian {
+        Endian::Big => reader.read_be()?,
+        Endian::Little => reader.read_le()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(format: Format, endian: Endian) -> ResourceSizeTable {
+        let mut table = ResourceSizeTable::new(format, endian);
+        table
+            .set("Actor/ActorLink/Enemy_Lizalfos_Electric.bxml", 0x1580)
+            .unwrap();
+        table
+            .set("Map/MainField/A-1/A-1_Dynamic.mubin", 0xC208)
+            .unwrap();
+        table.set_by_hash(0xDEADBEEF, 0x400);
+        table
+            .name_table
+            .insert("Actor/Pack/Test.bactorpack".into(), 0x3000);
+        table
+    }
+
+    #[test]
+    fn rstb_roundtrip() {
+        for (format, endian) in [
+            (Format::Rstb, Endian::Big),
+            (Format::Rstb, Endian::Little),
+            (Format::Restbl, Endian::Little),
+        ] {
+            let table = sample(format, endian);
+            let data = table.to_binary().unwrap();
+            let table2 = ResourceSizeTable::from_binary(&data).unwrap();
+            assert_eq!(table2.format(), format);
+            assert_eq!(table2.endian(), endian);
+            assert_eq!(table, table2);
+            assert_eq!(table2.get("Actor/Pack/Test.bactorpack"), Some(0x3000));
+            assert_eq!(
+                table2.get("Map/MainField/A-1/A-1_Dynamic.mubin"),
+                Some(0xC208)
+            );
+        }
+    }
+
+    #[test]
+    fn rstb_name_table() {
+        let mut table = sample(Format::Rstb, Endian::Big);
+        table.set("Actor/Pack/Test.bactorpack", 0x3100).unwrap();
+        assert_eq!(table.name_table["Actor/Pack/Test.bactorpack"], 0x3100);
+        assert!(!table.contains_hash(hash_path("Actor/Pack/Test.bactorpack")));
+        assert_eq!(table.remove("Actor/Pack/Test.bactorpack"), Some(0x3100));
+        assert!(!table.contains("Actor/Pack/Test.bactorpack"));
+        let long_name = "a".repeat(140);
+        table.name_table.insert(long_name.clone(), 0);
+        assert!(table.to_binary().is_err());
+        assert!(table.set(&long_name, 1).is_err());
+    }
+
+    #[cfg(feature = "yaz0")]
+    #[test]
+    fn parse_botw_rstb() {
+        let data = std::fs::read("test/yaz0/ResourceSizeTable.product.srsizetable").unwrap();
+        let table = ResourceSizeTable::from_binary(data).unwrap();
+        assert_eq!(table.format(), Format::Rstb);
+        assert!(!table.is_empty());
+        let table2 = ResourceSizeTable::from_binary(table.to_binary().unwrap()).unwrap();
+        assert_eq!(table, table2);
+    }
+}
@@ -0,0 +1,238 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::is_valid_alignment;
+use crate::{Error, Result};
+
+/// Selects which files an [`AlignmentRule`] applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlignmentMatch {
+    /// Matches files by extension (without the dot, e.g. `bfres`). Compound
+    /// extensions such as `Tex1.bfres` are supported.
+    Extension(String),
+    /// Matches files whose data starts with the given magic bytes (e.g.
+    /// `FRES`).
+    Magic(String),
+    /// Matches files whose path matches a glob pattern. `?` matches any
+    /// single character, `*` matches any run of characters except `/`, and
+    /// `**` matches any run of characters including `/`.
+    Glob(String),
+}
+
+impl AlignmentMatch {
+    /// Check if a file matches.
+    pub fn matches(&self, name: &str, data: &[u8]) -> bool {
+        match self {
+            Self::Extension(ext) => {
+                name.strip_suffix(ext.as_str())
+                    .map(|rest| rest.ends_with('.'))
+                    .unwrap_or(false)
+            }
+            Self::Magic(magic) => data.starts_with(magic.as_bytes()),
+            Self::Glob(pattern) => glob_match(pattern.as_bytes(), name.as_bytes()),
+        }
+    }
+}
+
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=name.len()).any(|i| glob_match(rest, &name[i..])),
+        [b'*', rest @ ..] => {
+            (0..=name.len())
+                .take_while(|i| *i == 0 || name[i - 1] != b'/')
+                .any(|i| glob_match(rest, &name[i..]))
+        }
+        [b'?', rest @ ..] => !name.is_empty() && name[0] != b'/' && glob_match(rest, &name[1..]),
+        [c, rest @ ..] => name.first() == Some(c) && glob_match(rest, &name[1..]),
+    }
+}
+
+/// A data alignment requirement for the files selected by a matcher.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AlignmentRule {
+    /// Which files the rule applies to.
+    #[serde(flatten)]
+    pub matcher:   AlignmentMatch,
+    /// Data alignment (must be a power of 2).
+    pub alignment: usize,
+}
+
+/// A set of SARC alignment rules which can be loaded at runtime, for games
+/// whose alignment requirements are not covered by the built-in BOTW tables.
+///
+/// Rule sets can be deserialized from JSON, for example:
+/// ```json
+/// {
+///   "replace_defaults": false,
+///   "rules": [
+///     { "extension": "bfres", "alignment": 4096 },
+///     { "magic": "BNTX", "alignment": 4096 },
+///     { "glob": "Shader/**.bfsha", "alignment": 8192 }
+///   ]
+/// }
+/// ```
+///
+/// The alignment for a file is the least common multiple of the alignments
+/// of all matching rules and the writer's other requirements. If
+/// `replace_defaults` is set, the built-in BOTW tables and the heuristics
+/// which detect alignment from file headers are not used; only the minimum
+/// alignment, requirements added with
+/// [`SarcWriter::add_alignment_requirement`](super::SarcWriter::add_alignment_requirement),
+/// and the rules apply.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AlignmentRules {
+    /// Whether the rules replace the default alignment tables and heuristics
+    /// instead of adding to them.
+    #[serde(default)]
+    pub replace_defaults: bool,
+    /// The alignment rules.
+    pub rules: Vec<AlignmentRule>,
+}
+
+impl AlignmentRules {
+    /// Create an empty rule set which adds to the default requirements.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Parse a rule set from JSON. Returns an error if the JSON is invalid or
+    /// any alignment is not a power of 2.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let rules: Self =
+            serde_json::from_str(text).map_err(|e| Error::InvalidDataD(e.to_string()))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Load a rule set from a JSON file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Serialize the rule set to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Alignment rules should serialize to JSON")
+    }
+
+    /// Check that every alignment in the rule set is a power of 2.
+    pub fn validate(&self) -> Result<()> {
+        match self
+            .rules
+            .iter()
+            .find(|rule| !is_valid_alignment(rule.alignment))
+        {
+            Some(rule) => {
+                Err(Error::InvalidDataD(format!(
+                    "Invalid alignment {} in SARC alignment rule",
+                    rule.alignment
+                )))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Add a rule.
+    ///
+    /// Panics if an invalid alignment is provided.
+    pub fn add_rule(&mut self, matcher: AlignmentMatch, alignment: usize) {
+        if !is_valid_alignment(alignment) {
+            panic!("Invalid alignment requirement");
+        }
+        self.rules.push(AlignmentRule { matcher, alignment });
+    }
+
+    /// Builder-style method to add a rule.
+    #[inline]
+    pub fn with_rule(mut self, matcher: AlignmentMatch, alignment: usize) -> Self {
+        self.add_rule(matcher, alignment);
+        self
+    }
+
+    /// Set whether the rules replace the default alignment tables and
+    /// heuristics.
+    #[inline]
+    pub fn set_replace_defaults(&mut self, value: bool) {
+        self.replace_defaults = value;
+    }
+
+    /// Builder-style method to set whether the rules replace the default
+    /// alignment tables and heuristics.
+    #[inline]
+    pub fn with_replace_defaults(mut self, value: bool) -> Self {
+        self.set_replace_defaults(value);
+        self
+    }
+
+    /// Iterate over the alignments of all rules matching a file.
+    pub fn matching<'a>(
+        &'a self,
+        name: &'a str,
+        data: &'a [u8],
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.rules
+            .iter()
+            .filter(move |rule| rule.matcher.matches(name, data))
+            .map(|rule| rule.alignment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment_rules() {
+        let rules = AlignmentRules::from_json(
+            r#"{
+                "rules": [
+                    { "extension": "Tex1.bfres", "alignment": 4096 },
+                    { "magic": "BNTX", "alignment": 8192 },
+                    { "glob": "Shader/**.bfsha", "alignment": 256 },
+                    { "glob": "Model/*.bin", "alignment": 16 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(!rules.replace_defaults);
+        assert_eq!(
+            rules
+                .matching("Model/A.Tex1.bfres", b"FRES")
+                .collect::<Vec<_>>(),
+            vec![4096]
+        );
+        assert_eq!(rules.matching("Model/A.bfres", b"FRES").count(), 0);
+        assert_eq!(
+            rules.matching("Tex/A.bntx", b"BNTX").collect::<Vec<_>>(),
+            vec![8192]
+        );
+        assert_eq!(
+            rules.matching("Shader/A/B.bfsha", b"").collect::<Vec<_>>(),
+            vec![256]
+        );
+        assert_eq!(rules.matching("Model/A.bin", b"").count(), 1);
+        assert_eq!(rules.matching("Model/A/B.bin", b"").count(), 0);
+        assert_eq!(AlignmentRules::from_json(&rules.to_json()).unwrap(), rules);
+        assert!(
+            AlignmentRules::from_json(r#"{"rules": [{"magic": "A", "alignment": 3}]}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn write_with_rules() {
+        let rules = AlignmentRules::new()
+            .with_rule(AlignmentMatch::Glob("Custom/**".into()), 0x400)
+            .with_replace_defaults(true);
+        let data = crate::sarc::SarcWriter::new(crate::Endian::Little)
+            .with_alignment_rules(rules)
+            .with_file("A.txt", b"abcd".to_vec())
+            .with_file("Custom/B.txt", b"efgh".to_vec())
+            .to_binary();
+        let sarc = crate::sarc::Sarc::new(&data).unwrap();
+        let offset =
+            sarc.get_data("Custom/B.txt").unwrap().as_ptr() as usize - data.as_ptr() as usize;
+        assert_eq!(offset % 0x400, 0);
+    }
+}
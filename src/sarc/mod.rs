@@ -41,8 +41,10 @@
 //! # Ok(())
 //! # }
 //! ```
+mod align;
 mod parse;
 mod write;
+pub use align::{AlignmentMatch, AlignmentRule, AlignmentRules};
 use binrw::{binrw, BinRead, BinWrite};
 pub use parse::Sarc;
 pub use write::SarcWriter;
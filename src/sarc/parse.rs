@@ -3,6 +3,7 @@ use std::{
     borrow::Cow,
     hash::{Hash, Hasher},
     io::Cursor,
+    path::Path,
 };
 
 use binrw::{BinRead, BinReaderExt};
@@ -322,6 +323,56 @@ impl<'a> Sarc<'_> {
         }
     }
 
+    /// Returns a parallel iterator over the contained files. Unlike
+    /// [`Sarc::files`], each file is a [`Result`], so a file whose entry
+    /// cannot be read is reported rather than ending the iteration.
+    ///
+    /// This is only available with the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    pub fn par_files(&self) -> impl rayon::iter::IndexedParallelIterator<Item = Result<File<'_>>> {
+        use rayon::prelude::*;
+        (0..self.len()).into_par_iter().map(|i| self.file_at(i))
+    }
+
+    /// Extract all named files in the archive to a folder, creating any
+    /// subfolders as needed. Files without a name are skipped. Returns an
+    /// error if a file name would escape the destination folder.
+    ///
+    /// **Note**: If and only if the `rayon` feature is enabled, files are
+    /// written in parallel.
+    pub fn extract_to(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        let extract = |file: File| -> Result<()> {
+            let Some(name) = file.name else {
+                return Ok(());
+            };
+            let rel = Path::new(name);
+            if !rel
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+            {
+                return Err(Error::InvalidDataD(jstr!(
+                    "Unsafe file name in SARC: {name}"
+                )));
+            }
+            let path = dir.join(rel);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, file.data)?;
+            Ok(())
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            self.par_files().try_for_each(|file| extract(file?))
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            (0..self.len()).try_for_each(|i| extract(self.file_at(i)?))
+        }
+    }
+
     /// Guess the minimum data alignment for files that are stored in the
     /// archive
     pub fn guess_min_alignment(&self) -> usize {
@@ -383,4 +434,30 @@ mod tests {
                 .unwrap_or_else(|| panic!("Could not find file {}", file));
         }
     }
+
+    #[test]
+    fn extract_sarc() {
+        let data = read("test/sarc/test.sarc").unwrap();
+        let sarc = Sarc::new(&data).unwrap();
+        let dir = std::env::temp_dir().join(format!("roead_extract_sarc_{}", std::process::id()));
+        sarc.extract_to(&dir).unwrap();
+        for file in sarc.files() {
+            let name = file.name().unwrap();
+            assert_eq!(read(dir.join(name)).unwrap(), file.data());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_files() {
+        use rayon::prelude::*;
+        let data = read("test/sarc/test.sarc").unwrap();
+        let sarc = Sarc::new(&data).unwrap();
+        let mut names: Vec<_> = sarc.par_files().filter_map(|f| f.unwrap().name).collect();
+        names.sort_unstable();
+        let mut expected: Vec<_> = sarc.files().filter_map(|f| f.name).collect();
+        expected.sort_unstable();
+        assert_eq!(names, expected);
+    }
 }
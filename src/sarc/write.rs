@@ -13,7 +13,10 @@ use rustc_hash::{FxHashMap, FxHashSet};
 use serde::Deserialize;
 
 use super::*;
-use crate::{Endian, Result};
+use crate::{
+    profile::{Compression, Profile},
+    Endian, Result,
+};
 
 static FACTORY_INFO: &str = include_str!("../../data/botw_resource_factory_info.tsv");
 static AGLENV_INFO: &str = include_str!("../../data/aglenv_file_info.json");
@@ -59,6 +62,28 @@ struct AglEnvInfo {
     desc: String,
 }
 
+/// Compress a file with the given codec if its name has a Yaz0-associated
+/// extension and it is not already compressed.
+#[cfg(feature = "yaz0")]
+fn compress_file(compression: Compression, name: &str, data: &[u8]) -> Option<Vec<u8>> {
+    match compression {
+        Compression::Yaz0 => (!data.starts_with(b"Yaz0") && crate::yaz0::is_compressed_ext(name))
+            .then(|| crate::yaz0::compress(data)),
+        Compression::None | Compression::Zstd => None,
+    }
+}
+
+/// Check that roead can compress files with a codec.
+#[cfg(feature = "yaz0")]
+fn check_compression(compression: Compression) -> Result<()> {
+    match compression {
+        Compression::Zstd => {
+            Err(crate::Error::InvalidData("Zstandard compression is not supported"))
+        }
+        Compression::None | Compression::Yaz0 => Ok(()),
+    }
+}
+
 #[inline(always)]
 fn align(pos: usize, alignment: usize) -> usize {
     let pos = pos as i64;
@@ -82,9 +107,12 @@ fn get_agl_env_alignment_requirements() -> &'static Vec<(String, usize)> {
 pub struct SarcWriter {
     pub endian: Endian,
     legacy: bool,
+    botw_alignments: bool,
     hash_multiplier: u32,
     min_alignment: usize,
     alignment_map: FxHashMap<String, usize>,
+    alignment_rules: AlignmentRules,
+    compression: Compression,
     brw_endian: binrw::Endian,
     /// Files to be written.
     pub files: IndexMap<String, Vec<u8>>,
@@ -95,9 +123,12 @@ impl std::fmt::Debug for SarcWriter {
         f.debug_struct("SarcWriter")
             .field("endian", &self.endian)
             .field("legacy", &self.legacy)
+            .field("botw_alignments", &self.botw_alignments)
             .field("hash_multiplier", &self.hash_multiplier)
             .field("min_alignment", &self.min_alignment)
             .field("alignment_map", &self.alignment_map)
+            .field("alignment_rules", &self.alignment_rules)
+            .field("compression", &self.compression)
             .field("files", &self.files.keys().collect::<Vec<_>>())
             .finish()
     }
@@ -107,9 +138,12 @@ impl PartialEq for SarcWriter {
     fn eq(&self, other: &Self) -> bool {
         self.endian == other.endian
             && self.legacy == other.legacy
+            && self.botw_alignments == other.botw_alignments
             && self.hash_multiplier == other.hash_multiplier
             && self.min_alignment == other.min_alignment
             && self.alignment_map == other.alignment_map
+            && self.alignment_rules == other.alignment_rules
+            && self.compression == other.compression
             && self.files == other.files
     }
 }
@@ -122,8 +156,11 @@ impl SarcWriter {
         SarcWriter {
             endian,
             legacy: false,
+            botw_alignments: true,
             hash_multiplier: HASH_MULTIPLIER,
             alignment_map: FxHashMap::default(),
+            alignment_rules: AlignmentRules::default(),
+            compression: Compression::Yaz0,
             files: IndexMap::new(),
             brw_endian: match endian {
                 Endian::Big => binrw::Endian::Big,
@@ -140,8 +177,11 @@ impl SarcWriter {
         SarcWriter {
             endian,
             legacy: false,
+            botw_alignments: true,
             hash_multiplier: HASH_MULTIPLIER,
             alignment_map: FxHashMap::default(),
+            alignment_rules: AlignmentRules::default(),
+            compression: Compression::Yaz0,
             files: sarc
                 .files()
                 .filter_map(|f| f.name.map(|name| (name.to_string(), f.data.to_vec())))
@@ -172,6 +212,81 @@ impl SarcWriter {
         buf
     }
 
+    /// Compress every file whose name has a Yaz0-associated extension (see
+    /// [`crate::yaz0::compress_if`]) with the writer's compression codec
+    /// (see [`SarcWriter::set_compression`]). Files which are already
+    /// compressed are left as they are.
+    ///
+    /// Returns an error if the codec is not supported by roead.
+    ///
+    /// **Note**: If and only if the `rayon` feature is enabled, files are
+    /// compressed in parallel.
+    #[cfg(feature = "yaz0")]
+    pub fn compress_files(&mut self) -> Result<()> {
+        check_compression(self.compression)?;
+        let compression = self.compression;
+        let compress = |(name, data): (&String, &mut Vec<u8>)| {
+            if let Some(compressed) = compress_file(compression, name, data) {
+                *data = compressed;
+            }
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            self.files
+                .iter_mut()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .for_each(compress);
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.files.iter_mut().for_each(compress);
+        }
+        Ok(())
+    }
+
+    /// Write the SARC archive to an in-memory buffer with its files
+    /// compressed as by [`SarcWriter::compress_files`]. The files in the
+    /// writer itself are left uncompressed.
+    ///
+    /// Returns an error if the writer's compression codec is not supported
+    /// by roead.
+    #[cfg(feature = "yaz0")]
+    pub fn to_binary_compressed(&self) -> Result<Vec<u8>> {
+        check_compression(self.compression)?;
+        let compression = self.compression;
+        let compress = |(name, data): (&String, &Vec<u8>)| {
+            let data = compress_file(compression, name, data).unwrap_or_else(|| data.clone());
+            (name.clone(), data)
+        };
+        #[cfg(feature = "rayon")]
+        let files = {
+            use rayon::prelude::*;
+            self.files
+                .iter()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(compress)
+                .collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let files = self.files.iter().map(compress).collect::<Vec<_>>();
+        let mut writer = SarcWriter {
+            endian: self.endian,
+            legacy: self.legacy,
+            botw_alignments: self.botw_alignments,
+            hash_multiplier: self.hash_multiplier,
+            min_alignment: self.min_alignment,
+            alignment_map: self.alignment_map.clone(),
+            alignment_rules: self.alignment_rules.clone(),
+            compression: self.compression,
+            brw_endian: self.brw_endian,
+            files: files.into_iter().collect(),
+        };
+        Ok(writer.to_binary())
+    }
+
     /// Write a SARC archive to a Write + Seek writer using the specified
     /// endianness. Default alignment requirements may be automatically
     /// added.
@@ -187,7 +302,9 @@ impl SarcWriter {
         self.files.sort_unstable_by(|ka, _, kb, _| {
             hash_name(HASH_MULTIPLIER, ka).cmp(&hash_name(HASH_MULTIPLIER, kb))
         });
-        self.add_default_alignments();
+        if self.botw_alignments && !self.alignment_rules.replace_defaults {
+            self.add_default_alignments();
+        }
         let mut alignments: Vec<usize> = Vec::with_capacity(self.files.len());
 
         {
@@ -298,6 +415,27 @@ impl SarcWriter {
         });
     }
 
+    /// Set a runtime alignment rule set, replacing any previous one. See
+    /// [`AlignmentRules`] for how the rules combine with the default
+    /// requirements.
+    #[inline]
+    pub fn set_alignment_rules(&mut self, rules: AlignmentRules) {
+        self.alignment_rules = rules;
+    }
+
+    /// Builder-style method to set a runtime alignment rule set
+    #[inline]
+    pub fn with_alignment_rules(mut self, rules: AlignmentRules) -> Self {
+        self.set_alignment_rules(rules);
+        self
+    }
+
+    /// Get the current runtime alignment rule set.
+    #[inline]
+    pub fn alignment_rules(&self) -> &AlignmentRules {
+        &self.alignment_rules
+    }
+
     /// Set the minimum data alignment.
     ///
     /// Panics if an invalid alignment is provided. If you're not passing an
@@ -333,10 +471,89 @@ impl SarcWriter {
         self
     }
 
+    /// Set whether to add BOTW's default alignment requirements (from the
+    /// resource factory and AGL environment tables) when writing. Enabled by
+    /// default.
+    #[inline]
+    pub fn set_botw_alignments(&mut self, value: bool) {
+        self.botw_alignments = value
+    }
+
+    /// Builder-style method to set whether to add BOTW's default alignment
+    /// requirements when writing
+    #[inline]
+    pub fn with_botw_alignments(mut self, value: bool) -> Self {
+        self.set_botw_alignments(value);
+        self
+    }
+
+    /// Set the codec used by [`SarcWriter::compress_files`] and
+    /// [`SarcWriter::to_binary_compressed`]. Yaz0 by default.
+    #[inline]
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression
+    }
+
+    /// Builder-style method to set the compression codec
+    #[inline]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.set_compression(compression);
+        self
+    }
+
+    /// Get the compression codec.
+    #[inline]
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Apply the SARC settings of a game profile (endianness, minimum
+    /// alignment, alignment tables, legacy mode and compression codec).
+    ///
+    /// Returns an error, leaving the writer unchanged, if the profile's
+    /// minimum alignment or any of its alignment requirements is not a power
+    /// of 2.
+    pub fn set_profile(&mut self, profile: &Profile) -> Result<()> {
+        if !is_valid_alignment(profile.sarc_min_alignment) {
+            return Err(crate::Error::InvalidData(
+                "Invalid minimum SARC file alignment",
+            ));
+        }
+        if let Some((ext, _)) = profile
+            .sarc_alignments
+            .iter()
+            .find(|(_, alignment)| !is_valid_alignment(*alignment))
+        {
+            return Err(crate::Error::InvalidDataD(format!(
+                "Invalid alignment requirement for {ext}"
+            )));
+        }
+        self.set_endian(profile.endian);
+        self.set_compression(profile.compression);
+        self.set_min_alignment(profile.sarc_min_alignment);
+        self.set_legacy_mode(profile.sarc_legacy);
+        self.set_botw_alignments(profile.sarc_botw_alignments);
+        for (ext, alignment) in &profile.sarc_alignments {
+            self.add_alignment_requirement(ext.clone(), *alignment);
+        }
+        Ok(())
+    }
+
+    /// Builder-style method to apply the SARC settings of a game profile
+    #[inline]
+    pub fn with_profile(mut self, profile: &Profile) -> Result<Self> {
+        self.set_profile(profile)?;
+        Ok(self)
+    }
+
     /// Set the endianness
     #[inline]
     pub fn set_endian(&mut self, endian: Endian) {
-        self.endian = endian
+        self.endian = endian;
+        self.brw_endian = match endian {
+            Endian::Big => binrw::Endian::Big,
+            Endian::Little => binrw::Endian::Little,
+        };
     }
 
     /// Builder-style method to set the endianness
@@ -394,10 +611,16 @@ impl SarcWriter {
         if let Some(requirement) = self.alignment_map.get(ext) {
             alignment = alignment.lcm(requirement);
         }
+        for requirement in self.alignment_rules.matching(name, data) {
+            alignment = alignment.lcm(&requirement);
+        }
+        if self.alignment_rules.replace_defaults {
+            return alignment;
+        }
         if self.legacy && Self::is_file_sarc(data) {
             alignment = alignment.lcm(&0x2000);
         }
-        if self.legacy || !get_botw_factory_names().contains(ext) {
+        if self.legacy || !self.botw_alignments || !get_botw_factory_names().contains(ext) {
             alignment = alignment.lcm(&Self::get_alignment_for_new_binary_file(data));
             if let Endian::Big = self.endian {
                 alignment = alignment.lcm(&Self::get_alignment_for_cafe_bflim(data));
@@ -512,4 +735,47 @@ mod tests {
             }
         }
     }
+
+    #[cfg(feature = "yaz0")]
+    #[test]
+    fn compress_files() {
+        let data = b"Compressible test data. ".repeat(64);
+        let mut writer = SarcWriter::new(crate::Endian::Little)
+            .with_file("Actor/ActorLink/Test.bxml", data.clone())
+            .with_file("Actor/Pack/Test.sbactorpack", data.clone())
+            .with_file("Pack/Test.sarc", data.clone())
+            .with_file("Model/Test.sbfres", crate::yaz0::compress(&data));
+        let binary = writer.to_binary_compressed().unwrap();
+        assert_eq!(writer.get_file("Actor/Pack/Test.sbactorpack"), Some(&data));
+
+        let sarc = Sarc::new(&binary).unwrap();
+        assert_eq!(sarc.get_data("Actor/ActorLink/Test.bxml"), Some(data.as_slice()));
+        assert_eq!(sarc.get_data("Pack/Test.sarc"), Some(data.as_slice()));
+        for name in ["Actor/Pack/Test.sbactorpack", "Model/Test.sbfres"] {
+            let file = sarc.get_data(name).unwrap();
+            assert!(file.starts_with(b"Yaz0"));
+            assert_eq!(crate::yaz0::decompress(file).unwrap(), data);
+        }
+
+        writer.compress_files().unwrap();
+        assert_eq!(
+            writer.get_file("Model/Test.sbfres"),
+            Some(&crate::yaz0::compress(&data))
+        );
+        assert_eq!(writer.to_binary(), binary);
+
+        writer.set_compression(crate::profile::Compression::None);
+        let binary = writer.to_binary_compressed().unwrap();
+        let sarc = Sarc::new(&binary).unwrap();
+        assert_eq!(sarc.get_data("Actor/Pack/Test.sbactorpack"), Some(data.as_slice()));
+
+        let mut writer = crate::profile::Profile::TOTK
+            .sarc_writer()
+            .unwrap()
+            .with_file("Test.bfres", data.clone())
+            .with_compression(crate::profile::Compression::Zstd);
+        assert!(writer.to_binary_compressed().is_err());
+        assert!(writer.compress_files().is_err());
+        assert_eq!(writer.get_file("Test.bfres"), Some(&data));
+    }
 }
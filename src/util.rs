@@ -3,6 +3,28 @@ pub(crate) fn align(value: u32, size: u32) -> u32 {
     value + (size - value % size) % size
 }
 
+/// Standard CRC32 (as used by BOTW for names and resource paths).
+#[cfg(any(feature = "aamp", feature = "byml", feature = "rstb"))]
+#[inline]
+pub(crate) const fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFF;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i] as u32;
+        let mut j = 0;
+        while j < 8 {
+            if crc & 1 == 1 {
+                crc = (crc >> 1) ^ 0xEDB88320;
+            } else {
+                crc >>= 1;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    !crc
+}
+
 pub(crate) trait SeekShim {
     fn stream_len(&mut self) -> std::io::Result<u64>
     where
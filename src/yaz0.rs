@@ -149,19 +149,24 @@ pub fn compress_with_options(data: impl AsRef<[u8]>, options: CompressOptions) -
 /// compressed, or containing the compressed data otherwise.
 #[inline]
 pub fn compress_if(data: &[u8], path: impl AsRef<std::path::Path>) -> Cow<'_, [u8]> {
-    if path
-        .as_ref()
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.starts_with('s') && e != "sarc")
-        .unwrap_or(false)
-    {
+    if is_compressed_ext(path) {
         Cow::Owned(compress(data))
     } else {
         Cow::Borrowed(data)
     }
 }
 
+/// Check if a path has a Yaz0-associated file extension (starts with `s`, but
+/// does not equal `sarc`).
+#[inline]
+pub(crate) fn is_compressed_ext(path: impl AsRef<std::path::Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.starts_with('s') && e != "sarc")
+        .unwrap_or(false)
+}
+
 #[cxx::bridge(namespace = "oead::yaz0")]
 mod ffi {
     unsafe extern "C++" {